@@ -166,6 +166,87 @@ def print_joining_table(f):
     print()
 
 
+def print_shaping_table(f):
+    shapes = {}
+    ligatures = {}
+    names = {}
+    for line in f:
+        fields = [x.strip() for x in line.split(";")]
+        if fields[5][0:1] != "<":
+            continue
+
+        items = fields[5].split(" ")
+        shape, items = items[0][1:-1], tuple(int(x, 16) for x in items[1:])
+
+        if shape not in ["initial", "medial", "isolated", "final"]:
+            continue
+
+        c = int(fields[0], 16)
+        if len(items) != 1:
+            # We only care about lam-alef ligatures.
+            if len(items) != 2 or items[0] != 0x0644 or items[1] not in [0x0622, 0x0623, 0x0625, 0x0627]:
+                continue
+
+            # Save ligature.
+            names[c] = fields[1]
+            if items not in ligatures:
+                ligatures[items] = {}
+            ligatures[items][shape] = c
+        else:
+            # Save shape.
+            if items[0] not in names:
+                names[items[0]] = fields[1]
+            else:
+                names[items[0]] = os.path.commonprefix([names[items[0]], fields[1]]).strip()
+            if items[0] not in shapes:
+                shapes[items[0]] = {}
+            shapes[items[0]][shape] = c
+
+    keys = shapes.keys()
+    min_u, max_u = min(keys), max(keys)
+
+    print("pub const SHAPING_TABLE_FIRST: u32 = 0x%04X;" % min_u)
+    print("pub const SHAPING_TABLE_LAST: u32 = 0x%04X;" % max_u)
+    print()
+    print("// Presentation forms in the initial, medial, final and isolated order.")
+    print("#[rustfmt::skip]")
+    print("pub const SHAPING_TABLE: &[[u16; 4]] = &[")
+    for u in range(min_u, max_u + 1):
+        s = [
+            shapes[u][shape] if u in shapes and shape in shapes[u] else 0
+            for shape in ["initial", "medial", "final", "isolated"]
+        ]
+        value = ", ".join("0x%04X" % c for c in s)
+        print(("    [%s], // U+%04X %s" % (value, u, names.get(u, ""))).rstrip())
+    print("];")
+    print()
+
+    ligas = {}
+    for pair in ligatures.keys():
+        for shape in ligatures[pair]:
+            c = ligatures[pair][shape]
+            if shape == "isolated":
+                liga = (shapes[pair[0]]["initial"], shapes[pair[1]]["final"])
+            elif shape == "final":
+                liga = (shapes[pair[0]]["medial"], shapes[pair[1]]["final"])
+            else:
+                raise Exception("Unexpected shape", shape)
+            if liga[0] not in ligas:
+                ligas[liga[0]] = []
+            ligas[liga[0]].append((liga[1], c))
+
+    print("// Lam-alef ligatures, keyed by the lam form and then by the alef form.")
+    print("#[rustfmt::skip]")
+    print("pub const LIGATURE_TABLE: &[(u16, &[(u16, u16)])] = &[")
+    for first in sorted(ligas.keys()):
+        print("    (0x%04X, &[" % first)
+        for second, lig in sorted(ligas[first]):
+            print("        (0x%04X, 0x%04X), // %s" % (second, lig, names[lig]))
+        print("    ]),")
+    print("];")
+    print()
+
+
 print("// WARNING: this file was generated by scripts/gen-arabic-table.py")
 print()
 print(
@@ -177,3 +258,4 @@ print()
 
 read_blocks(files[2])
 print_joining_table(files[0])
+print_shaping_table(files[1])
@@ -836,6 +836,7 @@ disambiguation = {
     'HAL': 'cfm',
     'HND': 'hnd',
     'HYE': 'hyw',
+    'IRT': 'ga-Latg',
     'KIS': 'kqs',
     'LRC': 'bqi',
     'NDB': 'nd',
@@ -867,6 +868,64 @@ for tricky_ot_tag in filter(lambda tag: re.match('[A-Z]{3}$', tag), ot.names):
         bcp_47.macrolanguages[possible_bcp_47_tag] = set()
 ot.sort_languages()
 
+
+def runtime_language(ot_tag):
+    """Return the BCP 47 tag that ``OPEN_TYPE_LANGUAGES`` alone maps an OpenType tag to.
+
+    Two-letter language subtags are preferred, as in ``tag::tag_to_language``.
+    Tags that are only reachable through ``tags_from_complex_language`` or
+    commented out of the table are never returned.
+    """
+    candidates = sorted((t for t in ot.to_bcp_47[ot_tag]
+                         if '-' not in t and not same_tag(t, ot.from_bcp_47[t])),
+                        key=lambda t: (len(t) != 2, t))
+    return candidates[0] if candidates else None
+
+
+def verify_disambiguation_dict():
+    """Complete and verify ``disambiguation``.
+
+    Every OpenType tag with more than one primary BCP 47 tag must be
+    resolvable to a single one, either automatically or through the
+    manual ``disambiguation`` entries. Entries that merely repeat what
+    a lookup in ``OPEN_TYPE_LANGUAGES`` would return are dropped.
+    """
+    for ot_tag, bcp_47_tags in ot.to_bcp_47.items():
+        if ot_tag == DEFAULT_LANGUAGE_SYSTEM:
+            continue
+        primary_tags = [t for t in bcp_47_tags
+                        if t not in bcp_47.grandfathered and ot.from_bcp_47.get(t)[0] == ot_tag]
+        if len(primary_tags) == 1:
+            expect(ot_tag not in disambiguation, 'unnecessary disambiguation for OT tag: %s' % ot_tag)
+            disambiguation[ot_tag] = primary_tags[0]
+        elif len(primary_tags) == 0:
+            expect(ot_tag not in disambiguation, 'there is no possible valid disambiguation for %s' % ot_tag)
+            continue
+        elif ot_tag not in disambiguation:
+            original_languages = [t for t in primary_tags
+                                  if t in ot.from_bcp_47_uninherited
+                                  and 'retired code' not in bcp_47.scopes.get(t, '')]
+            if len(original_languages) == 1:
+                macrolanguages = original_languages
+            else:
+                macrolanguages = [t for t in primary_tags if bcp_47.scopes.get(t) == ' [macrolanguage]']
+            if len(macrolanguages) != 1:
+                macrolanguages = [t for t in primary_tags if bcp_47.scopes.get(t) == ' [collection]']
+            if len(macrolanguages) != 1:
+                expect(runtime_language(ot_tag) in primary_tags,
+                       'ambiguous OT tag: %s %s' % (ot_tag, sorted(primary_tags)))
+                macrolanguages = [runtime_language(ot_tag)]
+            disambiguation[ot_tag] = macrolanguages[0]
+        expect(disambiguation[ot_tag] in bcp_47_tags,
+               '%s is not a valid disambiguation for %s' % (disambiguation[ot_tag], ot_tag))
+        if disambiguation[ot_tag] == runtime_language(ot_tag):
+            del disambiguation[ot_tag]
+    for ot_tag in disambiguation.keys():
+        expect(ot_tag in ot.to_bcp_47, 'unknown OT tag: %s' % ot_tag)
+
+
+verify_disambiguation_dict()
+
 print('// WARNING: this file was generated by ../scripts/gen-tag-table.py')
 print()
 print('use ttf_parser::Tag;')
@@ -1082,3 +1141,20 @@ print('        _ => {}')
 print('    }')
 print('    false')
 print('}')
+print()
+print('/// Returns the preferred BCP 47 language tag for an OpenType language system tag')
+print('/// that a lookup in `OPEN_TYPE_LANGUAGES` would not resolve as well.')
+print('#[rustfmt::skip]')
+print('pub fn ambiguous_tag_to_language(tag: Tag) -> Option<&\'static str> {')
+print('    match &tag.to_bytes() {')
+for ot_tag, bcp_47_tag in sorted(disambiguation.items()):
+    ot_name = ot.names[ot_tag]
+    bcp_47_name = bcp_47.get_name(LanguageTag(bcp_47_tag))
+    print('        %s => Some("%s"), // %s' % (hb_tag2(ot_tag), bcp_47_tag, ot_name), end='')
+    if language_name_intersection(ot_name, bcp_47_name):
+        print()
+    else:
+        print(' -> %s' % bcp_47_name)
+print('        _ => None,')
+print('    }')
+print('}')
@@ -3,9 +3,13 @@
 use super::buffer::{hb_buffer_t, hb_glyph_info_t};
 use super::hb_tag_t;
 use super::ot_shape_plan::hb_ot_shape_plan_t;
-use super::{aat_layout_kerx_table, aat_layout_morx_table, aat_layout_trak_table};
+use super::{
+    aat_layout_kerx_table, aat_layout_mort_table, aat_layout_morx_table, aat_layout_trak_table,
+};
 use super::{aat_map, hb_font_t};
 use crate::hb::aat_layout_common::hb_aat_apply_context_t;
+use alloc::vec::Vec;
+use ttf_parser::feat;
 
 pub type hb_aat_layout_feature_type_t = u8;
 pub const HB_AAT_LAYOUT_FEATURE_TYPE_INVALID: u8 = 0xFF;
@@ -491,6 +495,97 @@ mod AAT {
     pub const DELETED_GLYPH: u32 = 0xFFFF;
 }
 
+/// An AAT feature type from the `feat` table.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct hb_aat_layout_feature_type_info_t {
+    /// The feature type, like 1 for ligatures.
+    pub kind: u16,
+    /// The `name` table ID of the feature type name.
+    pub name_id: u16,
+    /// Whether only one selector can be enabled at a time.
+    pub is_exclusive: bool,
+    /// The index of the default selector. Only exclusive feature types have one.
+    pub default_index: Option<usize>,
+    /// The feature selectors.
+    pub selectors: Vec<hb_aat_layout_feature_selector_info_t>,
+}
+
+/// An AAT feature selector.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct hb_aat_layout_feature_selector_info_t {
+    /// The `name` table ID of the selector name.
+    pub name_id: u16,
+    /// The selector that enables the setting.
+    pub enable: u16,
+    /// The selector that disables the setting.
+    ///
+    /// This is the default selector for exclusive feature types.
+    pub disable: u16,
+}
+
+impl hb_aat_layout_feature_type_info_t {
+    // FeatureName::get_selector_infos in harfbuzz
+    fn new(name: feat::FeatureName) -> Self {
+        let mut default_index = None;
+        let mut default_selector = None;
+        if name.exclusive {
+            let index = usize::from(name.default_setting_index);
+            default_index = Some(index);
+            default_selector = name
+                .setting_names
+                .get(u16::from(name.default_setting_index))
+                .map(|setting| setting.setting);
+        }
+
+        let selectors = name
+            .setting_names
+            .into_iter()
+            .map(|setting| hb_aat_layout_feature_selector_info_t {
+                name_id: setting.name_index,
+                enable: setting.setting,
+                disable: default_selector.unwrap_or(setting.setting.wrapping_add(1)),
+            })
+            .collect();
+
+        hb_aat_layout_feature_type_info_t {
+            kind: name.feature,
+            name_id: name.name_index,
+            is_exclusive: name.exclusive,
+            default_index,
+            selectors,
+        }
+    }
+}
+
+impl<'a> hb_font_t<'a> {
+    /// Returns the AAT feature types from the `feat` table.
+    ///
+    /// Name IDs can be resolved via [`ttf_parser::Face::names`].
+    /// Use [`Feature::aat`](crate::Feature::aat) to request a selector when shaping.
+    pub fn aat_feature_types(&self) -> Vec<hb_aat_layout_feature_type_info_t> {
+        self.tables()
+            .feat
+            .map(|feat| {
+                feat.names
+                    .into_iter()
+                    .map(hb_aat_layout_feature_type_info_t::new)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns an AAT feature type from the `feat` table.
+    pub fn aat_feature_type(&self, kind: u16) -> Option<hb_aat_layout_feature_type_info_t> {
+        let name = self.tables().feat?.names.find(kind)?;
+        Some(hb_aat_layout_feature_type_info_t::new(name))
+    }
+}
+
+/// Checks whether the face has a `morx` or a legacy `mort` table.
+pub fn hb_aat_layout_has_substitution(face: &hb_font_t) -> bool {
+    face.tables().morx.is_some() || aat_layout_mort_table::table_data(face).is_some()
+}
+
 pub fn hb_aat_layout_substitute(
     plan: &hb_ot_shape_plan_t,
     face: &hb_font_t,
@@ -508,7 +603,11 @@ pub fn hb_aat_layout_substitute(
     }
 
     let mut c = hb_aat_apply_context_t::new(face, buffer);
-    aat_layout_morx_table::apply(&mut c, &mut aat_map);
+    if face.tables().morx.is_some() {
+        aat_layout_morx_table::apply(&mut c, &mut aat_map);
+    } else {
+        aat_layout_mort_table::apply(&mut c, &mut aat_map);
+    }
 }
 
 pub fn hb_aat_layout_zero_width_deleted_glyphs(buffer: &mut hb_buffer_t) {
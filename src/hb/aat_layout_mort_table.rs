@@ -0,0 +1,370 @@
+use super::aat_layout_morx_table::{
+    apply_contextual, apply_insertion, apply_ligature, apply_noncontextual, apply_rearrangement,
+    chain_flags_for, ContextualSubstitutions, InsertionGlyphs, LigatureTables, StateMachine,
+};
+use super::aat_map::{hb_aat_map_builder_t, hb_aat_map_t, range_flags_t};
+use super::algs::read_at;
+use super::hb_font_t;
+use crate::hb::aat_layout_common::hb_aat_apply_context_t;
+use alloc::vec;
+use core::num::NonZeroU16;
+use ttf_parser::apple_layout::{self, GenericStateEntry};
+use ttf_parser::{morx, FromData, GlyphId, LazyArray16, Tag};
+
+// The legacy `mort` table is the predecessor of `morx`, with 16-bit state tables
+// and offset based subtable data. It is not exposed by `ttf-parser`, so it is parsed here
+// and driven by the `morx` subtable contexts, with entries converted to the `morx` form.
+//
+// https://developer.apple.com/fonts/TrueType-Reference-Manual/RM06/Chap6mort.html
+
+/// Returns the `mort` table data.
+pub fn table_data<'a>(face: &hb_font_t<'a>) -> Option<&'a [u8]> {
+    face.raw_face().table(Tag::from_bytes(b"mort"))
+}
+
+// Chain::compile_flags in harfbuzz
+pub fn compile_flags(
+    face: &hb_font_t,
+    builder: &hb_aat_map_builder_t,
+    map: &mut hb_aat_map_t,
+) -> Option<()> {
+    let data = table_data(face)?;
+    map.chain_flags.resize(chains(data).count(), vec![]);
+
+    for (chain, chain_flags) in chains(data).zip(map.chain_flags.iter_mut()) {
+        chain_flags.push(range_flags_t {
+            flags: chain_flags_for(builder, chain.default_flags, chain.features),
+            cluster_first: builder.range_first as u32,
+            cluster_last: builder.range_last as u32,
+        });
+    }
+
+    Some(())
+}
+
+// Chain::apply in harfbuzz
+pub fn apply<'a>(c: &mut hb_aat_apply_context_t<'a>, map: &'a mut hb_aat_map_t) -> Option<()> {
+    c.buffer.unsafe_to_concat(None, None);
+
+    let data = table_data(c.face)?;
+    map.chain_flags.resize(chains(data).count(), vec![]);
+
+    for (chain, chain_flags) in chains(data).zip(map.chain_flags.iter_mut()) {
+        c.range_flags = Some(chain_flags.as_mut_slice());
+        for subtable in chain.subtables {
+            if let Some(range_flags) = c.range_flags.as_ref() {
+                if range_flags.len() == 1 && (subtable.feature_flags & range_flags[0].flags == 0) {
+                    continue;
+                }
+            }
+
+            c.subtable_flags = subtable.feature_flags;
+
+            if !subtable.is_all_directions()
+                && c.buffer.direction.is_vertical() != subtable.is_vertical()
+            {
+                continue;
+            }
+
+            // Unlike `morx`, there is no logical order bit,
+            // so subtables are always processed in layout order.
+            let reverse = subtable.is_backwards() != c.buffer.direction.is_backward();
+
+            if reverse {
+                c.buffer.reverse();
+            }
+
+            apply_subtable(&subtable, c);
+
+            if reverse {
+                c.buffer.reverse();
+            }
+        }
+    }
+
+    Some(())
+}
+
+fn apply_subtable(subtable: &Subtable, ac: &mut hb_aat_apply_context_t) -> Option<()> {
+    let data = subtable.data;
+    match subtable.kind() {
+        Subtable::REARRANGEMENT => {
+            let machine = StateTable::parse(data, |entry: GenericStateEntry<()>| entry)?;
+            apply_rearrangement(&machine, ac);
+        }
+        Subtable::CONTEXTUAL => {
+            let machine = StateTable::parse(data, contextual_entry)?;
+            apply_contextual(&machine, &SubtableData(data), ac);
+        }
+        Subtable::LIGATURE => {
+            let machine = StateTable::parse(data, ligature_entry)?;
+            apply_ligature(&machine, &SubtableData(data), ac);
+        }
+        Subtable::NONCONTEXTUAL => {
+            let number_of_glyphs = NonZeroU16::new(ac.face.number_of_glyphs())?;
+            let lookup = apple_layout::Lookup::parse(number_of_glyphs, data)?;
+            apply_noncontextual(&lookup, ac);
+        }
+        Subtable::INSERTION => {
+            let machine = StateTable::parse(data, insertion_entry)?;
+            apply_insertion(&machine, &SubtableData(data), ac);
+        }
+        _ => {}
+    }
+
+    Some(())
+}
+
+struct Chain<'a> {
+    default_flags: u32,
+    features: LazyArray16<'a, morx::Feature>,
+    subtables: Subtables<'a>,
+}
+
+fn chains(data: &[u8]) -> impl Iterator<Item = Chain<'_>> {
+    // Only version 1.0 is defined.
+    let count = match read_at::<u16>(data, 0) {
+        Some(1) => read_at::<u32>(data, 4).unwrap_or(0),
+        _ => 0,
+    };
+
+    let mut offset = 8usize;
+    (0..count).map_while(move |_| {
+        let default_flags = read_at::<u32>(data, offset)?;
+        let length = usize::try_from(read_at::<u32>(data, offset + 4)?).ok()?;
+        let feature_count = read_at::<u16>(data, offset + 8)?;
+        let subtable_count = read_at::<u16>(data, offset + 10)?;
+        let chain = data.get(offset..offset.checked_add(length)?)?;
+        offset += length;
+
+        let features_end = 12 + usize::from(feature_count) * morx::Feature::SIZE;
+        Some(Chain {
+            default_flags,
+            features: LazyArray16::new(chain.get(12..features_end)?),
+            subtables: Subtables {
+                data: chain.get(features_end..)?,
+                offset: 0,
+                count: subtable_count,
+            },
+        })
+    })
+}
+
+struct Subtable<'a> {
+    coverage: u16,
+    feature_flags: u32,
+    /// Subtable data past the header, which offsets are relative to.
+    data: &'a [u8],
+}
+
+impl Subtable<'_> {
+    const REARRANGEMENT: u16 = 0;
+    const CONTEXTUAL: u16 = 1;
+    const LIGATURE: u16 = 2;
+    const NONCONTEXTUAL: u16 = 4;
+    const INSERTION: u16 = 5;
+
+    const HEADER_SIZE: usize = 8;
+
+    fn kind(&self) -> u16 {
+        self.coverage & 0x0007
+    }
+
+    fn is_vertical(&self) -> bool {
+        self.coverage & 0x8000 != 0
+    }
+
+    fn is_backwards(&self) -> bool {
+        self.coverage & 0x4000 != 0
+    }
+
+    fn is_all_directions(&self) -> bool {
+        self.coverage & 0x2000 != 0
+    }
+}
+
+struct Subtables<'a> {
+    data: &'a [u8],
+    offset: usize,
+    count: u16,
+}
+
+impl<'a> Iterator for Subtables<'a> {
+    type Item = Subtable<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.count == 0 {
+            return None;
+        }
+
+        self.count -= 1;
+
+        let length = usize::from(read_at::<u16>(self.data, self.offset)?);
+        let coverage = read_at::<u16>(self.data, self.offset + 2)?;
+        let feature_flags = read_at::<u32>(self.data, self.offset + 4)?;
+        let data = self
+            .data
+            .get(self.offset + Subtable::HEADER_SIZE..self.offset.checked_add(length)?)?;
+        self.offset += length;
+
+        Some(Subtable {
+            coverage,
+            feature_flags,
+            data,
+        })
+    }
+}
+
+/// A 16-bit state table, with entries converted to the `morx` form.
+struct StateTable<'a, R: FromData, T: FromData> {
+    number_of_classes: u16,
+    first_glyph: u16,
+    class_array: &'a [u8],
+    state_array_offset: u16,
+    state_array: &'a [u8],
+    entry_table: &'a [u8],
+    convert: fn(GenericStateEntry<R>) -> GenericStateEntry<T>,
+}
+
+impl<'a, R: FromData, T: FromData> StateTable<'a, R, T> {
+    fn parse(
+        data: &'a [u8],
+        convert: fn(GenericStateEntry<R>) -> GenericStateEntry<T>,
+    ) -> Option<Self> {
+        let number_of_classes = read_at::<u16>(data, 0)?;
+        let class_table_offset = usize::from(read_at::<u16>(data, 2)?);
+        let state_array_offset = read_at::<u16>(data, 4)?;
+        let entry_table_offset = usize::from(read_at::<u16>(data, 6)?);
+
+        if number_of_classes == 0 {
+            return None;
+        }
+
+        let first_glyph = read_at::<u16>(data, class_table_offset)?;
+        let glyph_count = usize::from(read_at::<u16>(data, class_table_offset + 2)?);
+        let class_array = data.get(class_table_offset + 4..class_table_offset + 4 + glyph_count)?;
+
+        Some(StateTable {
+            number_of_classes,
+            first_glyph,
+            class_array,
+            state_array_offset,
+            // Like `ttf-parser`, store all the data past the offsets,
+            // since the actual sizes are expensive to calculate.
+            state_array: data.get(usize::from(state_array_offset)..)?,
+            entry_table: data.get(entry_table_offset..)?,
+            convert,
+        })
+    }
+}
+
+impl<R: FromData, T: FromData> StateMachine<T> for StateTable<'_, R, T> {
+    fn class(&self, glyph: GlyphId) -> Option<u16> {
+        if glyph.0 == 0xFFFF {
+            return Some(u16::from(apple_layout::class::DELETED_GLYPH));
+        }
+
+        let idx = glyph.0.checked_sub(self.first_glyph)?;
+        self.class_array
+            .get(usize::from(idx))
+            .copied()
+            .map(u16::from)
+    }
+
+    fn entry(&self, state: u16, mut class: u16) -> Option<GenericStateEntry<T>> {
+        if class >= self.number_of_classes {
+            class = u16::from(apple_layout::class::OUT_OF_BOUNDS);
+        }
+
+        let state_idx =
+            usize::from(state) * usize::from(self.number_of_classes) + usize::from(class);
+        let entry_idx = usize::from(*self.state_array.get(state_idx)?);
+        let entry: GenericStateEntry<R> =
+            read_at(self.entry_table, entry_idx * GenericStateEntry::<R>::SIZE)?;
+
+        // A new state is a byte offset to a state array row.
+        let new_state =
+            entry.new_state.checked_sub(self.state_array_offset)? / self.number_of_classes;
+
+        Some((self.convert)(GenericStateEntry { new_state, ..entry }))
+    }
+}
+
+/// Zero offsets mean no substitution, which `morx` denotes with 0xFFFF.
+fn contextual_entry(
+    mut entry: GenericStateEntry<morx::ContextualEntryData>,
+) -> GenericStateEntry<morx::ContextualEntryData> {
+    entry.extra.mark_index = none_if_zero(entry.extra.mark_index);
+    entry.extra.current_index = none_if_zero(entry.extra.current_index);
+    entry
+}
+
+/// Zero offsets mean no insertion, which `morx` denotes with 0xFFFF.
+fn insertion_entry(
+    mut entry: GenericStateEntry<morx::InsertionEntryData>,
+) -> GenericStateEntry<morx::InsertionEntryData> {
+    entry.extra.current_insert_index = none_if_zero(entry.extra.current_insert_index);
+    entry.extra.marked_insert_index = none_if_zero(entry.extra.marked_insert_index);
+    entry
+}
+
+/// `mort` ligature entries store the action offset in the flags,
+/// while `morx` ones have a separate action index and a PerformAction flag.
+fn ligature_entry(entry: GenericStateEntry<()>) -> GenericStateEntry<u16> {
+    const SET_COMPONENT_AND_DONT_ADVANCE: u16 = 0xC000;
+    const OFFSET: u16 = 0x3FFF;
+    const PERFORM_ACTION: u16 = 0x2000;
+
+    let offset = entry.flags & OFFSET;
+    let mut flags = entry.flags & SET_COMPONENT_AND_DONT_ADVANCE;
+    if offset != 0 {
+        flags |= PERFORM_ACTION;
+    }
+
+    GenericStateEntry {
+        new_state: entry.new_state,
+        flags,
+        extra: offset,
+    }
+}
+
+fn none_if_zero(offset: u16) -> u16 {
+    if offset == 0 {
+        0xFFFF
+    } else {
+        offset
+    }
+}
+
+/// Subtable data addressed by entries using offsets from the state table start.
+struct SubtableData<'a>(&'a [u8]);
+
+impl ContextualSubstitutions for SubtableData<'_> {
+    fn substitute(&self, index: u16, glyph: GlyphId) -> Option<Option<u16>> {
+        // The index is a word offset, which is added to the glyph id.
+        let offset = usize::from(index.wrapping_add(glyph.0)) * 2;
+        Some(read_at::<u16>(self.0, offset).filter(|&replacement| replacement != 0))
+    }
+}
+
+impl InsertionGlyphs for SubtableData<'_> {
+    fn glyph(&self, start: u16, i: u16) -> Option<GlyphId> {
+        read_at::<u16>(self.0, usize::from(start) + usize::from(i) * 2).map(GlyphId)
+    }
+}
+
+impl LigatureTables for SubtableData<'_> {
+    fn action(&self, start: u16, i: u16) -> Option<u32> {
+        read_at::<u32>(self.0, usize::from(start) + usize::from(i) * 4)
+    }
+
+    fn component(&self, index: u32) -> Option<u16> {
+        // The component index is a word offset.
+        read_at::<u16>(self.0, usize::try_from(index).ok()?.checked_mul(2)?)
+    }
+
+    fn ligature(&self, index: u16) -> Option<GlyphId> {
+        // While the ligature index is a byte offset.
+        read_at::<u16>(self.0, usize::from(index)).map(GlyphId)
+    }
+}
@@ -15,6 +15,29 @@ pub fn compile_flags(
     builder: &hb_aat_map_builder_t,
     map: &mut hb_aat_map_t,
 ) -> Option<()> {
+    let chains = face.tables().morx.as_ref()?.chains;
+    let chain_len = chains.clone().into_iter().count();
+    map.chain_flags.resize(chain_len, vec![]);
+
+    for (chain, chain_flags) in chains.into_iter().zip(map.chain_flags.iter_mut()) {
+        chain_flags.push(range_flags_t {
+            flags: chain_flags_for(builder, chain.default_flags, chain.features),
+            cluster_first: builder.range_first as u32,
+            cluster_last: builder.range_last as u32,
+        });
+    }
+
+    Some(())
+}
+
+/// Returns chain flags for the features requested in the builder.
+///
+/// Shared by `morx` and `mort` chains, which have the same feature entries.
+pub(crate) fn chain_flags_for(
+    builder: &hb_aat_map_builder_t,
+    default_flags: u32,
+    features: impl IntoIterator<Item = morx::Feature>,
+) -> u32 {
     let has_feature = |kind: u16, setting: u16| {
         builder
             .current_features
@@ -28,43 +51,31 @@ pub fn compile_flags(
             .is_ok()
     };
 
-    let chains = face.tables().morx.as_ref()?.chains;
-    let chain_len = chains.clone().into_iter().count();
-    map.chain_flags.resize(chain_len, vec![]);
-
-    for (chain, chain_flags) in chains.into_iter().zip(map.chain_flags.iter_mut()) {
-        let mut flags = chain.default_flags;
-        for feature in chain.features {
-            // Check whether this type/setting pair was requested in the map,
-            // and if so, apply its flags.
-
-            if has_feature(feature.kind, feature.setting) {
+    let mut flags = default_flags;
+    for feature in features {
+        // Check whether this type/setting pair was requested in the map,
+        // and if so, apply its flags.
+
+        if has_feature(feature.kind, feature.setting) {
+            flags &= feature.disable_flags;
+            flags |= feature.enable_flags;
+        } else if feature.kind == HB_AAT_LAYOUT_FEATURE_TYPE_LETTER_CASE as u16
+            && feature.setting == u16::from(HB_AAT_LAYOUT_FEATURE_SELECTOR_SMALL_CAPS)
+        {
+            // Deprecated. https://github.com/harfbuzz/harfbuzz/issues/1342
+            let ok = has_feature(
+                HB_AAT_LAYOUT_FEATURE_TYPE_LOWER_CASE as u16,
+                u16::from(HB_AAT_LAYOUT_FEATURE_SELECTOR_LOWER_CASE_SMALL_CAPS),
+            );
+            if ok {
                 flags &= feature.disable_flags;
                 flags |= feature.enable_flags;
-            } else if feature.kind == HB_AAT_LAYOUT_FEATURE_TYPE_LETTER_CASE as u16
-                && feature.setting == u16::from(HB_AAT_LAYOUT_FEATURE_SELECTOR_SMALL_CAPS)
-            {
-                // Deprecated. https://github.com/harfbuzz/harfbuzz/issues/1342
-                let ok = has_feature(
-                    HB_AAT_LAYOUT_FEATURE_TYPE_LOWER_CASE as u16,
-                    u16::from(HB_AAT_LAYOUT_FEATURE_SELECTOR_LOWER_CASE_SMALL_CAPS),
-                );
-                if ok {
-                    flags &= feature.disable_flags;
-                    flags |= feature.enable_flags;
-                }
             }
-            // TODO: Port the following commit: https://github.com/harfbuzz/harfbuzz/commit/2124ad890
         }
-
-        chain_flags.push(range_flags_t {
-            flags,
-            cluster_first: builder.range_first as u32,
-            cluster_last: builder.range_last as u32,
-        });
+        // TODO: Port the following commit: https://github.com/harfbuzz/harfbuzz/commit/2124ad890
     }
 
-    Some(())
+    flags
 }
 
 // Chain::apply in harfbuzz
@@ -140,6 +151,24 @@ pub fn apply<'a>(c: &mut hb_aat_apply_context_t<'a>, map: &'a mut hb_aat_map_t)
     Some(())
 }
 
+/// A state table driven by [`drive`].
+///
+/// Implemented by the `morx` extended state tables and the legacy `mort` ones.
+pub(crate) trait StateMachine<T: FromData> {
+    fn class(&self, glyph: GlyphId) -> Option<u16>;
+    fn entry(&self, state: u16, class: u16) -> Option<apple_layout::GenericStateEntry<T>>;
+}
+
+impl<T: FromData> StateMachine<T> for apple_layout::ExtendedStateTable<'_, T> {
+    fn class(&self, glyph: GlyphId) -> Option<u16> {
+        apple_layout::ExtendedStateTable::class(self, glyph)
+    }
+
+    fn entry(&self, state: u16, class: u16) -> Option<apple_layout::GenericStateEntry<T>> {
+        apple_layout::ExtendedStateTable::entry(self, state, class)
+    }
+}
+
 trait driver_context_t<T: FromData> {
     fn in_place(&self) -> bool;
     fn can_advance(&self, entry: &apple_layout::GenericStateEntry<T>) -> bool;
@@ -158,7 +187,7 @@ trait driver_context_t<T: FromData> {
 const START_OF_TEXT: u16 = 0;
 
 fn drive<T: FromData>(
-    machine: &apple_layout::ExtendedStateTable<T>,
+    machine: &dyn StateMachine<T>,
     c: &mut dyn driver_context_t<T>,
     ac: &mut hb_aat_apply_context_t,
 ) {
@@ -320,91 +349,111 @@ fn drive<T: FromData>(
 
 fn apply_subtable(kind: &morx::SubtableKind, ac: &mut hb_aat_apply_context_t) {
     match kind {
-        morx::SubtableKind::Rearrangement(ref table) => {
-            let mut c = RearrangementCtx { start: 0, end: 0 };
-
-            drive::<()>(table, &mut c, ac);
+        morx::SubtableKind::Rearrangement(ref table) => apply_rearrangement(table, ac),
+        morx::SubtableKind::Contextual(ref table) => apply_contextual(&table.state, table, ac),
+        morx::SubtableKind::Ligature(ref table) => apply_ligature(&table.state, table, ac),
+        morx::SubtableKind::NonContextual(ref lookup) => apply_noncontextual(lookup, ac),
+        morx::SubtableKind::Insertion(ref table) => {
+            apply_insertion(&table.state, &table.glyphs, ac)
         }
-        morx::SubtableKind::Contextual(ref table) => {
-            let mut c = ContextualCtx {
-                mark_set: false,
-                face_if_has_glyph_classes:
-                    matches!(ac.face.tables().gdef, Some(gdef) if gdef.has_glyph_classes())
-                        .then_some(ac.face),
-                mark: 0,
-                table,
-            };
+    }
+}
 
-            drive::<morx::ContextualEntryData>(&table.state, &mut c, ac);
-        }
-        morx::SubtableKind::Ligature(ref table) => {
-            let mut c = LigatureCtx {
-                table,
-                match_length: 0,
-                match_positions: [0; LIGATURE_MAX_MATCHES],
-            };
+pub(crate) fn apply_rearrangement(machine: &dyn StateMachine<()>, ac: &mut hb_aat_apply_context_t) {
+    let mut c = RearrangementCtx { start: 0, end: 0 };
+
+    drive::<()>(machine, &mut c, ac);
+}
 
-            drive::<u16>(&table.state, &mut c, ac);
+pub(crate) fn apply_contextual(
+    machine: &dyn StateMachine<morx::ContextualEntryData>,
+    table: &dyn ContextualSubstitutions,
+    ac: &mut hb_aat_apply_context_t,
+) {
+    let mut c = ContextualCtx {
+        mark_set: false,
+        face_if_has_glyph_classes:
+            matches!(ac.face.tables().gdef, Some(gdef) if gdef.has_glyph_classes())
+                .then_some(ac.face),
+        mark: 0,
+        table,
+    };
+
+    drive::<morx::ContextualEntryData>(machine, &mut c, ac);
+}
+
+pub(crate) fn apply_ligature(
+    machine: &dyn StateMachine<u16>,
+    table: &dyn LigatureTables,
+    ac: &mut hb_aat_apply_context_t,
+) {
+    let mut c = LigatureCtx {
+        table,
+        match_length: 0,
+        match_positions: [0; LIGATURE_MAX_MATCHES],
+    };
+
+    drive::<u16>(machine, &mut c, ac);
+}
+
+pub(crate) fn apply_noncontextual(lookup: &apple_layout::Lookup, ac: &mut hb_aat_apply_context_t) {
+    let face_if_has_glyph_classes =
+        matches!(ac.face.tables().gdef, Some(gdef) if gdef.has_glyph_classes()).then_some(ac.face);
+
+    let mut last_range = ac.range_flags.as_ref().and_then(|rf| {
+        if rf.len() > 1 {
+            rf.first().map(|_| 0usize)
+        } else {
+            // If there's only one range, we already checked the flag.
+            None
         }
-        morx::SubtableKind::NonContextual(ref lookup) => {
-            let face_if_has_glyph_classes =
-                matches!(ac.face.tables().gdef, Some(gdef) if gdef.has_glyph_classes())
-                    .then_some(ac.face);
-
-            let mut last_range = ac.range_flags.as_ref().and_then(|rf| {
-                if rf.len() > 1 {
-                    rf.first().map(|_| 0usize)
-                } else {
-                    // If there's only one range, we already checked the flag.
-                    None
-                }
-            });
+    });
 
-            for info in 0..ac.buffer.len {
-                // This block copied from StateTableDriver::drive. Keep in sync.
-                if let Some(range_flags) = ac.range_flags.as_ref() {
-                    if let Some(last_range) = last_range.as_mut() {
-                        let mut range = *last_range;
-                        if ac.buffer.idx < ac.buffer.len {
-                            // We need to access info
-                            let cluster = ac.buffer.cur(0).cluster;
-                            while cluster < range_flags[range].cluster_first {
-                                range -= 1;
-                            }
-
-                            while cluster > range_flags[range].cluster_last {
-                                range += 1;
-                            }
-
-                            *last_range = range;
-                        }
-
-                        if range_flags[range].flags & ac.subtable_flags == 0 {
-                            continue;
-                        }
+    for info in 0..ac.buffer.len {
+        // This block copied from StateTableDriver::drive. Keep in sync.
+        if let Some(range_flags) = ac.range_flags.as_ref() {
+            if let Some(last_range) = last_range.as_mut() {
+                let mut range = *last_range;
+                if ac.buffer.idx < ac.buffer.len {
+                    // We need to access info
+                    let cluster = ac.buffer.cur(0).cluster;
+                    while cluster < range_flags[range].cluster_first {
+                        range -= 1;
                     }
-                }
 
-                let info = &mut ac.buffer.info[info];
-                if let Some(replacement) = lookup.value(info.as_glyph()) {
-                    info.glyph_id = u32::from(replacement);
-                    if let Some(face) = face_if_has_glyph_classes {
-                        info.set_glyph_props(face.glyph_props(GlyphId(replacement)));
+                    while cluster > range_flags[range].cluster_last {
+                        range += 1;
                     }
+
+                    *last_range = range;
+                }
+
+                if range_flags[range].flags & ac.subtable_flags == 0 {
+                    continue;
                 }
             }
         }
-        morx::SubtableKind::Insertion(ref table) => {
-            let mut c = InsertionCtx {
-                mark: 0,
-                glyphs: table.glyphs,
-            };
 
-            drive::<morx::InsertionEntryData>(&table.state, &mut c, ac);
+        let info = &mut ac.buffer.info[info];
+        if let Some(replacement) = lookup.value(info.as_glyph()) {
+            info.glyph_id = u32::from(replacement);
+            if let Some(face) = face_if_has_glyph_classes {
+                info.set_glyph_props(face.glyph_props(GlyphId(replacement)));
+            }
         }
     }
 }
 
+pub(crate) fn apply_insertion(
+    machine: &dyn StateMachine<morx::InsertionEntryData>,
+    glyphs: &dyn InsertionGlyphs,
+    ac: &mut hb_aat_apply_context_t,
+) {
+    let mut c = InsertionCtx { mark: 0, glyphs };
+
+    drive::<morx::InsertionEntryData>(machine, &mut c, ac);
+}
+
 struct RearrangementCtx {
     start: usize,
     end: usize,
@@ -525,7 +574,7 @@ struct ContextualCtx<'a> {
     mark_set: bool,
     face_if_has_glyph_classes: Option<&'a hb_font_t<'a>>,
     mark: usize,
-    table: &'a morx::ContextualSubtable<'a>,
+    table: &'a dyn ContextualSubstitutions,
 }
 
 impl ContextualCtx<'_> {
@@ -571,8 +620,9 @@ impl driver_context_t<morx::ContextualEntryData> for ContextualCtx<'_> {
         let mut replacement = None;
 
         if entry.extra.mark_index != 0xFFFF {
-            let lookup = self.table.lookup(u32::from(entry.extra.mark_index))?;
-            replacement = lookup.value(buffer.info[self.mark].as_glyph());
+            replacement = self
+                .table
+                .substitute(entry.extra.mark_index, buffer.info[self.mark].as_glyph())?;
         }
 
         if let Some(replacement) = replacement {
@@ -587,8 +637,9 @@ impl driver_context_t<morx::ContextualEntryData> for ContextualCtx<'_> {
         replacement = None;
         let idx = buffer.idx.min(buffer.len - 1);
         if entry.extra.current_index != 0xFFFF {
-            let lookup = self.table.lookup(u32::from(entry.extra.current_index))?;
-            replacement = lookup.value(buffer.info[idx].as_glyph());
+            replacement = self
+                .table
+                .substitute(entry.extra.current_index, buffer.info[idx].as_glyph())?;
         }
 
         if let Some(replacement) = replacement {
@@ -610,16 +661,40 @@ impl driver_context_t<morx::ContextualEntryData> for ContextualCtx<'_> {
 
 struct InsertionCtx<'a> {
     mark: u32,
-    glyphs: LazyArray32<'a, GlyphId>,
+    glyphs: &'a dyn InsertionGlyphs,
 }
 
 impl InsertionCtx<'_> {
     const SET_MARK: u16 = 0x8000;
     const DONT_ADVANCE: u16 = 0x4000;
+    const CURRENT_IS_KASHIDA_LIKE: u16 = 0x2000;
+    const MARKED_IS_KASHIDA_LIKE: u16 = 0x1000;
     const CURRENT_INSERT_BEFORE: u16 = 0x0800;
     const MARKED_INSERT_BEFORE: u16 = 0x0400;
     const CURRENT_INSERT_COUNT: u16 = 0x03E0;
     const MARKED_INSERT_COUNT: u16 = 0x001F;
+
+    /// Split-vowel-like glyphs are a part of the glyph they were inserted next to
+    /// and share its cluster, which is what `output_glyph` does.
+    ///
+    /// Kashida-like glyphs inserted before a glyph extend the connection
+    /// from the preceding glyph instead, so they belong to the preceding cluster
+    /// and the connection is unsafe to break.
+    fn attach_kashida(buffer: &mut hb_buffer_t, start: usize, count: usize) {
+        if start == 0 || count == 0 {
+            return;
+        }
+
+        let cluster = buffer.out_info()[start - 1].cluster;
+        for info in &mut buffer.out_info_mut()[start..start + count] {
+            info.cluster = cluster;
+        }
+
+        buffer.unsafe_to_break_from_outbuffer(
+            Some(start - 1),
+            Some((buffer.idx + 1).min(buffer.len)),
+        );
+    }
 }
 
 impl driver_context_t<morx::InsertionEntryData> for InsertionCtx<'_> {
@@ -669,10 +744,13 @@ impl driver_context_t<morx::InsertionEntryData> for InsertionCtx<'_> {
                 buffer.copy_glyph();
             }
 
-            // TODO We ignore KashidaLike setting.
+            let insert_start = buffer.out_len;
             for i in 0..count {
-                let i = u32::from(start + i);
-                buffer.output_glyph(u32::from(self.glyphs.get(i)?.0));
+                buffer.output_glyph(u32::from(self.glyphs.glyph(start, i)?.0));
+            }
+
+            if before && flags & Self::MARKED_IS_KASHIDA_LIKE != 0 {
+                Self::attach_kashida(buffer, insert_start, usize::from(count));
             }
 
             if buffer.idx < buffer.len && !before {
@@ -706,10 +784,13 @@ impl driver_context_t<morx::InsertionEntryData> for InsertionCtx<'_> {
                 buffer.copy_glyph();
             }
 
-            // TODO We ignore KashidaLike setting.
+            let insert_start = buffer.out_len;
             for i in 0..count {
-                let i = u32::from(start + i);
-                buffer.output_glyph(u32::from(self.glyphs.get(i)?.0));
+                buffer.output_glyph(u32::from(self.glyphs.glyph(start, i)?.0));
+            }
+
+            if before && flags & Self::CURRENT_IS_KASHIDA_LIKE != 0 {
+                Self::attach_kashida(buffer, insert_start, usize::from(count));
             }
 
             if buffer.idx < buffer.len && !before {
@@ -741,10 +822,59 @@ impl driver_context_t<morx::InsertionEntryData> for InsertionCtx<'_> {
     }
 }
 
+/// Substitution tables of a contextual subtable.
+pub(crate) trait ContextualSubstitutions {
+    /// Returns the substitution of a glyph from the table referenced by an entry.
+    ///
+    /// Returns `None` when the table itself is invalid.
+    fn substitute(&self, index: u16, glyph: GlyphId) -> Option<Option<u16>>;
+}
+
+impl ContextualSubstitutions for morx::ContextualSubtable<'_> {
+    fn substitute(&self, index: u16, glyph: GlyphId) -> Option<Option<u16>> {
+        Some(self.lookup(u32::from(index))?.value(glyph))
+    }
+}
+
+/// Glyphs inserted by an insertion subtable.
+pub(crate) trait InsertionGlyphs {
+    /// Returns the `i`-th glyph of the insertion list referenced by an entry.
+    fn glyph(&self, start: u16, i: u16) -> Option<GlyphId>;
+}
+
+impl InsertionGlyphs for LazyArray32<'_, GlyphId> {
+    fn glyph(&self, start: u16, i: u16) -> Option<GlyphId> {
+        self.get(u32::from(start + i))
+    }
+}
+
+/// Ligature actions, components and glyphs of a ligature subtable.
+pub(crate) trait LigatureTables {
+    /// Returns the `i`-th action of the action list referenced by an entry.
+    fn action(&self, start: u16, i: u16) -> Option<u32>;
+    fn component(&self, index: u32) -> Option<u16>;
+    fn ligature(&self, index: u16) -> Option<GlyphId>;
+}
+
+impl LigatureTables for morx::LigatureSubtable<'_> {
+    fn action(&self, start: u16, i: u16) -> Option<u32> {
+        self.ligature_actions
+            .get(u32::from(start).checked_add(u32::from(i))?)
+    }
+
+    fn component(&self, index: u32) -> Option<u16> {
+        self.components.get(index)
+    }
+
+    fn ligature(&self, index: u16) -> Option<GlyphId> {
+        self.ligatures.get(u32::from(index))
+    }
+}
+
 const LIGATURE_MAX_MATCHES: usize = 64;
 
 struct LigatureCtx<'a> {
-    table: &'a morx::LigatureSubtable<'a>,
+    table: &'a dyn LigatureTables,
     match_length: usize,
     match_positions: [usize; LIGATURE_MAX_MATCHES],
 }
@@ -803,8 +933,8 @@ impl driver_context_t<u16> for LigatureCtx<'_> {
 
             let mut cursor = self.match_length;
 
-            let mut ligature_actions_index = entry.extra;
-            let mut ligature_idx = 0;
+            let mut action_number = 0;
+            let mut ligature_idx: u16 = 0;
             loop {
                 if cursor == 0 {
                     // Stack underflow. Clear the stack.
@@ -817,11 +947,7 @@ impl driver_context_t<u16> for LigatureCtx<'_> {
 
                 // We cannot use ? in this loop, because we must call
                 // buffer.move_to(end) in the end.
-                let action = match self
-                    .table
-                    .ligature_actions
-                    .get(u32::from(ligature_actions_index))
-                {
+                let action = match self.table.action(entry.extra, action_number) {
                     Some(v) => v,
                     None => break,
                 };
@@ -833,13 +959,14 @@ impl driver_context_t<u16> for LigatureCtx<'_> {
 
                 let offset = uoffset as i32;
                 let component_idx = (buffer.cur(0).glyph_id as i32 + offset) as u32;
-                ligature_idx += match self.table.components.get(component_idx) {
-                    Some(v) => v,
+                // `mort` components are byte offsets, which are expected to wrap.
+                ligature_idx = match self.table.component(component_idx) {
+                    Some(v) => ligature_idx.wrapping_add(v),
                     None => break,
                 };
 
                 if (action & (Self::LIG_ACTION_STORE | Self::LIG_ACTION_LAST)) != 0 {
-                    let lig = match self.table.ligatures.get(u32::from(ligature_idx)) {
+                    let lig = match self.table.ligature(ligature_idx) {
                         Some(v) => v,
                         None => break,
                     };
@@ -868,7 +995,7 @@ impl driver_context_t<u16> for LigatureCtx<'_> {
                     );
                 }
 
-                ligature_actions_index += 1;
+                action_number += 1;
 
                 if action & Self::LIG_ACTION_LAST != 0 {
                     break;
@@ -0,0 +1,123 @@
+use super::algs::read_at;
+use super::hb_font_t;
+use alloc::vec::Vec;
+use core::num::NonZeroU16;
+use ttf_parser::{apple_layout, loca, GlyphId, Tag};
+
+// The `opbd` table is not exposed by `ttf-parser`, so it is parsed here.
+//
+// https://developer.apple.com/fonts/TrueType-Reference-Manual/RM06/Chap6opbd.html
+
+const DISTANCE_FORMAT: u16 = 0;
+const CONTROL_POINT_FORMAT: u16 = 1;
+const NO_CONTROL_POINT: u16 = 0xFFFF;
+
+/// Returns the left and right optical edges of a glyph, relative to its origin, in font units.
+///
+/// Returns `None` when the font has no `opbd` table or the glyph has no entry in it.
+pub fn optical_edges(face: &hb_font_t, glyph: GlyphId) -> Option<(i32, i32)> {
+    let data = face.raw_face().table(Tag::from_bytes(b"opbd"))?;
+    let format = read_at::<u16>(data, 4)?;
+    let number_of_glyphs = NonZeroU16::new(face.number_of_glyphs())?;
+    let lookup = apple_layout::Lookup::parse(number_of_glyphs, data.get(6..)?)?;
+    let offset = usize::from(lookup.value(glyph)?);
+
+    // Left, top, right and bottom values. Only the horizontal ones are used.
+    let left = read_at::<u16>(data, offset)?;
+    let right = read_at::<u16>(data, offset + 4)?;
+    let advance = face.glyph_h_advance(glyph);
+
+    match format {
+        DISTANCE_FORMAT => Some((i32::from(left as i16), advance + i32::from(right as i16))),
+        CONTROL_POINT_FORMAT => {
+            let left = match left {
+                NO_CONTROL_POINT => 0,
+                point => control_point_x(face, glyph, point)?,
+            };
+            let right = match right {
+                NO_CONTROL_POINT => advance,
+                point => control_point_x(face, glyph, point)?,
+            };
+            Some((left, right))
+        }
+        _ => None,
+    }
+}
+
+fn control_point_x(face: &hb_font_t, glyph: GlyphId, point: u16) -> Option<i32> {
+    if let Some(x) = outline_point_x(face, glyph, point) {
+        return Some(x);
+    }
+
+    // Fonts without `glyf` outlines can still provide points via `ankr`.
+    let ankr = face.tables().ankr.as_ref()?;
+    let anchor = ankr.points(glyph)?.get(u32::from(point))?;
+    Some(i32::from(anchor.x))
+}
+
+// Returns the x coordinate of a simple `glyf` glyph point.
+// Variations are not applied.
+fn outline_point_x(face: &hb_font_t, glyph: GlyphId, point: u16) -> Option<i32> {
+    let raw_face = face.raw_face();
+    let glyf = raw_face.table(Tag::from_bytes(b"glyf"))?;
+    let loca = loca::Table::parse(
+        NonZeroU16::new(face.number_of_glyphs())?,
+        face.tables().head.index_to_location_format,
+        raw_face.table(Tag::from_bytes(b"loca"))?,
+    )?;
+    let data = glyf.get(loca.glyph_range(glyph)?)?;
+
+    let number_of_contours = read_at::<u16>(data, 0)? as i16;
+    if number_of_contours <= 0 {
+        // Composite and empty glyphs.
+        return None;
+    }
+
+    let contours_end = 10 + 2 * usize::from(number_of_contours as u16);
+    let number_of_points = usize::from(read_at::<u16>(data, contours_end - 2)?) + 1;
+    let point = usize::from(point);
+    if point >= number_of_points {
+        return None;
+    }
+
+    let instructions_len = usize::from(read_at::<u16>(data, contours_end)?);
+    let mut offset = contours_end + 2 + instructions_len;
+
+    // Flags are run-length encoded and must be read in full
+    // to find where the x coordinates start.
+    let mut flags = Vec::with_capacity(number_of_points);
+    while flags.len() < number_of_points {
+        let flag = *data.get(offset)?;
+        offset += 1;
+        flags.push(flag);
+        if flag & REPEAT_FLAG != 0 {
+            let count = *data.get(offset)?;
+            offset += 1;
+            for _ in 0..count {
+                flags.push(flag);
+            }
+        }
+    }
+
+    let mut x = 0i32;
+    for flag in flags.iter().take(point + 1) {
+        if flag & X_SHORT_VECTOR != 0 {
+            let delta = i32::from(*data.get(offset)?);
+            offset += 1;
+            x += if flag & X_IS_SAME_OR_POSITIVE != 0 {
+                delta
+            } else {
+                -delta
+            };
+        } else if flag & X_IS_SAME_OR_POSITIVE == 0 {
+            x += i32::from(read_at::<u16>(data, offset)? as i16);
+            offset += 2;
+        }
+    }
+
+    Some(x)
+}
+
+const X_SHORT_VECTOR: u8 = 0x02;
+const REPEAT_FLAG: u8 = 0x08;
+const X_IS_SAME_OR_POSITIVE: u8 = 0x10;
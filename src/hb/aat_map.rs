@@ -96,6 +96,28 @@ impl Default for hb_aat_map_builder_t {
 
 impl hb_aat_map_builder_t {
     pub fn add_feature(&mut self, face: &hb_font_t, feature: &Feature) -> Option<()> {
+        if let Some((kind, setting)) = feature.aat_selector {
+            // Unlike mapped OpenType features, direct requests don't need a `feat` entry.
+            let is_exclusive = face
+                .tables()
+                .feat
+                .and_then(|feat| feat.names.find(kind))
+                .map(|name| name.exclusive)
+                .unwrap_or(false);
+
+            self.features.push(feature_range_t {
+                start: feature.start,
+                end: feature.end,
+                info: feature_info_t {
+                    kind,
+                    setting,
+                    is_exclusive,
+                },
+            });
+
+            return Some(());
+        }
+
         let feat = face.tables().feat?;
 
         if feature.tag == hb_tag_t::from_bytes(b"aalt") {
@@ -231,7 +253,11 @@ impl hb_aat_map_builder_t {
                     self.current_features.truncate(j + 1);
                 }
 
-                super::aat_layout_morx_table::compile_flags(face, self, m);
+                if face.tables().morx.is_some() {
+                    super::aat_layout_morx_table::compile_flags(face, self, m);
+                } else {
+                    super::aat_layout_mort_table::compile_flags(face, self, m);
+                }
                 last_index = event.index;
             }
 
@@ -35,3 +35,10 @@ pub fn rb_flag64_unsafe(x: u32) -> u64 {
         0
     }
 }
+
+// Reads a big-endian value at a byte offset, like `StructAtOffset` in harfbuzz.
+#[inline]
+pub fn read_at<T: ttf_parser::FromData>(data: &[u8], offset: usize) -> Option<T> {
+    data.get(offset..offset.checked_add(T::SIZE)?)
+        .and_then(T::parse)
+}
@@ -1,3 +1,4 @@
+use alloc::sync::Arc;
 use alloc::{string::String, vec::Vec};
 use core::cmp::min;
 use core::convert::TryFrom;
@@ -5,10 +6,13 @@ use ttf_parser::GlyphId;
 
 use super::buffer::glyph_flag::{SAFE_TO_INSERT_TATWEEL, UNSAFE_TO_BREAK, UNSAFE_TO_CONCAT};
 use super::face::hb_glyph_extents_t;
-use super::unicode::{CharExt, GeneralCategoryExt};
+use super::unicode::{hb_unicode_funcs_default_t, CharExt, GeneralCategoryExt, UnicodeFuncs};
 use super::{hb_font_t, hb_mask_t};
 use crate::hb::set_digest::{hb_set_digest_ext, hb_set_digest_t};
-use crate::{script, BufferClusterLevel, BufferFlags, Direction, Language, Script, SerializeFlags};
+use crate::{
+    script, BufferClusterLevel, BufferFlags, Direction, Language, LayoutTags, Script,
+    SerializeFlags,
+};
 
 const CONTEXT_LENGTH: usize = 5;
 
@@ -219,9 +223,13 @@ impl hb_glyph_info_t {
         v[0] = n;
     }
 
-    pub(crate) fn init_unicode_props(&mut self, scratch_flags: &mut hb_buffer_scratch_flags_t) {
+    pub(crate) fn init_unicode_props(
+        &mut self,
+        unicode: &dyn UnicodeFuncs,
+        scratch_flags: &mut hb_buffer_scratch_flags_t,
+    ) {
         let u = self.as_char();
-        let gc = u.general_category();
+        let gc = unicode.general_category(u);
         let mut props = gc.to_rb() as u16;
 
         if u as u32 >= 0x80 {
@@ -258,11 +266,14 @@ impl hb_glyph_info_t {
                     _ => {}
                 }
             }
+        }
 
-            if gc.is_mark() {
-                props |= UnicodeProps::CONTINUATION.bits();
-                props |= (u.modified_combining_class() as u16) << 8;
-            }
+        if gc.is_mark() {
+            // Only custom Unicode functions can report marks below U+0080.
+            // Those need the same cluster handling as any other mark.
+            *scratch_flags |= HB_BUFFER_SCRATCH_FLAG_HAS_NON_ASCII;
+            props |= UnicodeProps::CONTINUATION.bits();
+            props |= (unicode.modified_combining_class(u) as u16) << 8;
         }
 
         self.set_unicode_props(props);
@@ -331,12 +342,17 @@ pub const HB_BUFFER_CLUSTER_LEVEL_MONOTONE_CHARACTERS: u32 = 1;
 pub const HB_BUFFER_CLUSTER_LEVEL_CHARACTERS: u32 = 2;
 pub const HB_BUFFER_CLUSTER_LEVEL_DEFAULT: u32 = HB_BUFFER_CLUSTER_LEVEL_MONOTONE_GRAPHEMES;
 
+#[derive(Clone)]
 pub struct hb_buffer_t {
     // Information about how the text in the buffer should be treated.
     pub flags: BufferFlags,
     pub cluster_level: hb_buffer_cluster_level_t,
     pub invisible: Option<GlyphId>,
     pub not_found_variation_selector: Option<u32>,
+    pub letter_spacing: i32,
+    pub word_spacing: i32,
+    pub layout_tags: LayoutTags,
+    pub unicode: Option<Arc<dyn UnicodeFuncs>>,
 
     // Buffer contents.
     pub direction: Direction,
@@ -395,6 +411,10 @@ impl hb_buffer_t {
             invisible: None,
             scratch_flags: HB_BUFFER_SCRATCH_FLAG_DEFAULT,
             not_found_variation_selector: None,
+            letter_spacing: 0,
+            word_spacing: 0,
+            layout_tags: LayoutTags::default(),
+            unicode: None,
             max_len: Self::MAX_LEN_DEFAULT,
             max_ops: Self::MAX_OPS_DEFAULT,
             direction: Direction::Invalid,
@@ -442,6 +462,19 @@ impl hb_buffer_t {
         }
     }
 
+    pub fn init_out_unicode_props(&mut self, i: usize) {
+        let unicode = self
+            .unicode
+            .as_deref()
+            .unwrap_or(&hb_unicode_funcs_default_t);
+        let out_info: &mut [hb_glyph_info_t] = if self.have_separate_output {
+            bytemuck::cast_slice_mut(self.pos.as_mut_slice())
+        } else {
+            &mut self.info
+        };
+        out_info[i].init_unicode_props(unicode, &mut self.scratch_flags);
+    }
+
     #[inline]
     fn set_out_info(&mut self, i: usize, info: hb_glyph_info_t) {
         self.out_info_mut()[i] = info;
@@ -470,12 +503,6 @@ impl hb_buffer_t {
         &self.out_info()[idx]
     }
 
-    #[inline]
-    pub fn prev_mut(&mut self) -> &mut hb_glyph_info_t {
-        let idx = self.out_len.saturating_sub(1);
-        &mut self.out_info_mut()[idx]
-    }
-
     pub fn digest(&self) -> hb_set_digest_t {
         let mut digest = hb_set_digest_t::new();
         digest.add_array(self.info.iter().map(|i| GlyphId(i.glyph_id as u16)));
@@ -508,6 +535,17 @@ impl hb_buffer_t {
         self.scratch_flags = HB_BUFFER_SCRATCH_FLAG_DEFAULT;
         self.cluster_level = HB_BUFFER_CLUSTER_LEVEL_DEFAULT;
         self.not_found_variation_selector = None;
+        self.letter_spacing = 0;
+        self.word_spacing = 0;
+        self.layout_tags = LayoutTags::default();
+        self.unicode = None;
+    }
+
+    #[inline]
+    pub fn unicode(&self) -> &dyn UnicodeFuncs {
+        self.unicode
+            .as_deref()
+            .unwrap_or(&hb_unicode_funcs_default_t)
     }
 
     #[inline]
@@ -536,7 +574,7 @@ impl hb_buffer_t {
         self.serial
     }
 
-    fn add(&mut self, codepoint: u32, cluster: u32) {
+    pub(crate) fn add(&mut self, codepoint: u32, cluster: u32) {
         if !self.ensure(self.len + 1) {
             return;
         }
@@ -629,7 +667,7 @@ impl hb_buffer_t {
     pub fn guess_segment_properties(&mut self) {
         if self.script.is_none() {
             for info in &self.info {
-                match info.as_char().script() {
+                match self.unicode().script(info.as_char()) {
                     crate::script::COMMON | crate::script::INHERITED | crate::script::UNKNOWN => {}
                     s => {
                         self.script = Some(s);
@@ -1693,6 +1731,72 @@ impl UnicodeBuffer {
         }
     }
 
+    /// Set the space to add to each grapheme cluster, in font units.
+    ///
+    /// Like the CSS `letter-spacing` property, a non-zero value disables optional ligatures:
+    /// `liga`, `clig`, `dlig` and `calt`, unless requested explicitly.
+    /// [`shape_with_plan`](crate::shape_with_plan) uses the features of the plan as is,
+    /// so the plan has to disable them itself.
+    ///
+    /// Only the OpenType shaper applies spacing. Fonts shaped by their `Wasm` or Graphite
+    /// tables ignore it.
+    #[inline]
+    pub fn set_letter_spacing(&mut self, spacing: i32) {
+        self.0.letter_spacing = spacing;
+    }
+
+    /// Returns the space added to each grapheme cluster.
+    #[inline]
+    pub fn letter_spacing(&self) -> i32 {
+        self.0.letter_spacing
+    }
+
+    /// Set the space to add to each space character, in font units.
+    ///
+    /// Like the CSS `word-spacing` property, it is added on top of the letter spacing.
+    /// Fonts shaped by their `Wasm` or Graphite tables ignore it.
+    #[inline]
+    pub fn set_word_spacing(&mut self, spacing: i32) {
+        self.0.word_spacing = spacing;
+    }
+
+    /// Returns the space added to each space character.
+    #[inline]
+    pub fn word_spacing(&self) -> i32 {
+        self.0.word_spacing
+    }
+
+    /// Set explicit OpenType script and language system tags.
+    ///
+    /// They take precedence over the tags derived from the buffer script and language.
+    /// [`shape_with_plan`](crate::shape_with_plan) uses the tags of the plan.
+    /// See [`ShapePlan::with_layout_tags`](crate::ShapePlan::with_layout_tags).
+    #[inline]
+    pub fn set_layout_tags(&mut self, tags: LayoutTags) {
+        self.0.layout_tags = tags;
+    }
+
+    /// Returns the explicit OpenType script and language system tags.
+    #[inline]
+    pub fn layout_tags(&self) -> LayoutTags {
+        self.0.layout_tags
+    }
+
+    /// Set the Unicode character properties used to shape the buffer.
+    ///
+    /// `None` restores the built-in tables.
+    #[inline]
+    pub fn set_unicode_funcs(&mut self, funcs: Option<Arc<dyn UnicodeFuncs>>) {
+        self.0.unicode = funcs;
+    }
+
+    /// Returns the Unicode character properties set with
+    /// [`set_unicode_funcs`](Self::set_unicode_funcs).
+    #[inline]
+    pub fn unicode_funcs(&self) -> Option<&Arc<dyn UnicodeFuncs>> {
+        self.0.unicode.as_ref()
+    }
+
     /// Resets clusters.
     #[inline]
     pub fn reset_clusters(&mut self) {
@@ -1713,6 +1817,9 @@ impl core::fmt::Debug for UnicodeBuffer {
             .field("language", &self.language())
             .field("script", &self.script())
             .field("cluster_level", &self.cluster_level())
+            .field("letter_spacing", &self.letter_spacing())
+            .field("word_spacing", &self.word_spacing())
+            .field("layout_tags", &self.layout_tags())
             .finish()
     }
 }
@@ -479,12 +479,17 @@ pub mod script {
 /// `shape`s input it should be applied.
 #[repr(C)]
 #[allow(missing_docs)]
-#[derive(Clone, Copy, PartialEq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct Feature {
     pub tag: Tag,
     pub value: u32,
     pub start: u32,
     pub end: u32,
+    /// An AAT feature type and selector, requested directly instead of through `tag`.
+    ///
+    /// When set, `tag` and `value` are ignored and the feature only affects
+    /// faces shaped using `morx` or `mort`. See [`Feature::aat`].
+    pub aat_selector: Option<(u16, u16)>,
 }
 
 impl Feature {
@@ -507,6 +512,19 @@ impl Feature {
             value,
             start,
             end,
+            aat_selector: None,
+        }
+    }
+
+    /// Create a new `Feature` that requests an AAT feature type and selector directly,
+    /// instead of going through an OpenType feature tag.
+    ///
+    /// Available types and selectors are listed by [`Face::aat_feature_types`](crate::Face::aat_feature_types).
+    /// Only affects faces shaped using `morx` or `mort`.
+    pub fn aat(kind: u16, selector: u16, range: impl RangeBounds<usize>) -> Feature {
+        Feature {
+            aat_selector: Some((kind, selector)),
+            ..Feature::new(Tag(0), 1, range)
         }
     }
 
@@ -618,6 +636,7 @@ impl core::str::FromStr for Feature {
                 value,
                 start,
                 end,
+                aat_selector: None,
             })
         }
 
@@ -710,6 +729,20 @@ impl core::str::FromStr for Variation {
     }
 }
 
+/// Explicit OpenType script and language system tags.
+///
+/// Tags that are set take precedence over the ones derived from the buffer
+/// [`Script`] and [`Language`] when selecting the `GSUB` and `GPOS` script and
+/// language system. Like for derived tags, the default script and language
+/// system are used when the font doesn't have the requested ones.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub struct LayoutTags {
+    /// The script tag, like `arab` or `dev2`.
+    pub script: Option<Tag>,
+    /// The language system tag, like `URD ` or `SRB `.
+    pub language: Option<Tag>,
+}
+
 pub trait TagExt {
     fn default_script() -> Self;
     fn default_language() -> Self;
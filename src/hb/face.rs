@@ -25,6 +25,8 @@ const UNICODE_2_0_FULL_ENCODING: u16 = 4;
 //const UNICODE_VARIATION_ENCODING: u16 = 5;
 const UNICODE_FULL_ENCODING: u16 = 6;
 
+const OPSZ_AXIS: ttf_parser::Tag = ttf_parser::Tag::from_bytes(b"opsz");
+
 /// A font face handle.
 #[derive(Clone)]
 pub struct hb_font_t<'a> {
@@ -32,9 +34,17 @@ pub struct hb_font_t<'a> {
     pub(crate) units_per_em: u16,
     pixels_per_em: Option<(u16, u16)>,
     pub(crate) points_per_em: Option<f32>,
+    optical_sizing: bool,
+    explicit_opsz: bool,
     prefered_cmap_encoding_subtable: Option<u16>,
     pub(crate) gsub: Option<SubstitutionTable<'a>>,
     pub(crate) gpos: Option<PositioningTable<'a>>,
+    #[cfg(feature = "graphite")]
+    pub(crate) graphite_face: super::graphite_face::gr_face_cache_t,
+    #[cfg(feature = "wasm-shaper")]
+    pub(crate) wasm_module: std::sync::OnceLock<Option<wasmi::Module>>,
+    #[cfg(feature = "wasm-shaper")]
+    pub(crate) wasm_limits: super::shape_wasm::hb_wasm_limits_t,
 }
 
 impl<'a> AsRef<ttf_parser::Face<'a>> for hb_font_t<'a> {
@@ -84,9 +94,17 @@ impl<'a> hb_font_t<'a> {
             units_per_em: face.units_per_em(),
             pixels_per_em: None,
             points_per_em: None,
+            optical_sizing: false,
+            explicit_opsz: false,
             prefered_cmap_encoding_subtable: find_best_cmap_subtable(&face),
             gsub: face.tables().gsub.map(SubstitutionTable::new),
             gpos: face.tables().gpos.map(PositioningTable::new),
+            #[cfg(feature = "graphite")]
+            graphite_face: Default::default(),
+            #[cfg(feature = "wasm-shaper")]
+            wasm_module: std::sync::OnceLock::new(),
+            #[cfg(feature = "wasm-shaper")]
+            wasm_limits: Default::default(),
             ttfp_face: face,
         }
     }
@@ -115,21 +133,90 @@ impl<'a> hb_font_t<'a> {
 
     /// Sets point size per EM.
     ///
-    /// Used for optical-sizing in Apple fonts.
+    /// Used for optical-sizing in Apple fonts
+    /// and for the `opsz` axis when [automatic optical sizing](Self::set_optical_sizing) is enabled.
     ///
     /// `None` by default.
     #[inline]
     pub fn set_points_per_em(&mut self, ptem: Option<f32>) {
         self.points_per_em = ptem;
+        self.update_optical_size();
+    }
+
+    /// Enables automatic optical sizing.
+    ///
+    /// When enabled, the `opsz` variation follows the point size set via
+    /// [`set_points_per_em`](Self::set_points_per_em), clamped to the axis range,
+    /// like `font-optical-sizing: auto` in CSS.
+    /// An `opsz` value passed to [`set_variations`](Self::set_variations) takes precedence
+    /// until automatic optical sizing is enabled again or all coordinates are reset via
+    /// [`set_named_instance`](Self::set_named_instance) or
+    /// [`set_variation_coordinates`](Self::set_variation_coordinates).
+    ///
+    /// `false` by default.
+    pub fn set_optical_sizing(&mut self, enabled: bool) {
+        if enabled {
+            self.explicit_opsz = false;
+        } else if self.optical_sizing && !self.explicit_opsz {
+            // Restore the default optical size.
+            if let Some(axis) = self.opsz_axis() {
+                self.set_variation(OPSZ_AXIS, axis.def_value);
+            }
+        }
+
+        self.optical_sizing = enabled;
+        self.update_optical_size();
+    }
+
+    /// Sets resource limits for fonts shaped by their `Wasm` table.
+    ///
+    /// [`WasmLimits::default`](crate::WasmLimits::default) by default.
+    #[cfg(feature = "wasm-shaper")]
+    #[inline]
+    pub fn set_wasm_limits(&mut self, limits: crate::WasmLimits) {
+        self.wasm_limits = limits;
     }
 
     /// Sets font variations.
     pub fn set_variations(&mut self, variations: &[Variation]) {
         for variation in variations {
+            if variation.tag == OPSZ_AXIS {
+                self.explicit_opsz = true;
+            }
+
             self.set_variation(variation.tag, variation.value);
         }
     }
 
+    // Called when all coordinates are replaced, dropping an explicit `opsz` value.
+    pub(crate) fn reset_optical_size(&mut self) {
+        self.explicit_opsz = false;
+        self.update_optical_size();
+    }
+
+    pub(crate) fn update_optical_size(&mut self) {
+        if !self.optical_sizing || self.explicit_opsz {
+            return;
+        }
+
+        let Some(axis) = self.opsz_axis() else {
+            return;
+        };
+
+        let value = match self.points_per_em {
+            Some(ptem) => ptem.clamp(axis.min_value, axis.max_value),
+            None => axis.def_value,
+        };
+
+        self.set_variation(OPSZ_AXIS, value);
+    }
+
+    fn opsz_axis(&self) -> Option<ttf_parser::VariationAxis> {
+        self.variation_axes()
+            .into_iter()
+            .find(|axis| axis.tag == OPSZ_AXIS)
+    }
+
     pub(crate) fn has_glyph(&self, c: u32) -> bool {
         self.get_nominal_glyph(c).is_some()
     }
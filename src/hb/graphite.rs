@@ -0,0 +1,223 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use ttf_parser::Tag;
+
+use super::buffer::{_cluster_group_func, glyph_flag, hb_buffer_t};
+use super::graphite_face::{gr_face_t, has_graphite};
+use super::graphite_pass::run_graphite;
+use super::graphite_segment::gr_segment_t;
+use super::hb_font_t;
+use super::ot_shape_plan::hb_ot_shape_plan_t;
+use crate::Direction;
+
+// The glue between the Graphite engine and the buffer follows `hb-graphite2.cc`.
+// Collision avoidance and justification are not supported.
+
+#[derive(Clone, Copy, Default)]
+struct hb_graphite2_cluster_t {
+    base_char: usize,
+    num_chars: usize,
+    base_glyph: usize,
+    num_glyphs: usize,
+    cluster: u32,
+    advance: i32,
+}
+
+/// Shapes the buffer with the font's Graphite tables.
+///
+/// Returns `None` when the font has no usable Graphite tables, so the OpenType shaper
+/// runs instead.
+pub(crate) fn shape_with_graphite(
+    font: &hb_font_t,
+    plan: &hb_ot_shape_plan_t,
+    buffer: &mut hb_buffer_t,
+) -> Option<()> {
+    if !has_graphite(font) {
+        return None;
+    }
+
+    let face = gr_face_t::new(font)?;
+
+    // Only the primary language subtag selects `Sill` defaults.
+    let language = buffer.language.as_ref().map_or(0, |language| {
+        let tag = language.as_str().split('-').next().unwrap_or_default();
+        Tag::from_bytes_lossy(tag.as_bytes()).0
+    });
+    let mut features = face.features_for_language(language);
+    for feature in &plan.user_features {
+        // AAT feature requests only apply to `morx` and `mort`.
+        if feature.aat_selector.is_some() {
+            continue;
+        }
+
+        if let Some(index) = face.find_feature(feature.tag.0) {
+            face.set_feature(&mut features, index, feature.value);
+        }
+    }
+
+    let mut direction = buffer.direction;
+    let horizontal = buffer.script.and_then(Direction::from_script);
+    let reversed = (direction.is_horizontal() && horizontal.is_some_and(|h| h != direction))
+        || (direction.is_vertical() && direction != Direction::TopToBottom);
+    if reversed {
+        buffer.reverse_groups(_cluster_group_func, false);
+        direction = direction.reverse();
+    }
+    let rtl = direction == Direction::RightToLeft;
+    let backward = direction.is_backward();
+
+    let chars: Vec<u32> = buffer.info[..buffer.len]
+        .iter()
+        .map(|info| info.glyph_id)
+        .collect();
+    let mut seg = gr_segment_t::new(&face, &chars, features, rtl);
+    if run_graphite(&mut seg).is_none() || !buffer.ensure(seg.num_slots()) {
+        if reversed {
+            buffer.reverse_groups(_cluster_group_func, false);
+        }
+        return None;
+    }
+    seg.finalise();
+
+    let mut slots = Vec::with_capacity(seg.num_slots());
+    let mut slot = seg.first;
+    while let Some(s) = slot {
+        slots.push(&seg.slots[s]);
+        slot = seg.slots[s].next;
+    }
+
+    if slots.is_empty() {
+        buffer.len = 0;
+        buffer.leave();
+        return Some(());
+    }
+
+    let glyph_count = slots.len();
+
+    let mut clusters = vec![hb_graphite2_cluster_t::default(); buffer.len.max(1)];
+    clusters[0].cluster = buffer.info[0].cluster;
+    let mut ci = 0;
+    let mut curr_adv_x = 0;
+    if backward {
+        curr_adv_x = slots[0].position.x as i32;
+        clusters[0].advance = (seg.advance.x - curr_adv_x as f32) as i32;
+    }
+
+    for (ic, slot) in slots.iter().enumerate() {
+        let before = slot.before.max(0) as usize;
+        let after = slot.after.max(0) as usize;
+        while clusters[ci].base_char > before && ci != 0 {
+            let c = clusters[ci];
+            let prev = &mut clusters[ci - 1];
+            prev.num_chars += c.num_chars;
+            prev.num_glyphs += c.num_glyphs;
+            prev.advance += c.advance;
+            ci -= 1;
+        }
+
+        let current = clusters[ci];
+        if slot.can_insert_before()
+            && current.num_chars != 0
+            && before >= current.base_char + current.num_chars
+            && ci + 1 < clusters.len()
+        {
+            let base_char = current.base_char + current.num_chars;
+            let mut c = hb_graphite2_cluster_t {
+                base_char,
+                cluster: buffer.info[base_char].cluster,
+                num_chars: before - base_char,
+                base_glyph: ic,
+                num_glyphs: 0,
+                advance: 0,
+            };
+            if backward {
+                c.advance = (curr_adv_x as f32 - slot.position.x) as i32;
+                curr_adv_x -= c.advance;
+            } else {
+                let advance = &mut clusters[ci].advance;
+                *advance = (*advance as f32 + slot.position.x - curr_adv_x as f32) as i32;
+                curr_adv_x += *advance;
+            }
+            clusters[ci + 1] = c;
+            ci += 1;
+        }
+
+        let c = &mut clusters[ci];
+        c.num_glyphs += 1;
+        if c.base_char + c.num_chars < after + 1 {
+            c.num_chars = after + 1 - c.base_char;
+        }
+    }
+
+    if backward {
+        clusters[ci].advance += curr_adv_x;
+    } else {
+        let advance = &mut clusters[ci].advance;
+        *advance = (*advance as f32 + seg.advance.x - curr_adv_x as f32) as i32;
+    }
+    ci += 1;
+
+    // Every glyph of a cluster gets the cluster advance.
+    let mut advances = vec![0; glyph_count];
+    for c in &clusters[..ci] {
+        for j in c.base_glyph..c.base_glyph + c.num_glyphs {
+            let info = &mut buffer.info[j];
+            info.glyph_id = u32::from(slots[j].display_glyph());
+            info.cluster = c.cluster;
+            advances[j] = c.advance;
+        }
+    }
+    buffer.len = glyph_count;
+    buffer.clear_positions();
+
+    let mut curr_cluster = u32::MAX;
+    let mut curr_adv_y = 0;
+    if !backward {
+        curr_adv_x = 0;
+        for (i, slot) in slots.iter().enumerate() {
+            let info = &buffer.info[i];
+            let pos = &mut buffer.pos[i];
+            pos.x_offset = (slot.position.x - curr_adv_x as f32) as i32;
+            pos.y_offset = (slot.position.y - curr_adv_y as f32) as i32;
+            if info.cluster != curr_cluster {
+                pos.x_advance = advances[i];
+                curr_adv_x += pos.x_advance;
+                curr_cluster = info.cluster;
+            } else {
+                pos.x_advance = 0;
+            }
+
+            pos.y_advance = slot.advance.y as i32;
+            curr_adv_y += pos.y_advance;
+        }
+    } else {
+        curr_adv_x = seg.advance.x as i32;
+        for (i, slot) in slots.iter().enumerate() {
+            let info = &buffer.info[i];
+            let pos = &mut buffer.pos[i];
+            if info.cluster != curr_cluster {
+                pos.x_advance = advances[i];
+                curr_adv_x -= pos.x_advance;
+                curr_cluster = info.cluster;
+            } else {
+                pos.x_advance = 0;
+            }
+
+            pos.y_advance = slot.advance.y as i32;
+            curr_adv_y -= pos.y_advance;
+            pos.x_offset =
+                (slot.position.x - (advances[i] + curr_adv_x - pos.x_advance) as f32) as i32;
+            pos.y_offset = (slot.position.y - curr_adv_y as f32) as i32;
+        }
+        buffer.reverse_groups(_cluster_group_func, false);
+    }
+
+    for info in &mut buffer.info[..buffer.len] {
+        info.mask &= !glyph_flag::DEFINED;
+    }
+    buffer.unsafe_to_break(None, None);
+    buffer.leave();
+
+    Some(())
+}
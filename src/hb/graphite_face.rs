@@ -0,0 +1,1010 @@
+use alloc::borrow::Cow;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use ttf_parser::{FromData, GlyphId, Tag};
+
+use super::algs::read_at;
+use super::graphite_vm::opcode_len;
+use super::hb_font_t;
+
+// The Graphite tables: `Silf` holds the rules, `Glat` and `Gloc` the glyph attributes,
+// `Feat` the feature definitions and `Sill` the per-language feature defaults.
+//
+// Parsing and validation follow graphite2. Anything graphite2 would refuse to load
+// makes the whole face unavailable, so the OpenType shaper is used instead.
+//
+// https://github.com/silnrsi/graphite/blob/master/doc/table_details.txt
+
+const SILF: Tag = Tag::from_bytes(b"Silf");
+const GLAT: Tag = Tag::from_bytes(b"Glat");
+const GLOC: Tag = Tag::from_bytes(b"Gloc");
+const FEAT: Tag = Tag::from_bytes(b"Feat");
+const SILL: Tag = Tag::from_bytes(b"Sill");
+
+// FiniteStateMachine::MAX_RULES in graphite2
+pub const MAX_RULES: usize = 128;
+
+/// Checks that the face has a `Silf` table.
+pub fn has_graphite(face: &hb_font_t) -> bool {
+    face.raw_face()
+        .table(SILF)
+        .is_some_and(|data| !data.is_empty())
+}
+
+#[derive(Clone, Copy)]
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, offset: 0 }
+    }
+
+    fn at(data: &'a [u8], offset: usize) -> Self {
+        Reader { data, offset }
+    }
+
+    fn read<T: FromData>(&mut self) -> Option<T> {
+        let value = read_at(self.data, self.offset)?;
+        self.offset += T::SIZE;
+        Some(value)
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        self.offset = self.offset.checked_add(len)?;
+        (self.offset <= self.data.len()).then_some(())
+    }
+
+    fn read_array<T: FromData>(&mut self, count: usize) -> Option<Vec<T>> {
+        (0..count).map(|_| self.read()).collect()
+    }
+
+    fn bytes(&self, range: Range<usize>) -> Option<&'a [u8]> {
+        self.data.get(range)
+    }
+}
+
+/// Removes trailing spaces from a tag, the way graphite2 stores language and feature ids.
+pub fn zeropad(x: u32) -> u32 {
+    if x == 0x20202020 {
+        0
+    } else if x & 0x00FF_FFFF == 0x0020_2020 {
+        x & 0xFF00_0000
+    } else if x & 0x0000_FFFF == 0x0000_2020 {
+        x & 0xFFFF_0000
+    } else if x & 0x0000_00FF == 0x0000_0020 {
+        x & 0xFFFF_FF00
+    } else {
+        x
+    }
+}
+
+// Table::decompress in graphite2. Compressed tables are stored whole, version included.
+fn decompress(data: &[u8], compressed: bool) -> Option<Cow<'_, [u8]>> {
+    if !compressed {
+        return Some(Cow::Borrowed(data));
+    }
+
+    let version = read_at::<u32>(data, 0)?;
+    let header = read_at::<u32>(data, 4)?;
+    match header >> 27 {
+        0 => Some(Cow::Borrowed(data)),
+        1 => {
+            let size = (header & 0x07FF_FFFF) as usize;
+            let out = lz4_decompress(data.get(8..)?, size)?;
+            (size >= 4 && read_at::<u32>(&out, 0)? >> 16 == version >> 16)
+                .then_some(Cow::Owned(out))
+        }
+        _ => None,
+    }
+}
+
+// A plain LZ4 block decoder.
+fn lz4_decompress(mut src: &[u8], size: usize) -> Option<Vec<u8>> {
+    fn length(src: &mut &[u8], mut len: usize) -> Option<usize> {
+        if len == 15 {
+            loop {
+                let (&b, rest) = src.split_first()?;
+                *src = rest;
+                len += usize::from(b);
+                if b != 255 {
+                    break;
+                }
+            }
+        }
+        Some(len)
+    }
+
+    let mut out = Vec::with_capacity(size);
+    loop {
+        let (&token, rest) = src.split_first()?;
+        src = rest;
+
+        let literals = length(&mut src, usize::from(token >> 4))?;
+        out.extend_from_slice(src.get(..literals)?);
+        src = &src[literals..];
+        if src.is_empty() || out.len() > size {
+            break;
+        }
+
+        let offset = usize::from(u16::from_le_bytes([*src.first()?, *src.get(1)?]));
+        src = &src[2..];
+        let len = length(&mut src, usize::from(token & 0xF))? + 4;
+        if offset == 0 || offset > out.len() || out.len() + len > size {
+            return None;
+        }
+
+        let start = out.len() - offset;
+        for i in 0..len {
+            out.push(out[start + i]);
+        }
+    }
+
+    (out.len() == size).then_some(out)
+}
+
+/// A feature definition from `Feat`.
+#[derive(Clone, Copy, Debug)]
+pub struct gr_feature_t {
+    pub id: u32,
+    pub max: u32,
+    pub default: u32,
+}
+
+/// A rule of a pass.
+pub struct gr_rule_t {
+    pub pre_context: u8,
+    pub sort: u16,
+    pub constraint: Vec<u8>,
+    pub action: Vec<u8>,
+}
+
+/// A `Silf` pass: a finite state machine over glyph columns and the rules it selects.
+pub struct gr_pass_t {
+    pub reverse_dir: bool,
+    pub max_loop: u8,
+    pub min_pre_context: u8,
+    pub max_pre_context: u8,
+    pub constraint: Vec<u8>,
+    pub rules: Vec<gr_rule_t>,
+    columns: Vec<u16>,
+    num_columns: u16,
+    num_transitions: u16,
+    success_start: u16,
+    start_states: Vec<u16>,
+    transitions: Vec<u16>,
+    // Rule indices of each success state, sorted by preference.
+    state_rules: Vec<Vec<u16>>,
+}
+
+impl gr_pass_t {
+    fn parse(data: &[u8], start: usize, end: usize) -> Option<Self> {
+        let pass = data.get(start..end)?;
+        if pass.len() < 40 {
+            return None;
+        }
+
+        let mut s = Reader::new(pass);
+        let flags = s.read::<u8>()?;
+        let max_loop = s.read::<u8>()?.max(1);
+        s.skip(2)?; // maxContext and maxBackup
+        let num_rules = s.read::<u16>()?;
+        s.skip(2)?; // fsmOffset
+        let code_offset = |v: u32| (v as usize).checked_sub(start);
+        let pc_code = code_offset(s.read::<u32>()?)?;
+        let rc_code = code_offset(s.read::<u32>()?)?;
+        let a_code = code_offset(s.read::<u32>()?)?;
+        s.skip(4)?; // oDebug
+        let num_states = s.read::<u16>()?;
+        let num_transitions = s.read::<u16>()?;
+        let num_success = s.read::<u16>()?;
+        let num_columns = s.read::<u16>()?;
+        let num_ranges = s.read::<u16>()?;
+        s.skip(6)?;
+
+        if num_transitions > num_states
+            || num_success > num_states
+            || u32::from(num_success) + u32::from(num_transitions) < u32::from(num_states)
+            || (num_rules != 0 && num_ranges == 0)
+            || num_columns > 0x7FFF
+        {
+            return None;
+        }
+
+        let ranges: Vec<(u16, u16, u16)> = (0..num_ranges)
+            .map(|_| Some((s.read()?, s.read()?, s.read()?)))
+            .collect::<Option<_>>()?;
+        let num_glyphs = ranges.last().map_or(0, |r| usize::from(r.1) + 1);
+        let mut columns = vec![0xFFFF; num_glyphs];
+        for &(first, last, column) in &ranges {
+            if first > last || usize::from(last) >= num_glyphs || column >= num_columns {
+                return None;
+            }
+
+            for c in &mut columns[usize::from(first)..=usize::from(last)] {
+                if *c != 0xFFFF {
+                    return None;
+                }
+                *c = column;
+            }
+        }
+
+        let rule_map_offsets: Vec<u16> = s.read_array(usize::from(num_success) + 1)?;
+        let rule_map: Vec<u16> = s.read_array(usize::from(*rule_map_offsets.last()?))?;
+        let min_pre_context = s.read::<u8>()?;
+        let max_pre_context = s.read::<u8>()?;
+        if min_pre_context > max_pre_context {
+            return None;
+        }
+
+        let start_states: Vec<u16> =
+            s.read_array(usize::from(max_pre_context - min_pre_context) + 1)?;
+        let sort_keys: Vec<u16> = s.read_array(usize::from(num_rules))?;
+        let pre_contexts: Vec<u8> = s.read_array(usize::from(num_rules))?;
+        s.skip(1)?; // collision threshold
+        let pass_constraint_len = usize::from(s.read::<u16>()?);
+        let constraint_offsets: Vec<u16> = s.read_array(usize::from(num_rules) + 1)?;
+        let action_offsets: Vec<u16> = s.read_array(usize::from(num_rules) + 1)?;
+        let transitions: Vec<u16> =
+            s.read_array(usize::from(num_transitions) * usize::from(num_columns))?;
+        s.skip(1)?;
+
+        let rc_len = usize::from(*constraint_offsets.last()?);
+        let a_len = usize::from(*action_offsets.last()?);
+        if s.offset != pc_code
+            || pc_code + pass_constraint_len != rc_code
+            || rc_code + rc_len != a_code
+            || a_code + a_len > pass.len()
+        {
+            return None;
+        }
+
+        let constraint = s.bytes(pc_code..rc_code)?.to_vec();
+        let rc_data = s.bytes(rc_code..a_code)?;
+        let a_data = s.bytes(a_code..a_code + a_len)?;
+
+        // Rules are read backwards, so that an empty constraint extends the previous one.
+        let mut rules = Vec::with_capacity(usize::from(num_rules));
+        let mut rc_end = rc_len;
+        let mut a_end = a_len;
+        for i in (0..usize::from(num_rules)).rev() {
+            let pre_context = pre_contexts[i];
+            let sort = sort_keys[i];
+            if sort > 63
+                || u16::from(pre_context) >= sort
+                || pre_context > max_pre_context
+                || pre_context < min_pre_context
+            {
+                return None;
+            }
+
+            let a_begin = usize::from(action_offsets[i]);
+            let rc_begin = match constraint_offsets[i] {
+                0 => rc_end,
+                v => usize::from(v),
+            };
+            if a_begin > a_end || rc_begin > rc_end {
+                return None;
+            }
+
+            let action = a_data[a_begin..a_end].to_vec();
+            let rule_constraint = rc_data[rc_begin..rc_end].to_vec();
+            if !is_valid_code(&action) || !is_valid_code(&rule_constraint) {
+                return None;
+            }
+
+            rules.push(gr_rule_t {
+                pre_context,
+                sort,
+                constraint: rule_constraint,
+                action,
+            });
+            a_end = a_begin;
+            rc_end = rc_begin;
+        }
+        rules.reverse();
+
+        if !is_valid_code(&constraint)
+            || start_states.iter().any(|&s| s >= num_states)
+            || transitions.iter().any(|&s| s >= num_states)
+        {
+            return None;
+        }
+
+        let mut state_rules = Vec::with_capacity(usize::from(num_success));
+        for w in rule_map_offsets.windows(2) {
+            let (begin, end) = (usize::from(w[0]), usize::from(w[1]));
+            if begin >= rule_map.len() || end > rule_map.len() || begin > end {
+                return None;
+            }
+
+            let mut entries = rule_map[begin..end].to_vec();
+            if entries.iter().any(|&r| r >= num_rules) {
+                return None;
+            }
+
+            entries.truncate(MAX_RULES);
+            entries.sort_by(|&a, &b| {
+                let (a_sort, b_sort) = (rules[usize::from(a)].sort, rules[usize::from(b)].sort);
+                b_sort.cmp(&a_sort).then(a.cmp(&b))
+            });
+            state_rules.push(entries);
+        }
+
+        Some(gr_pass_t {
+            reverse_dir: (flags >> 5) & 1 != 0,
+            max_loop,
+            min_pre_context,
+            max_pre_context,
+            constraint,
+            rules,
+            columns,
+            num_columns,
+            num_transitions,
+            success_start: num_states - num_success,
+            start_states,
+            transitions,
+            state_rules,
+        })
+    }
+
+    /// Returns the start state for the given number of pre-context slots.
+    pub fn start_state(&self, context: u8) -> u16 {
+        self.start_states[usize::from(self.max_pre_context - context)]
+    }
+
+    /// Returns the column of a glyph, if the pass matches it at all.
+    pub fn column(&self, glyph: u16) -> Option<u16> {
+        self.columns
+            .get(usize::from(glyph))
+            .copied()
+            .filter(|&c| c != 0xFFFF)
+    }
+
+    /// Returns the next state, or `None` when the state has no transitions.
+    pub fn transition(&self, state: u16, column: u16) -> Option<u16> {
+        if state >= self.num_transitions {
+            return None;
+        }
+
+        let index = usize::from(state) * usize::from(self.num_columns) + usize::from(column);
+        self.transitions.get(index).copied()
+    }
+
+    /// Returns the rules matched once the machine reaches the state.
+    pub fn rules_for(&self, state: u16) -> &[u16] {
+        match state.checked_sub(self.success_start) {
+            Some(i) => &self.state_rules[usize::from(i)],
+            None => &[],
+        }
+    }
+}
+
+/// Checks that code only has known instructions and ends with a return.
+fn is_valid_code(code: &[u8]) -> bool {
+    let mut i = 0;
+    let mut last = 0;
+    while i < code.len() {
+        last = code[i];
+        match opcode_len(code, i) {
+            Some(len) => i += len,
+            None => return false,
+        }
+    }
+
+    code.is_empty() || (i == code.len() && matches!(last, 48..=50))
+}
+
+/// A `Silf` subtable. Only the first one is ever used, like in graphite2.
+pub struct gr_silf_t {
+    pub passes: Vec<gr_pass_t>,
+    pub positioning_pass: u8,
+    pub bidi_pass: u8,
+    pub attr_pseudo: u8,
+    pub attr_break: u8,
+    pub attr_bidi: u8,
+    pub attr_mirror: u8,
+    pub attr_pass_bits: u8,
+    pub num_user: u8,
+    pub dir: u8,
+    pseudos: Vec<(u32, u16)>,
+    num_linear: u16,
+    class_offsets: Vec<u32>,
+    class_data: Vec<u16>,
+}
+
+impl gr_silf_t {
+    fn parse(data: &[u8], version: u32, num_attrs: u16) -> Option<Self> {
+        let mut s = Reader::new(data);
+        if version >= 0x0003_0000 {
+            s.skip(8)?; // ruleVersion, passOffset and pseudosOffset
+        }
+
+        s.skip(6)?; // maxGlyphID, extraAscent and extraDescent
+        let num_passes = s.read::<u8>()?;
+        let substitution_pass = s.read::<u8>()?;
+        let positioning_pass = s.read::<u8>()?;
+        let justification_pass = s.read::<u8>()?;
+        let bidi_pass = s.read::<u8>()?;
+        s.skip(3)?; // flags, maxPreContext and maxPostContext
+        let attr_pseudo = s.read::<u8>()?;
+        let attr_break = s.read::<u8>()?;
+        let attr_bidi = s.read::<u8>()?;
+        let attr_mirror = s.read::<u8>()?;
+        let attr_pass_bits = s.read::<u8>()?;
+        let num_justs = s.read::<u8>()?;
+        s.skip(usize::from(num_justs) * 8)?;
+        let attr_lig = s.read::<u16>()?;
+        let num_user = s.read::<u8>()?;
+        s.skip(1)?; // maxCompPerLig
+        let dir = s.read::<u8>()?.wrapping_sub(1);
+        let attr_collision = s.read::<u8>()?;
+        s.skip(3)?;
+        let num_crit_features = s.read::<u8>()?;
+        s.skip(usize::from(num_crit_features) * 2 + 1)?;
+        let num_script_tags = s.read::<u8>()?;
+        s.skip(usize::from(num_script_tags) * 4 + 2)?; // scriptTags and lbGID
+        let pass_offsets: Vec<u32> = s.read_array(usize::from(num_passes) + 1)?;
+        let passes_start = *pass_offsets.first()? as usize;
+
+        let num_attrs = u32::from(num_attrs);
+        if u32::from(attr_pseudo) >= num_attrs
+            || u32::from(attr_break) >= num_attrs
+            || u32::from(attr_bidi) >= num_attrs
+            || u32::from(attr_mirror) >= num_attrs
+            || (attr_collision != 0 && u32::from(attr_collision) + 5 >= num_attrs)
+            || num_passes > 128
+            || passes_start >= data.len()
+            || positioning_pass < substitution_pass
+            || positioning_pass > num_passes
+            || substitution_pass > num_passes
+            || justification_pass < positioning_pass
+            || justification_pass > num_passes
+            || (bidi_pass != 0xFF && (bidi_pass < justification_pass || bidi_pass > num_passes))
+            || attr_lig > 127
+        {
+            return None;
+        }
+
+        let num_pseudos = s.read::<u16>()?;
+        s.skip(6)?;
+        let pseudos = (0..num_pseudos)
+            .map(|_| Some((s.read::<u32>()?, s.read::<u16>()?)))
+            .collect::<Option<_>>()?;
+
+        let class_map = data.get(s.offset..passes_start)?;
+        let (num_linear, class_offsets, class_data) =
+            Self::parse_class_map(class_map, version >= 0x0004_0000)?;
+
+        let mut passes = Vec::with_capacity(usize::from(num_passes));
+        for w in pass_offsets.windows(2) {
+            let (start, end) = (w[0] as usize, w[1] as usize);
+            if start > end || start < passes_start || end > data.len() {
+                return None;
+            }
+
+            passes.push(gr_pass_t::parse(data, start, end)?);
+        }
+
+        Some(gr_silf_t {
+            passes,
+            positioning_pass,
+            bidi_pass,
+            attr_pseudo,
+            attr_break,
+            attr_bidi,
+            attr_mirror,
+            attr_pass_bits,
+            num_user,
+            dir,
+            pseudos,
+            num_linear,
+            class_offsets,
+            class_data,
+        })
+    }
+
+    // Silf::readClassMap in graphite2
+    fn parse_class_map(data: &[u8], long_offsets: bool) -> Option<(u16, Vec<u32>, Vec<u16>)> {
+        let mut s = Reader::new(data);
+        let num_classes = s.read::<u16>()?;
+        let num_linear = s.read::<u16>()?;
+        if num_classes < num_linear {
+            return None;
+        }
+
+        let count = usize::from(num_classes) + 1;
+        let raw_offsets: Vec<u32> = if long_offsets {
+            s.read_array(count)?
+        } else {
+            s.read_array::<u16>(count)?
+                .into_iter()
+                .map(u32::from)
+                .collect()
+        };
+
+        let data_start = s.offset as u32;
+        if *raw_offsets.first()? != data_start {
+            return None;
+        }
+
+        let max_offset = (*raw_offsets.last()? - data_start) / 2;
+        if max_offset as usize > (data.len() - s.offset) / 2 {
+            return None;
+        }
+
+        let mut offsets = Vec::with_capacity(count);
+        for o in raw_offsets {
+            let o = o.checked_sub(data_start)? / 2;
+            if o > max_offset {
+                return None;
+            }
+            offsets.push(o);
+        }
+
+        if offsets[..=usize::from(num_linear)]
+            .windows(2)
+            .any(|w| w[0] > w[1])
+        {
+            return None;
+        }
+
+        let class_data: Vec<u16> = s.read_array(max_offset as usize)?;
+        for w in offsets[usize::from(num_linear)..].windows(2) {
+            let o = w[0] as usize;
+            let lookup = class_data.get(o..o + 4)?;
+            if lookup[0] == 0
+                || o + 4 + usize::from(lookup[0]) * 2 > class_data.len()
+                || lookup[3].wrapping_add(lookup[1]) != lookup[0]
+                || w[1].wrapping_sub(w[0]) & 1 != 0
+            {
+                return None;
+            }
+        }
+
+        Some((num_linear, offsets, class_data))
+    }
+
+    /// Returns the glyph a pseudo-glyph code point maps to.
+    pub fn find_pseudo(&self, c: u32) -> u16 {
+        self.pseudos.iter().find(|p| p.0 == c).map_or(0, |p| p.1)
+    }
+
+    /// Returns the index of a glyph in an input class.
+    pub fn find_class_index(&self, class: u16, glyph: u16) -> Option<u16> {
+        let start = *self.class_offsets.get(usize::from(class))? as usize;
+        let end = *self.class_offsets.get(usize::from(class) + 1)? as usize;
+        if class < self.num_linear {
+            let glyphs = self.class_data.get(start..end)?;
+            return glyphs.iter().position(|&g| g == glyph).map(|i| i as u16);
+        }
+
+        // Pairs of glyph and index, sorted by glyph.
+        let count = usize::from(self.class_data[start]);
+        let pairs = self.class_data.get(start + 4..start + 4 + count * 2)?;
+        let (mut min, mut max) = (0, count);
+        while max - min > 1 {
+            let mid = (min + max) / 2;
+            if pairs[mid * 2] > glyph {
+                max = mid;
+            } else {
+                min = mid;
+            }
+        }
+        (pairs[min * 2] == glyph).then_some(pairs[min * 2 + 1])
+    }
+
+    /// Returns the glyph at an index of an output class.
+    pub fn class_glyph(&self, class: u16, index: Option<u16>) -> u16 {
+        let (Some(&start), Some(&end), Some(index)) = (
+            self.class_offsets.get(usize::from(class)),
+            self.class_offsets.get(usize::from(class) + 1),
+            index,
+        ) else {
+            return 0;
+        };
+
+        let (start, end) = (start as usize, end as usize);
+        if class < self.num_linear {
+            if usize::from(index) < end - start {
+                return self.class_data[start + usize::from(index)];
+            }
+        } else {
+            let mut i = start + 4;
+            while i + 1 < end {
+                if self.class_data[i + 1] == index {
+                    return self.class_data[i];
+                }
+                i += 2;
+            }
+        }
+
+        0
+    }
+}
+
+/// The parsed Graphite tables of a face.
+///
+/// Parsed once and cached on [`hb_font_t`], since `Silf` tables can be large.
+pub struct gr_face_data_t {
+    pub num_glyphs: u16,
+    pub silf: gr_silf_t,
+    pub features: Vec<gr_feature_t>,
+    languages: Vec<(u32, Vec<u32>)>,
+    ascent: u16,
+    descent: u16,
+    // A decompressed `Glat` table. Uncompressed tables are read from the face.
+    glat: Option<Vec<u8>>,
+    long_gloc: bool,
+    num_attributed: usize,
+    pub num_attrs: u16,
+}
+
+#[cfg(feature = "std")]
+pub type gr_face_cache_t = std::sync::OnceLock<Option<Arc<gr_face_data_t>>>;
+#[cfg(not(feature = "std"))]
+pub type gr_face_cache_t = core::cell::OnceCell<Option<Arc<gr_face_data_t>>>;
+
+impl gr_face_data_t {
+    fn parse(font: &hb_font_t) -> Option<Self> {
+        let raw = font.raw_face();
+        let num_glyphs = font.number_of_glyphs();
+
+        // Glyph attributes. Both tables are required.
+        let gloc = raw.table(GLOC)?;
+        let mut s = Reader::new(gloc);
+        let gloc_version = s.read::<u32>()?;
+        let gloc_flags = s.read::<u16>()?;
+        let num_attrs = s.read::<u16>()?;
+        let long_gloc = gloc_flags & 1 != 0;
+        let names_len = if gloc_flags & 2 != 0 {
+            usize::from(num_attrs) * 2
+        } else {
+            0
+        };
+        let entry_size = if long_gloc { 4 } else { 2 };
+        let num_attributed =
+            (gloc.len().checked_sub(8 + names_len)? / entry_size).checked_sub(1)?;
+        if gloc_version >= 0x0002_0000
+            || num_attributed > 0xFFFF
+            || num_attrs == 0
+            || num_attrs > 0x3000
+            || usize::from(num_glyphs) > num_attributed
+        {
+            return None;
+        }
+
+        let glat_raw = raw.table(GLAT)?;
+        let glat_version = read_at::<u32>(glat_raw, 0)?;
+        if glat_version >= 0x0004_0000 || (glat_version >= 0x0003_0000 && glat_raw.len() < 8) {
+            return None;
+        }
+        let glat = match decompress(glat_raw, glat_version >= 0x0003_0000)? {
+            Cow::Owned(data) => Some(data),
+            Cow::Borrowed(_) => None,
+        };
+
+        let silf = Self::parse_silf(raw.table(SILF)?, num_attrs)?;
+
+        let features = raw.table(FEAT).map_or(Some(Vec::new()), parse_feat)?;
+        let languages = raw
+            .table(SILL)
+            .and_then(|data| parse_sill(data, &features))
+            .unwrap_or_default();
+
+        let (ascent, descent) = font.tables().os2.map_or((0, 0), |os2| {
+            (
+                os2.windows_ascender() as u16,
+                os2.windows_descender().wrapping_neg() as u16,
+            )
+        });
+
+        Some(gr_face_data_t {
+            num_glyphs,
+            silf,
+            features,
+            languages,
+            ascent,
+            descent,
+            glat,
+            long_gloc,
+            num_attributed,
+            num_attrs,
+        })
+    }
+
+    fn parse_silf(data: &[u8], num_attrs: u16) -> Option<gr_silf_t> {
+        let version = read_at::<u32>(data, 0)?;
+        if version < 0x0002_0000 {
+            return None;
+        }
+        let data = decompress(data, version >= 0x0005_0000)?;
+
+        let mut s = Reader::new(&data);
+        s.skip(4)?;
+        if version >= 0x0003_0000 {
+            s.skip(4)?; // compilerVersion
+        }
+
+        let num_subtables = s.read::<u16>()?;
+        s.skip(2)?;
+        if num_subtables == 0 {
+            return None;
+        }
+
+        let offset = s.read::<u32>()? as usize;
+        let end = if num_subtables == 1 {
+            data.len()
+        } else {
+            s.read::<u32>()? as usize
+        };
+        if end > data.len() || offset >= end {
+            return None;
+        }
+
+        gr_silf_t::parse(&data[offset..end], version, num_attrs)
+    }
+
+    /// Returns feature values for a language, or the defaults.
+    pub fn features_for_language(&self, language: u32) -> Vec<u32> {
+        let language = zeropad(language);
+        if language != 0 {
+            if let Some((_, values)) = self.languages.iter().find(|l| l.0 == language) {
+                return values.clone();
+            }
+        }
+
+        self.features.iter().map(|f| f.default).collect()
+    }
+
+    /// Returns the index of a feature id.
+    pub fn find_feature(&self, id: u32) -> Option<usize> {
+        let id = zeropad(id);
+        self.features.iter().position(|f| f.id == id)
+    }
+
+    /// Sets a feature value, unless it is out of range.
+    pub fn set_feature(&self, values: &mut [u32], index: usize, value: u32) {
+        if let (Some(feature), Some(v)) = (self.features.get(index), values.get_mut(index)) {
+            if value <= feature.max {
+                *v = value;
+            }
+        }
+    }
+}
+
+/// A Graphite face.
+pub struct gr_face_t<'a> {
+    pub font: &'a hb_font_t<'a>,
+    data: &'a gr_face_data_t,
+    glat: &'a [u8],
+    gloc: &'a [u8],
+}
+
+impl<'a> gr_face_t<'a> {
+    /// Returns the face, parsing its Graphite tables on first use.
+    pub fn new(font: &'a hb_font_t<'a>) -> Option<Self> {
+        let data = font
+            .graphite_face
+            .get_or_init(|| gr_face_data_t::parse(font).map(Arc::new))
+            .as_deref()?;
+
+        let raw = font.raw_face();
+        Some(gr_face_t {
+            font,
+            data,
+            glat: match &data.glat {
+                Some(glat) => glat,
+                None => raw.table(GLAT)?,
+            },
+            gloc: raw.table(GLOC)?,
+        })
+    }
+
+    /// Returns a glyph attribute.
+    pub fn glyph_attr(&self, glyph: u16, attr: u16) -> u16 {
+        self.find_glyph_attr(glyph, attr).unwrap_or(0) as u16
+    }
+
+    fn find_glyph_attr(&self, glyph: u16, attr: u16) -> Option<i16> {
+        if glyph >= self.num_glyphs
+            || usize::from(glyph) >= self.num_attributed
+            || attr >= self.num_attrs
+        {
+            return None;
+        }
+
+        let (start, end) = if self.long_gloc {
+            let i = 8 + usize::from(glyph) * 4;
+            (
+                read_at::<u32>(self.gloc, i)? as usize,
+                read_at::<u32>(self.gloc, i + 4)? as usize,
+            )
+        } else {
+            let i = 8 + usize::from(glyph) * 2;
+            (
+                usize::from(read_at::<u16>(self.gloc, i)?),
+                usize::from(read_at::<u16>(self.gloc, i + 2)?),
+            )
+        };
+
+        let version = read_at::<u32>(self.glat, 0)?;
+        let mut s = Reader::at(self.glat.get(..end)?, start);
+        if version >= 0x0003_0000 {
+            // Octaboxes come first.
+            let bitmap = s.read::<u16>()?;
+            s.skip(4 + 8 * bitmap.count_ones() as usize)?;
+        }
+
+        while s.offset < end {
+            let (first, count) = if version < 0x0002_0000 {
+                (u16::from(s.read::<u8>()?), u16::from(s.read::<u8>()?))
+            } else {
+                (s.read::<u16>()?, s.read::<u16>()?)
+            };
+
+            if (first..first.saturating_add(count)).contains(&attr) {
+                s.skip(usize::from(attr - first) * 2)?;
+                return s.read::<i16>();
+            }
+
+            s.skip(usize::from(count) * 2)?;
+        }
+
+        None
+    }
+
+    /// Returns the horizontal advance of a glyph.
+    pub fn glyph_advance(&self, glyph: u16) -> f32 {
+        self.font
+            .glyph_hor_advance(GlyphId(glyph))
+            .map_or(0.0, f32::from)
+    }
+
+    /// Returns the `glyf` bounding box of a glyph as left, bottom, right and top.
+    pub fn glyph_bbox(&self, glyph: u16) -> [f32; 4] {
+        self.font
+            .tables()
+            .glyf
+            .and_then(|glyf| glyf.bbox(GlyphId(glyph)))
+            .filter(|r| r.x_min <= r.x_max && r.y_min <= r.y_max)
+            .map_or([0.0; 4], |r| {
+                [
+                    f32::from(r.x_min),
+                    f32::from(r.y_min),
+                    f32::from(r.x_max),
+                    f32::from(r.y_max),
+                ]
+            })
+    }
+
+    /// Returns a glyph metric, as used by the `PUSH_GLYPH_METRIC` instruction.
+    pub fn glyph_metric(&self, glyph: u16, metric: u8) -> i32 {
+        match metric {
+            10 => return i32::from(self.ascent),
+            11 => return i32::from(self.descent),
+            _ => {}
+        }
+
+        if glyph >= self.num_glyphs {
+            return 0;
+        }
+
+        let [left, bottom, right, top] = self.glyph_bbox(glyph);
+        let advance = self.glyph_advance(glyph);
+        (match metric {
+            0 | 4 => left,
+            1 => advance - right,
+            2 => top,
+            3 => bottom,
+            5 => right,
+            6 => top - bottom,
+            7 => right - left,
+            8 => advance,
+            _ => 0.0,
+        }) as i32
+    }
+}
+
+impl core::ops::Deref for gr_face_t<'_> {
+    type Target = gr_face_data_t;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+// FeatureMap::readFeats in graphite2
+fn parse_feat(data: &[u8]) -> Option<Vec<gr_feature_t>> {
+    let mut s = Reader::new(data);
+    let version = s.read::<u32>()?;
+    let count = s.read::<u16>()?;
+    s.skip(6)?;
+
+    let mut features = Vec::with_capacity(usize::from(count));
+    for _ in 0..count {
+        let id = if version < 0x0002_0000 {
+            u32::from(s.read::<u16>()?)
+        } else {
+            s.read::<u32>()?
+        };
+        let num_settings = usize::from(s.read::<u16>()?);
+        if version >= 0x0002_0000 {
+            s.skip(2)?;
+        }
+        let offset = s.read::<u32>()? as usize;
+        s.skip(4)?; // flags and label
+
+        let mut settings = Reader::at(data, offset);
+        let mut max = 0;
+        let mut default = 0;
+        for i in 0..num_settings {
+            let value = settings.read::<i16>()?;
+            settings.skip(2)?;
+            if value as u16 as u32 > max {
+                max = u32::from(value as u16);
+            }
+            if i == 0 {
+                default = value as u32;
+            }
+        }
+
+        if num_settings == 0 {
+            max = u32::MAX;
+        }
+
+        features.push(gr_feature_t {
+            id,
+            max,
+            default: if default <= max { default } else { 0 },
+        });
+    }
+
+    Some(features)
+}
+
+// SillMap::readSill in graphite2
+fn parse_sill(data: &[u8], features: &[gr_feature_t]) -> Option<Vec<(u32, Vec<u32>)>> {
+    let mut s = Reader::new(data);
+    if data.len() < 12 || s.read::<u32>()? != 0x0001_0000 || features.is_empty() {
+        return None;
+    }
+
+    let count = s.read::<u16>()?;
+    s.skip(6)?;
+    let mut languages = Vec::with_capacity(usize::from(count));
+    for _ in 0..count {
+        let language = s.read::<u32>()?;
+        let num_settings = s.read::<u16>()?;
+        let offset = usize::from(s.read::<u16>()?);
+
+        let mut values: Vec<u32> = features.iter().map(|f| f.default).collect();
+        let set = |values: &mut Vec<u32>, id: u32, value: u32| {
+            if let Some(i) = features.iter().position(|f| f.id == id) {
+                if value <= features[i].max {
+                    values[i] = value;
+                }
+            }
+        };
+
+        let mut settings = Reader::at(data, offset);
+        for _ in 0..num_settings {
+            let id = settings.read::<u32>()?;
+            let value = settings.read::<u16>()?;
+            settings.skip(2)?;
+            set(&mut values, id, u32::from(value));
+        }
+
+        // The language itself is always feature 1.
+        set(&mut values, 1, language);
+        languages.push((language, values));
+    }
+
+    Some(languages)
+}
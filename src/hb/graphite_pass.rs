@@ -0,0 +1,397 @@
+use alloc::vec::Vec;
+
+use super::graphite_face::{gr_pass_t, gr_silf_t, MAX_RULES};
+use super::graphite_segment::{gr_segment_t, slotref};
+use super::graphite_vm;
+
+// SlotMap::MAX_SLOTS in graphite2
+const MAX_SLOTS: usize = 64;
+// MAX_SEG_GROWTH_FACTOR in graphite2
+const MAX_SEG_GROWTH_FACTOR: usize = 64;
+
+/// The slots matched by the state machine.
+///
+/// Index 0 holds the slot before the first match, so the matched slots start at 1.
+pub struct gr_slot_map_t {
+    slots: [slotref; MAX_SLOTS + 1],
+    pub size: usize,
+    pub context: u8,
+    pub highwater: slotref,
+    pub highpassed: bool,
+    pub max_size: i32,
+    pub rtl: bool,
+}
+
+impl gr_slot_map_t {
+    fn new(rtl: bool, max_size: usize) -> Self {
+        gr_slot_map_t {
+            slots: [None; MAX_SLOTS + 1],
+            size: 0,
+            context: 0,
+            highwater: None,
+            highpassed: false,
+            max_size: i32::try_from(max_size).unwrap_or(i32::MAX),
+            rtl,
+        }
+    }
+
+    fn reset(&mut self, prev: slotref, context: u8) {
+        self.slots = [None; MAX_SLOTS + 1];
+        self.slots[0] = prev;
+        self.size = 0;
+        self.context = context;
+    }
+
+    fn push(&mut self, slot: slotref) {
+        self.size += 1;
+        self.slots[self.size] = slot;
+    }
+
+    pub fn get(&self, index: usize) -> slotref {
+        self.slots.get(index).copied().flatten()
+    }
+
+    pub fn set(&mut self, index: usize, slot: slotref) {
+        if let Some(s) = self.slots.get_mut(index) {
+            *s = slot;
+        }
+    }
+
+    /// Returns the matched slots, which attachments refer to.
+    pub fn rule_slots(&self) -> &[slotref] {
+        &self.slots[1..=self.size]
+    }
+
+    // SlotMap::collectGarbage in graphite2
+    fn collect_garbage(&mut self, seg: &mut gr_segment_t, slot: &mut slotref) {
+        for i in 1..self.size {
+            let Some(s) = self.slots[i] else {
+                continue;
+            };
+
+            let s_ = &seg.slots[s];
+            if s_.is_deleted() || s_.is_copied() {
+                if *slot == Some(s) {
+                    *slot = s_.prev.or(s_.next);
+                }
+                seg.free_slot(s);
+            }
+        }
+    }
+}
+
+struct gr_pass_runner_t<'a, 'b, 'c> {
+    pass: &'a gr_pass_t,
+    seg: &'a mut gr_segment_t<'b>,
+    smap: &'c mut gr_slot_map_t,
+    rules: Vec<u16>,
+}
+
+impl gr_pass_runner_t<'_, '_, '_> {
+    // Pass::runFSM in graphite2
+    fn run_fsm(&mut self, mut slot: usize) -> bool {
+        self.rules.clear();
+        let mut context = 0;
+        while context != self.pass.max_pre_context {
+            match self.seg.slots[slot].prev {
+                Some(prev) => slot = prev,
+                None => break,
+            }
+            context += 1;
+        }
+
+        self.smap.reset(self.seg.slots[slot].prev, context);
+        if context < self.pass.min_pre_context {
+            return false;
+        }
+
+        let mut state = self.pass.start_state(context);
+        let mut free_slots = MAX_SLOTS;
+        let mut slot = Some(slot);
+        while let Some(s) = slot {
+            self.smap.push(slot);
+            let Some(column) = self.pass.column(self.seg.slots[s].glyph) else {
+                return true;
+            };
+
+            free_slots -= 1;
+            if free_slots == 0 {
+                return false;
+            }
+
+            let Some(next) = self.pass.transition(state, column) else {
+                return true;
+            };
+
+            state = next;
+            self.accumulate_rules(state);
+            if state == 0 {
+                break;
+            }
+            slot = self.seg.slots[s].next;
+        }
+
+        self.smap.push(None);
+        true
+    }
+
+    // Rules are preferred by length and then by order.
+    fn accumulate_rules(&mut self, state: u16) {
+        let rules = self.pass.rules_for(state);
+        if rules.is_empty() {
+            return;
+        }
+
+        self.rules.extend_from_slice(rules);
+        let pass = self.pass;
+        self.rules.sort_by(|&a, &b| {
+            let sort = |i: u16| pass.rules[usize::from(i)].sort;
+            sort(b).cmp(&sort(a)).then(a.cmp(&b))
+        });
+        self.rules.dedup();
+        self.rules.truncate(MAX_RULES);
+    }
+
+    // Pass::testConstraint in graphite2
+    fn test_constraint(&mut self, rule: u16) -> Option<bool> {
+        let pass = self.pass;
+        let rule = &pass.rules[usize::from(rule)];
+        let context = usize::from(self.smap.context);
+        let sort = usize::from(rule.sort);
+        let Some(start) = context.checked_sub(usize::from(rule.pre_context)) else {
+            return Some(false);
+        };
+
+        if sort + start > self.smap.size || sort == 0 || self.smap.get(start + sort).is_none() {
+            return Some(false);
+        }
+
+        if rule.constraint.is_empty() {
+            return Some(true);
+        }
+
+        let mut map = start + 1;
+        for _ in 0..sort {
+            if self.smap.get(map).is_some() {
+                let ret = graphite_vm::run(self.seg, self.smap, &rule.constraint, &mut map)?;
+                if ret == 0 {
+                    return Some(false);
+                }
+            }
+            map += 1;
+        }
+
+        Some(true)
+    }
+
+    // Pass::findNDoRule in graphite2
+    fn find_and_do_rule(&mut self, slot: &mut slotref) -> Option<()> {
+        let current = slot.unwrap();
+        if self.run_fsm(current) {
+            for i in 0..self.rules.len() {
+                let index = self.rules[i];
+                if !self.test_constraint(index)? {
+                    continue;
+                }
+
+                let pass = self.pass;
+                let action = &pass.rules[usize::from(index)].action;
+                let delta = self.do_action(action, slot)?;
+                if graphite_vm::deletes(action) {
+                    self.smap.collect_garbage(self.seg, slot);
+                }
+                self.adjust_slot(delta, slot);
+                return Some(());
+            }
+        }
+
+        *slot = self.seg.slots[current].next;
+        Some(())
+    }
+
+    // Pass::doAction in graphite2
+    fn do_action(&mut self, code: &[u8], slot: &mut slotref) -> Option<i32> {
+        if code.is_empty() {
+            return Some(0);
+        }
+
+        let mut map = usize::from(self.smap.context) + 1;
+        self.smap.highpassed = false;
+        let ret = graphite_vm::run(self.seg, self.smap, code, &mut map)?;
+        *slot = self.smap.get(map);
+        Some(ret)
+    }
+
+    // Pass::adjustSlot in graphite2
+    fn adjust_slot(&mut self, mut delta: i32, slot: &mut slotref) {
+        let smap = &mut *self.smap;
+        if slot.is_none() {
+            if smap.highpassed || smap.highwater.is_none() {
+                *slot = self.seg.last;
+                delta += 1;
+                if smap.highwater.is_none() || smap.highwater == *slot {
+                    smap.highpassed = false;
+                }
+            } else {
+                *slot = self.seg.first;
+                delta -= 1;
+            }
+        }
+
+        while delta < 0 {
+            let Some(s) = *slot else {
+                break;
+            };
+            delta += 1;
+            *slot = self.seg.slots[s].prev;
+            if smap.highpassed && smap.highwater == *slot {
+                smap.highpassed = false;
+            }
+        }
+
+        while delta > 0 {
+            let Some(s) = *slot else {
+                break;
+            };
+            delta -= 1;
+            if *slot == smap.highwater {
+                smap.highpassed = true;
+            }
+            *slot = self.seg.slots[s].next;
+        }
+    }
+
+    // Pass::runGraphite in graphite2
+    fn run(&mut self, reverse: bool) -> Option<()> {
+        let Some(mut slot) = self.seg.first else {
+            return Some(());
+        };
+
+        if !self.pass.constraint.is_empty() {
+            self.smap.set(1, Some(slot));
+            let mut map = 1;
+            let ret = graphite_vm::run(self.seg, self.smap, &self.pass.constraint, &mut map)?;
+            if ret == 0 {
+                return Some(());
+            }
+        }
+
+        if reverse {
+            self.seg.reverse_slots();
+            slot = self.seg.first?;
+        }
+
+        if self.pass.rules.is_empty() {
+            return Some(());
+        }
+
+        self.smap.highwater = self.seg.slots[slot].next;
+        let max_loop = i32::from(self.pass.max_loop);
+        let mut loop_count = max_loop;
+        let mut slot = Some(slot);
+        while slot.is_some() {
+            self.find_and_do_rule(&mut slot)?;
+            if slot.is_some()
+                && (slot == self.smap.highwater || self.smap.highpassed || {
+                    loop_count -= 1;
+                    loop_count == 0
+                })
+            {
+                if loop_count == 0 {
+                    slot = self.smap.highwater;
+                }
+                loop_count = max_loop;
+                if let Some(s) = slot {
+                    self.smap.highwater = self.seg.slots[s].next;
+                }
+            }
+        }
+
+        Some(())
+    }
+}
+
+// Silf::runGraphite in graphite2. Passes from `first` to `last` run, along with the bidi
+// pass when it falls within them.
+fn run_passes(
+    silf: &gr_silf_t,
+    seg: &mut gr_segment_t,
+    first: u8,
+    mut last: u8,
+    do_bidi: bool,
+) -> Option<()> {
+    let max_size = seg.slot_count * MAX_SEG_GROWTH_FACTOR;
+    let mut smap = gr_slot_map_t::new(silf.dir != 0, max_size);
+    let num_passes = silf.passes.len() as u8;
+    let mut bidi = silf.bidi_pass;
+
+    if last == 0 {
+        if first == last && bidi == 0xFF {
+            return Some(());
+        }
+        last = num_passes;
+    }
+
+    let (first, mut last) = (usize::from(first), usize::from(last));
+    let mut bidi_pass = usize::from(bidi);
+    if (first < bidi_pass || (do_bidi && first == bidi_pass))
+        && (last >= bidi_pass || (do_bidi && last + 1 == bidi_pass))
+    {
+        last += 1;
+    } else {
+        bidi = 0xFF;
+        bidi_pass = usize::MAX;
+    }
+
+    let mut i = first;
+    while i < last {
+        if i == bidi_pass {
+            if seg.curr_dir() != (silf.dir & 1 != 0) {
+                seg.reverse_slots();
+            }
+            if silf.attr_mirror != 0 && seg.dir & 3 == 3 {
+                seg.do_mirror(silf.attr_mirror);
+            }
+            bidi_pass = last;
+            last -= 1;
+            continue;
+        }
+
+        let pass = silf.passes.get(i)?;
+        let reverse = bidi == 0xFF && seg.curr_dir() != ((silf.dir & 1 != 0) ^ pass.reverse_dir);
+        if i >= 32 || seg.pass_bits & (1 << i) == 0 {
+            let mut runner = gr_pass_runner_t {
+                pass,
+                seg: &mut *seg,
+                smap: &mut smap,
+                rules: Vec::new(),
+            };
+            runner.run(reverse)?;
+        }
+
+        if seg.slot_count > max_size {
+            return None;
+        }
+        i += 1;
+    }
+
+    Some(())
+}
+
+// Face::runGraphite in graphite2, without collision avoidance.
+pub fn run_graphite(seg: &mut gr_segment_t) -> Option<()> {
+    let silf = &seg.face.silf;
+    if seg.dir & 3 == 3 && silf.bidi_pass == 0xFF {
+        seg.do_mirror(silf.attr_mirror);
+    }
+
+    run_passes(silf, seg, 0, silf.positioning_pass, true)?;
+    seg.associate_chars();
+    run_passes(
+        silf,
+        seg,
+        silf.positioning_pass,
+        silf.passes.len() as u8,
+        false,
+    )
+}
@@ -0,0 +1,932 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::{Add, AddAssign, Sub};
+
+use super::graphite_face::gr_face_t;
+
+// A segment is a doubly linked list of slots, one glyph each, over the characters it was
+// made from. Slots live in an arena and are referred to by index.
+
+pub type slotref = Option<usize>;
+
+// Slot flags
+const DELETED: u8 = 1;
+const INSERTED: u8 = 2;
+const COPIED: u8 = 4;
+
+// Bidi class of non-spacing marks, which stay after their base when slots are reversed.
+const BIDI_NSM: i8 = 16;
+
+// Attributes of `gr_attrCode` in graphite2.
+pub mod attr {
+    pub const ADV_X: u8 = 0;
+    pub const ADV_Y: u8 = 1;
+    pub const ATT_TO: u8 = 2;
+    pub const ATT_X: u8 = 3;
+    pub const ATT_Y: u8 = 4;
+    pub const ATT_WITH_X: u8 = 8;
+    pub const ATT_WITH_Y: u8 = 9;
+    pub const ATT_LEVEL: u8 = 13;
+    pub const BREAK: u8 = 14;
+    pub const DIR: u8 = 16;
+    pub const INSERT: u8 = 17;
+    pub const POS_X: u8 = 18;
+    pub const POS_Y: u8 = 19;
+    pub const SHIFT_X: u8 = 20;
+    pub const SHIFT_Y: u8 = 21;
+    pub const USER_DEFN_V1: u8 = 22;
+    pub const MEASURE_SOL: u8 = 23;
+    pub const MEASURE_EOL: u8 = 24;
+    pub const J_STRETCH: u8 = 25;
+    pub const J_WIDTH: u8 = 29;
+    pub const SEG_SPLIT: u8 = 54;
+    pub const USER_DEFN: u8 = 55;
+    pub const BIDI_LEVEL: u8 = 56;
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct gr_position_t {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl gr_position_t {
+    pub fn new(x: f32, y: f32) -> Self {
+        gr_position_t { x, y }
+    }
+}
+
+impl Add for gr_position_t {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        gr_position_t::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl AddAssign for gr_position_t {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for gr_position_t {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        gr_position_t::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+/// A bounding box as bottom left and top right corners.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct gr_rect_t {
+    pub bl: gr_position_t,
+    pub tr: gr_position_t,
+}
+
+impl gr_rect_t {
+    fn widen(self, other: gr_rect_t) -> gr_rect_t {
+        gr_rect_t {
+            bl: gr_position_t::new(self.bl.x.min(other.bl.x), self.bl.y.min(other.bl.y)),
+            tr: gr_position_t::new(self.tr.x.max(other.tr.x), self.tr.y.max(other.tr.y)),
+        }
+    }
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct gr_slot_t {
+    pub glyph: u16,
+    pub real_glyph: u16,
+    pub original: i32,
+    pub before: i32,
+    pub after: i32,
+    pub index: i32,
+    pub prev: slotref,
+    pub next: slotref,
+    pub parent: slotref,
+    pub child: slotref,
+    pub sibling: slotref,
+    pub position: gr_position_t,
+    pub shift: gr_position_t,
+    pub advance: gr_position_t,
+    pub attach: gr_position_t,
+    pub with: gr_position_t,
+    pub just: f32,
+    pub flags: u8,
+    pub att_level: u8,
+    pub bidi_class: i8,
+    pub bidi_level: u8,
+    pub user_attrs: Vec<i16>,
+}
+
+impl gr_slot_t {
+    fn new(num_user: u8) -> Self {
+        gr_slot_t {
+            bidi_class: -1,
+            user_attrs: vec![0; usize::from(num_user)],
+            ..gr_slot_t::default()
+        }
+    }
+
+    /// Returns the glyph that is displayed, which differs for pseudo-glyphs.
+    pub fn display_glyph(&self) -> u16 {
+        if self.real_glyph != 0 {
+            self.real_glyph
+        } else {
+            self.glyph
+        }
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.flags & DELETED != 0
+    }
+
+    pub fn is_copied(&self) -> bool {
+        self.flags & COPIED != 0
+    }
+
+    pub fn can_insert_before(&self) -> bool {
+        self.flags & INSERTED == 0
+    }
+
+    pub fn mark_deleted(&mut self, state: bool) {
+        if state {
+            self.flags |= DELETED;
+        } else {
+            self.flags &= !DELETED;
+        }
+    }
+
+    pub fn mark_copied(&mut self, state: bool) {
+        if state {
+            self.flags |= COPIED;
+        } else {
+            self.flags &= !COPIED;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct gr_char_info_t {
+    pub before: i32,
+    pub after: i32,
+    pub break_weight: i16,
+    pub flags: u8,
+}
+
+pub struct gr_segment_t<'a> {
+    pub face: &'a gr_face_t<'a>,
+    pub slots: Vec<gr_slot_t>,
+    pub first: slotref,
+    pub last: slotref,
+    pub chars: Vec<gr_char_info_t>,
+    pub features: Vec<u32>,
+    pub dir: u32,
+    pub pass_bits: u32,
+    pub slot_count: usize,
+    pub advance: gr_position_t,
+}
+
+impl<'a> gr_segment_t<'a> {
+    /// Creates a segment with a slot for each character.
+    pub fn new(face: &'a gr_face_t<'a>, text: &[u32], features: Vec<u32>, rtl: bool) -> Self {
+        let silf = &face.silf;
+        let mut seg = gr_segment_t {
+            face,
+            slots: Vec::with_capacity(text.len()),
+            first: None,
+            last: None,
+            chars: Vec::with_capacity(text.len()),
+            features,
+            dir: 2 | u32::from(rtl),
+            pass_bits: if silf.attr_pass_bits != 0 {
+                u32::MAX
+            } else {
+                0
+            },
+            slot_count: 0,
+            advance: gr_position_t::default(),
+        };
+
+        for (i, &c) in text.iter().enumerate() {
+            let mut glyph = char::from_u32(c)
+                .and_then(|c| face.font.glyph_index(c))
+                .map_or(0, |g| g.0);
+            if glyph == 0 {
+                glyph = silf.find_pseudo(c);
+            }
+            seg.append_slot(i as i32, glyph);
+        }
+
+        seg
+    }
+
+    fn append_slot(&mut self, index: i32, glyph: u16) {
+        let silf = &self.face.silf;
+        let exists = glyph < self.face.num_glyphs;
+        self.chars.push(gr_char_info_t {
+            break_weight: if exists {
+                self.glyph_attr(glyph, u16::from(silf.attr_break)) as i16
+            } else {
+                0
+            },
+            ..gr_char_info_t::default()
+        });
+
+        let slot = self.new_slot();
+        self.slot_count += 1;
+        self.set_glyph(slot, glyph);
+        let s = &mut self.slots[slot];
+        s.original = index;
+        s.before = index;
+        s.after = index;
+        s.prev = self.last;
+        if let Some(last) = self.last {
+            self.slots[last].next = Some(slot);
+        }
+        self.last = Some(slot);
+        if self.first.is_none() {
+            self.first = Some(slot);
+        }
+
+        if exists && silf.attr_pass_bits != 0 {
+            let attr = u16::from(silf.attr_pass_bits);
+            let mut bits = u32::from(self.glyph_attr(glyph, attr));
+            if silf.passes.len() > 16 {
+                bits |= u32::from(self.glyph_attr(glyph, attr + 1)) << 16;
+            }
+            self.pass_bits &= bits;
+        }
+    }
+
+    /// Allocates a detached slot.
+    pub fn new_slot(&mut self) -> usize {
+        self.slots.push(gr_slot_t::new(self.face.silf.num_user));
+        self.slots.len() - 1
+    }
+
+    /// Returns the number of slots in the list.
+    pub fn num_slots(&self) -> usize {
+        let mut count = 0;
+        let mut slot = self.first;
+        while let Some(s) = slot {
+            count += 1;
+            slot = self.slots[s].next;
+        }
+        count
+    }
+
+    pub fn glyph_attr(&self, glyph: u16, attr: u16) -> u16 {
+        self.face.glyph_attr(glyph, attr)
+    }
+
+    /// Checks whether the segment is currently in right-to-left order.
+    pub fn curr_dir(&self) -> bool {
+        ((self.dir >> 6) ^ self.dir) & 1 != 0
+    }
+
+    pub fn set_glyph(&mut self, slot: usize, glyph: u16) {
+        let face = self.face;
+        let silf = &face.silf;
+        let s = &mut self.slots[slot];
+        s.glyph = glyph;
+        s.bidi_class = -1;
+        if glyph >= face.num_glyphs {
+            s.real_glyph = 0;
+            s.advance = gr_position_t::default();
+            return;
+        }
+
+        s.real_glyph = face.glyph_attr(glyph, u16::from(silf.attr_pseudo));
+        if s.real_glyph > face.num_glyphs {
+            s.real_glyph = 0;
+        }
+
+        let shown = if s.real_glyph != 0 && s.real_glyph < face.num_glyphs {
+            s.real_glyph
+        } else {
+            glyph
+        };
+        s.advance = gr_position_t::new(face.glyph_advance(shown), 0.0);
+
+        if silf.attr_pass_bits != 0 {
+            let attr = u16::from(silf.attr_pass_bits);
+            self.pass_bits &= u32::from(face.glyph_attr(glyph, attr) as u8);
+            if silf.passes.len() > 16 {
+                self.pass_bits &= u32::from(face.glyph_attr(glyph, attr + 1)) << 16;
+            }
+        }
+    }
+
+    // Slot::getAttr in graphite2
+    pub fn get_attr(&self, slot: usize, attr: u8, index: u8) -> i32 {
+        let s = &self.slots[slot];
+        if (attr::J_STRETCH..attr::J_STRETCH + 20).contains(&attr) && attr != attr::J_WIDTH {
+            // Justification is not supported.
+            return 0;
+        }
+
+        match attr {
+            attr::ADV_X => s.advance.x as i32,
+            attr::ADV_Y => s.advance.y as i32,
+            attr::ATT_TO => i32::from(s.parent.is_some()),
+            attr::ATT_X => s.attach.x as i32,
+            attr::ATT_Y => s.attach.y as i32,
+            attr::ATT_WITH_X => s.with.x as i32,
+            attr::ATT_WITH_Y => s.with.y as i32,
+            attr::ATT_LEVEL => i32::from(s.att_level),
+            attr::BREAK => self
+                .char_info(s.original)
+                .map_or(0, |c| c.break_weight.into()),
+            attr::DIR => (self.dir & 1) as i32,
+            attr::INSERT => i32::from(s.can_insert_before()),
+            attr::POS_X => s.position.x as i32,
+            attr::POS_Y => s.position.y as i32,
+            attr::SHIFT_X => s.shift.x as i32,
+            attr::SHIFT_Y => s.shift.y as i32,
+            attr::MEASURE_SOL | attr::MEASURE_EOL => -1,
+            attr::J_WIDTH => s.just as i32,
+            attr::USER_DEFN_V1 => s.user_attrs.first().copied().map_or(0, i32::from),
+            attr::USER_DEFN => s
+                .user_attrs
+                .get(usize::from(index))
+                .copied()
+                .map_or(0, i32::from),
+            attr::SEG_SPLIT => self
+                .char_info(s.original)
+                .map_or(0, |c| i32::from(c.flags & 3)),
+            attr::BIDI_LEVEL => i32::from(s.bidi_level),
+            _ => 0,
+        }
+    }
+
+    // Slot::setAttr in graphite2. `map` holds the slots of the current rule.
+    pub fn set_attr(
+        &mut self,
+        slot: usize,
+        mut attr: u8,
+        mut index: u8,
+        value: i16,
+        map: &[slotref],
+        map_rtl: bool,
+    ) {
+        if attr == attr::USER_DEFN_V1 {
+            attr = attr::USER_DEFN;
+            index = 0;
+        } else if (attr::J_STRETCH..attr::J_STRETCH + 20).contains(&attr) && attr != attr::J_WIDTH {
+            return;
+        }
+
+        let value_f = f32::from(value);
+        let original = self.slots[slot].original;
+        match attr {
+            attr::ADV_X => self.slots[slot].advance.x = value_f,
+            attr::ADV_Y => self.slots[slot].advance.y = value_f,
+            attr::ATT_TO => {
+                let idx = value as u16;
+                let Some(Some(other)) = map.get(usize::from(idx)).copied() else {
+                    return;
+                };
+
+                let s = &self.slots[slot];
+                if other == slot || Some(other) == s.parent || self.slots[other].is_copied() {
+                    return;
+                }
+
+                if let Some(parent) = s.parent {
+                    self.remove_child(parent, slot);
+                    self.slots[slot].parent = None;
+                }
+
+                let mut count = 0;
+                let mut found = false;
+                let mut p = Some(other);
+                while let Some(i) = p {
+                    count += 1;
+                    if i == slot {
+                        found = true;
+                    }
+                    p = self.slots[i].parent;
+                }
+                let mut p = self.slots[slot].child;
+                while let Some(i) = p {
+                    count += 1;
+                    p = self.slots[i].child;
+                }
+                let mut p = self.slots[slot].sibling;
+                while let Some(i) = p {
+                    count += 1;
+                    p = self.slots[i].sibling;
+                }
+
+                if count < 100 && !found && self.add_child(other, slot) {
+                    self.slots[slot].parent = Some(other);
+                    if map_rtl ^ (idx > u16::from(index)) {
+                        self.slots[slot].with = gr_position_t::new(self.slots[slot].advance.x, 0.0);
+                    } else {
+                        self.slots[slot].attach =
+                            gr_position_t::new(self.slots[other].advance.x, 0.0);
+                    }
+                }
+            }
+            attr::ATT_X => self.slots[slot].attach.x = value_f,
+            attr::ATT_Y => self.slots[slot].attach.y = value_f,
+            attr::ATT_WITH_X => self.slots[slot].with.x = value_f,
+            attr::ATT_WITH_Y => self.slots[slot].with.y = value_f,
+            attr::ATT_LEVEL => self.slots[slot].att_level = value as u8,
+            attr::BREAK => {
+                if let Some(c) = self.char_info_mut(original) {
+                    c.break_weight = value;
+                }
+            }
+            attr::INSERT => {
+                if value != 0 {
+                    self.slots[slot].flags &= !INSERTED;
+                } else {
+                    self.slots[slot].flags |= INSERTED;
+                }
+            }
+            attr::SHIFT_X => self.slots[slot].shift.x = value_f,
+            attr::SHIFT_Y => self.slots[slot].shift.y = value_f,
+            attr::J_WIDTH => self.slots[slot].just = value_f,
+            attr::SEG_SPLIT => {
+                if let Some(c) = self.char_info_mut(original) {
+                    c.flags |= (value & 3) as u8;
+                }
+            }
+            attr::USER_DEFN => {
+                if let Some(v) = self.slots[slot].user_attrs.get_mut(usize::from(index)) {
+                    *v = value;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn char_info(&self, index: i32) -> Option<&gr_char_info_t> {
+        usize::try_from(index).ok().and_then(|i| self.chars.get(i))
+    }
+
+    fn char_info_mut(&mut self, index: i32) -> Option<&mut gr_char_info_t> {
+        usize::try_from(index)
+            .ok()
+            .and_then(|i| self.chars.get_mut(i))
+    }
+
+    // Slot::child in graphite2
+    pub fn add_child(&mut self, parent: usize, slot: usize) -> bool {
+        if parent == slot {
+            return false;
+        }
+
+        match self.slots[parent].child {
+            Some(child) if child == slot => true,
+            Some(child) => self.add_sibling(child, Some(slot)),
+            None => {
+                self.slots[parent].child = Some(slot);
+                true
+            }
+        }
+    }
+
+    // Slot::sibling in graphite2
+    fn add_sibling(&mut self, mut s: usize, ap: slotref) -> bool {
+        loop {
+            if Some(s) == ap {
+                return false;
+            }
+
+            match self.slots[s].sibling {
+                sibling if sibling == ap => return true,
+                Some(sibling) if ap.is_some() => s = sibling,
+                _ => {
+                    self.slots[s].sibling = ap;
+                    return true;
+                }
+            }
+        }
+    }
+
+    // Slot::removeChild in graphite2
+    fn remove_child(&mut self, parent: usize, ap: usize) -> bool {
+        let Some(child) = self.slots[parent].child else {
+            return false;
+        };
+
+        if parent == ap {
+            return false;
+        }
+
+        if child == ap {
+            self.slots[parent].child = self.slots[child].sibling;
+            self.slots[child].sibling = None;
+            return true;
+        }
+
+        let mut p = Some(child);
+        while let Some(i) = p {
+            if self.slots[i].sibling == Some(ap) {
+                self.slots[i].sibling = self.slots[ap].sibling;
+                self.slots[ap].sibling = None;
+                return true;
+            }
+            p = self.slots[i].sibling;
+        }
+
+        false
+    }
+
+    /// Detaches a slot that has been removed from the list.
+    pub fn free_slot(&mut self, slot: usize) {
+        if self.last == Some(slot) {
+            self.last = self.slots[slot].prev;
+        }
+        if self.first == Some(slot) {
+            self.first = self.slots[slot].next;
+        }
+        if let Some(parent) = self.slots[slot].parent {
+            self.remove_child(parent, slot);
+        }
+        while let Some(child) = self.slots[slot].child {
+            if self.slots[child].parent == Some(slot) {
+                self.slots[child].parent = None;
+                self.remove_child(slot, child);
+            } else {
+                self.slots[slot].child = None;
+            }
+        }
+
+        self.slots[slot] = gr_slot_t::new(self.face.silf.num_user);
+    }
+
+    fn bidi_class(&mut self, slot: usize) -> i8 {
+        let s = &self.slots[slot];
+        if s.bidi_class != -1 {
+            return s.bidi_class;
+        }
+
+        let class = self.glyph_attr(s.glyph, u16::from(self.face.silf.attr_bidi)) as i8;
+        self.slots[slot].bidi_class = class;
+        class
+    }
+
+    // Segment::reverseSlots in graphite2. Marks are kept after their bases.
+    pub fn reverse_slots(&mut self) {
+        self.dir ^= 64;
+        if self.first == self.last {
+            return;
+        }
+
+        let mut curr = self.first;
+        while let Some(c) = curr {
+            if self.bidi_class(c) != BIDI_NSM {
+                break;
+            }
+            curr = self.slots[c].next;
+        }
+
+        let Some(c) = curr else {
+            return;
+        };
+
+        let t_first = self.slots[c].prev;
+        let mut t_last = c;
+        let mut out: slotref = None;
+        while let Some(c) = curr {
+            let t;
+            if self.bidi_class(c) == BIDI_NSM {
+                let mut d = self.slots[c].next;
+                while let Some(i) = d {
+                    if self.bidi_class(i) != BIDI_NSM {
+                        break;
+                    }
+                    d = self.slots[i].next;
+                }
+                let d = match d {
+                    Some(i) => self.slots[i].prev.unwrap(),
+                    None => self.last.unwrap(),
+                };
+
+                let o = out.unwrap();
+                let p = self.slots[o].next;
+                match p {
+                    Some(p) => self.slots[p].prev = Some(d),
+                    None => t_last = d,
+                }
+                t = self.slots[d].next;
+                self.slots[d].next = p;
+                self.slots[c].prev = Some(o);
+                self.slots[o].next = Some(c);
+            } else {
+                if let Some(o) = out {
+                    self.slots[o].prev = Some(c);
+                }
+                t = self.slots[c].next;
+                self.slots[c].next = out;
+                out = Some(c);
+            }
+            curr = t;
+        }
+
+        let out = out.unwrap();
+        self.slots[out].prev = t_first;
+        match t_first {
+            Some(f) => self.slots[f].next = Some(out),
+            None => self.first = Some(out),
+        }
+        self.last = Some(t_last);
+    }
+
+    // Segment::doMirror in graphite2
+    pub fn do_mirror(&mut self, attr: u8) {
+        let attr = u16::from(attr);
+        let mut s = self.first;
+        while let Some(i) = s {
+            let glyph = self.slots[i].glyph;
+            let g = self.glyph_attr(glyph, attr);
+            if g != 0 && (self.dir & 4 == 0 || self.glyph_attr(glyph, attr + 1) == 0) {
+                self.set_glyph(i, g);
+            }
+            s = self.slots[i].next;
+        }
+    }
+
+    // Segment::associateChars in graphite2
+    pub fn associate_chars(&mut self) {
+        for c in &mut self.chars {
+            c.before = -1;
+            c.after = -1;
+        }
+
+        let mut i = 0;
+        let mut s = self.first;
+        while let Some(si) = s {
+            let slot = &self.slots[si];
+            let (before, after) = (slot.before, slot.after);
+            if before >= 0 {
+                for j in before..=after {
+                    if let Some(c) = self.char_info_mut(j) {
+                        if c.before == -1 || i < c.before {
+                            c.before = i;
+                        }
+                        if c.after < i {
+                            c.after = i;
+                        }
+                    }
+                }
+            }
+            self.slots[si].index = i;
+            i += 1;
+            s = self.slots[si].next;
+        }
+
+        let num_chars = self.chars.len() as i32;
+        let mut s = self.first;
+        while let Some(si) = s {
+            let index = self.slots[si].index;
+            let mut a = self.slots[si].after + 1;
+            while a < num_chars && self.chars[a as usize].after < 0 {
+                self.chars[a as usize].after = index;
+                a += 1;
+            }
+            self.slots[si].after = a - 1;
+
+            let mut a = self.slots[si].before - 1;
+            while a >= 0 && self.chars[a as usize].before < 0 {
+                self.chars[a as usize].before = index;
+                a -= 1;
+            }
+            self.slots[si].before = a + 1;
+            s = self.slots[si].next;
+        }
+    }
+
+    // Segment::positionSlots in graphite2
+    pub fn position_slots(
+        &mut self,
+        mut start: slotref,
+        mut end: slotref,
+        rtl: bool,
+    ) -> gr_position_t {
+        let mut pos = gr_position_t::default();
+        let mut cluster_min;
+        let mut bbox = gr_rect_t::default();
+        let reorder = self.curr_dir() != rtl;
+        if reorder {
+            self.reverse_slots();
+            core::mem::swap(&mut start, &mut end);
+        }
+
+        let (Some(start), Some(end)) = (start.or(self.first), end.or(self.last)) else {
+            return pos;
+        };
+
+        if rtl {
+            let stop = self.slots[start].prev;
+            let mut s = Some(end);
+            while let Some(i) = s.filter(|&i| Some(i) != stop) {
+                if self.slots[i].parent.is_none() {
+                    cluster_min = pos.x;
+                    pos = self.finalise_slot(i, pos, &mut bbox, 0, &mut cluster_min, rtl, 0);
+                }
+                s = self.slots[i].prev;
+            }
+        } else {
+            let stop = self.slots[end].next;
+            let mut s = Some(start);
+            while let Some(i) = s.filter(|&i| Some(i) != stop) {
+                if self.slots[i].parent.is_none() {
+                    cluster_min = pos.x;
+                    pos = self.finalise_slot(i, pos, &mut bbox, 0, &mut cluster_min, rtl, 0);
+                }
+                s = self.slots[i].next;
+            }
+        }
+
+        if reorder {
+            self.reverse_slots();
+        }
+
+        pos
+    }
+
+    // Slot::finalise in graphite2, without scaling and collision offsets.
+    #[allow(clippy::too_many_arguments)]
+    fn finalise_slot(
+        &mut self,
+        slot: usize,
+        base: gr_position_t,
+        bbox: &mut gr_rect_t,
+        attr_level: u8,
+        cluster_min: &mut f32,
+        rtl: bool,
+        depth: u32,
+    ) -> gr_position_t {
+        let s = &self.slots[slot];
+        if depth > 100 || (attr_level != 0 && s.att_level > attr_level) {
+            return gr_position_t::default();
+        }
+
+        let shift =
+            gr_position_t::new(s.shift.x * if rtl { -1.0 } else { 1.0 } + s.just, s.shift.y);
+        let t_advance = s.advance.x + s.just;
+        let glyph = s.display_glyph();
+
+        let mut position = base + shift;
+        let mut res;
+        if s.parent.is_none() {
+            res = base + gr_position_t::new(t_advance, s.advance.y);
+            *cluster_min = position.x;
+        } else {
+            position += s.attach - s.with;
+            let t_adv = if s.advance.x >= 0.5 {
+                position.x + t_advance - shift.x
+            } else {
+                0.0
+            };
+            res = gr_position_t::new(t_adv, 0.0);
+            if (s.advance.x >= 0.5 || position.x < 0.0) && position.x < *cluster_min {
+                *cluster_min = position.x;
+            }
+        }
+        self.slots[slot].position = position;
+
+        if glyph < self.face.num_glyphs {
+            let [left, bottom, right, top] = self.face.glyph_bbox(glyph);
+            let ours = gr_rect_t {
+                bl: gr_position_t::new(left, bottom) + position,
+                tr: gr_position_t::new(right, top) + position,
+            };
+            *bbox = bbox.widen(ours);
+        }
+
+        let s = &self.slots[slot];
+        let (parent, child, sibling, advance) = (s.parent, s.child, s.sibling, s.advance.x);
+        if let Some(child) = child.filter(|&c| c != slot && self.slots[c].parent == Some(slot)) {
+            let t = self.finalise_slot(
+                child,
+                position,
+                bbox,
+                attr_level,
+                cluster_min,
+                rtl,
+                depth + 1,
+            );
+            if (parent.is_none() || advance >= 0.5) && t.x > res.x {
+                res = t;
+            }
+        }
+
+        if let Some(sibling) = sibling.filter(|&s| s != slot && self.slots[s].parent == parent) {
+            if parent.is_some() {
+                let t = self.finalise_slot(
+                    sibling,
+                    base,
+                    bbox,
+                    attr_level,
+                    cluster_min,
+                    rtl,
+                    depth + 1,
+                );
+                if t.x > res.x {
+                    res = t;
+                }
+            }
+        }
+
+        if parent.is_none() && *cluster_min < base.x {
+            let adj = gr_position_t::new(self.slots[slot].position.x - *cluster_min, 0.0);
+            res += adj;
+            self.slots[slot].position += adj;
+            if let Some(child) = child {
+                self.flood_shift(child, adj, 0);
+            }
+        }
+
+        res
+    }
+
+    fn flood_shift(&mut self, slot: usize, adj: gr_position_t, depth: u32) {
+        if depth > 100 {
+            return;
+        }
+
+        self.slots[slot].position += adj;
+        if let Some(child) = self.slots[slot].child {
+            self.flood_shift(child, adj, depth + 1);
+        }
+        if let Some(sibling) = self.slots[slot].sibling {
+            self.flood_shift(sibling, adj, depth + 1);
+        }
+    }
+
+    /// Returns the root of an attachment tree.
+    pub fn find_root(&self, mut slot: usize) -> usize {
+        let mut depth = 0;
+        while let Some(parent) = self.slots[slot].parent {
+            slot = parent;
+            depth += 1;
+            if depth > 100 {
+                break;
+            }
+        }
+        slot
+    }
+
+    // Segment::getGlyphMetric in graphite2
+    pub fn glyph_metric(&mut self, slot: usize, metric: u8, attr_level: u8, rtl: bool) -> i32 {
+        if attr_level == 0 {
+            return self.face.glyph_metric(self.slots[slot].glyph, metric);
+        }
+
+        // Slot::clusterMetric
+        let root = self.find_root(slot);
+        let glyph = self.slots[root].display_glyph();
+        if glyph >= self.face.num_glyphs {
+            return 0;
+        }
+
+        let [left, bottom, right, top] = self.face.glyph_bbox(glyph);
+        let mut bbox = gr_rect_t {
+            bl: gr_position_t::new(left, bottom),
+            tr: gr_position_t::new(right, top),
+        };
+        let mut cluster_min = 0.0;
+        let res = self.finalise_slot(
+            root,
+            gr_position_t::default(),
+            &mut bbox,
+            attr_level,
+            &mut cluster_min,
+            rtl,
+            0,
+        );
+
+        (match metric {
+            0 | 4 => bbox.bl.x,
+            1 => res.x - bbox.tr.x,
+            2 => bbox.tr.y,
+            3 => bbox.bl.y,
+            5 => bbox.tr.x,
+            6 => bbox.tr.y - bbox.bl.y,
+            7 => bbox.tr.x - bbox.bl.x,
+            8 => res.x,
+            9 => res.y,
+            _ => 0.0,
+        }) as i32
+    }
+
+    // Segment::finalise in graphite2
+    pub fn finalise(&mut self) {
+        if self.first.is_none() || self.last.is_none() {
+            return;
+        }
+
+        let rtl = self.face.silf.dir != 0;
+        self.advance = self.position_slots(self.first, self.last, rtl);
+        if self.curr_dir() != (self.dir & 1 != 0) {
+            self.reverse_slots();
+        }
+    }
+}
@@ -0,0 +1,572 @@
+use alloc::vec::Vec;
+
+use super::graphite_pass::gr_slot_map_t;
+use super::graphite_segment::{attr, gr_segment_t, slotref};
+
+// The stack machine that runs rule constraints and actions. Instructions are interpreted
+// straight from the bytecode instead of being compiled first like in graphite2.
+
+// Machine::STACK_MAX in graphite2
+const STACK_MAX: usize = 1 << 10;
+
+mod op {
+    pub const PUSH_BYTE: u8 = 1;
+    pub const PUSH_BYTE_U: u8 = 2;
+    pub const PUSH_SHORT: u8 = 3;
+    pub const PUSH_SHORT_U: u8 = 4;
+    pub const PUSH_LONG: u8 = 5;
+    pub const ADD: u8 = 6;
+    pub const SUB: u8 = 7;
+    pub const MUL: u8 = 8;
+    pub const DIV: u8 = 9;
+    pub const MIN: u8 = 10;
+    pub const MAX: u8 = 11;
+    pub const NEG: u8 = 12;
+    pub const TRUNC8: u8 = 13;
+    pub const TRUNC16: u8 = 14;
+    pub const COND: u8 = 15;
+    pub const AND: u8 = 16;
+    pub const OR: u8 = 17;
+    pub const NOT: u8 = 18;
+    pub const EQUAL: u8 = 19;
+    pub const NOT_EQ: u8 = 20;
+    pub const LESS: u8 = 21;
+    pub const GTR: u8 = 22;
+    pub const LESS_EQ: u8 = 23;
+    pub const GTR_EQ: u8 = 24;
+    pub const NEXT: u8 = 25;
+    pub const COPY_NEXT: u8 = 27;
+    pub const PUT_GLYPH_8BIT_OBS: u8 = 28;
+    pub const PUT_SUBS_8BIT_OBS: u8 = 29;
+    pub const PUT_COPY: u8 = 30;
+    pub const INSERT: u8 = 31;
+    pub const DELETE: u8 = 32;
+    pub const ASSOC: u8 = 33;
+    pub const CNTXT_ITEM: u8 = 34;
+    pub const ATTR_SET: u8 = 35;
+    pub const ATTR_ADD: u8 = 36;
+    pub const ATTR_SUB: u8 = 37;
+    pub const ATTR_SET_SLOT: u8 = 38;
+    pub const IATTR_SET_SLOT: u8 = 39;
+    pub const PUSH_SLOT_ATTR: u8 = 40;
+    pub const PUSH_GLYPH_ATTR_OBS: u8 = 41;
+    pub const PUSH_GLYPH_METRIC: u8 = 42;
+    pub const PUSH_FEAT: u8 = 43;
+    pub const PUSH_ATT_TO_GATTR_OBS: u8 = 44;
+    pub const PUSH_ATT_TO_GLYPH_METRIC: u8 = 45;
+    pub const PUSH_ISLOT_ATTR: u8 = 46;
+    pub const POP_RET: u8 = 48;
+    pub const RET_ZERO: u8 = 49;
+    pub const RET_TRUE: u8 = 50;
+    pub const IATTR_SET: u8 = 51;
+    pub const IATTR_ADD: u8 = 52;
+    pub const IATTR_SUB: u8 = 53;
+    pub const PUSH_PROC_STATE: u8 = 54;
+    pub const PUSH_VERSION: u8 = 55;
+    pub const PUT_SUBS: u8 = 56;
+    pub const PUT_GLYPH: u8 = 59;
+    pub const PUSH_GLYPH_ATTR: u8 = 60;
+    pub const PUSH_ATT_TO_GLYPH_ATTR: u8 = 61;
+    pub const BITOR: u8 = 62;
+    pub const BITAND: u8 = 63;
+    pub const BITNOT: u8 = 64;
+    pub const BITSET: u8 = 65;
+    pub const SET_FEAT: u8 = 66;
+}
+
+/// Returns the length of the instruction at `i`, including its parameters.
+///
+/// Returns `None` for unknown or truncated instructions.
+pub fn opcode_len(code: &[u8], i: usize) -> Option<usize> {
+    let params = match *code.get(i)? {
+        0 | op::ADD..=op::NEXT | op::COPY_NEXT | op::INSERT | op::DELETE => 0,
+        op::POP_RET..=op::RET_TRUE | op::PUSH_VERSION | op::BITOR..=op::BITNOT => 0,
+        op::PUSH_BYTE | op::PUSH_BYTE_U | op::PUT_GLYPH_8BIT_OBS | op::PUT_COPY => 1,
+        op::ATTR_SET..=op::ATTR_SET_SLOT | op::PUSH_PROC_STATE => 1,
+        op::PUSH_SHORT | op::PUSH_SHORT_U | op::CNTXT_ITEM | op::IATTR_SET_SLOT => 2,
+        op::PUSH_SLOT_ATTR | op::PUSH_GLYPH_ATTR_OBS | op::PUSH_FEAT => 2,
+        op::PUSH_ATT_TO_GATTR_OBS | op::IATTR_SET..=op::IATTR_SUB => 2,
+        op::PUT_GLYPH | op::SET_FEAT => 2,
+        op::PUT_SUBS_8BIT_OBS | op::PUSH_GLYPH_METRIC | op::PUSH_ATT_TO_GLYPH_METRIC => 3,
+        op::PUSH_ISLOT_ATTR | op::PUSH_GLYPH_ATTR | op::PUSH_ATT_TO_GLYPH_ATTR => 3,
+        op::PUSH_LONG | op::BITSET => 4,
+        op::PUT_SUBS => 5,
+        op::ASSOC => 1 + usize::from(*code.get(i + 1)?),
+        _ => return None,
+    };
+
+    let len = 1 + params;
+    if i + len <= code.len() {
+        Some(len)
+    } else {
+        None
+    }
+}
+
+/// Checks whether code deletes slots.
+pub fn deletes(code: &[u8]) -> bool {
+    let mut i = 0;
+    while let Some(len) = opcode_len(code, i) {
+        if code[i] == op::DELETE {
+            return true;
+        }
+        i += len;
+    }
+
+    false
+}
+
+struct gr_machine_t<'a, 'b> {
+    seg: &'a mut gr_segment_t<'b>,
+    smap: &'a mut gr_slot_map_t,
+    stack: Vec<i32>,
+    // The current slot and its index in the slot map.
+    is: slotref,
+    map: usize,
+    positioned: bool,
+}
+
+impl gr_machine_t<'_, '_> {
+    fn push(&mut self, v: i32) -> Option<()> {
+        if self.stack.len() >= STACK_MAX {
+            return None;
+        }
+
+        self.stack.push(v);
+        Some(())
+    }
+
+    fn pop(&mut self) -> Option<i32> {
+        self.stack.pop()
+    }
+
+    fn top(&mut self) -> Option<&mut i32> {
+        self.stack.last_mut()
+    }
+
+    fn binary(&mut self, f: impl FnOnce(i32, i32) -> i32) -> Option<()> {
+        let b = self.pop()?;
+        let a = self.top()?;
+        *a = f(*a, b);
+        Some(())
+    }
+
+    fn slot_at(&self, offset: i8) -> slotref {
+        let index = self.map.checked_add_signed(isize::from(offset))?;
+        self.smap.get(index)
+    }
+
+    fn position_if_needed(&mut self, slat: u8) {
+        if (slat == attr::POS_X || slat == attr::POS_Y) && !self.positioned {
+            let start = self.smap.get(1);
+            let end = self.smap.get(self.smap.size);
+            let rtl = self.seg.curr_dir();
+            self.seg.position_slots(start, end, rtl);
+            self.positioned = true;
+        }
+    }
+
+    fn set_attr(&mut self, slat: u8, index: u8, value: i32) {
+        if let Some(is) = self.is {
+            let rtl = self.smap.rtl;
+            self.seg
+                .set_attr(is, slat, index, value as i16, self.smap.rule_slots(), rtl);
+        }
+    }
+
+    fn feature(&self, index: u8) -> i32 {
+        self.seg
+            .features
+            .get(usize::from(index))
+            .map_or(0, |&v| v as i32)
+    }
+
+    fn set_glyph(&mut self, glyph: u16) {
+        if let Some(is) = self.is {
+            self.seg.set_glyph(is, glyph);
+        }
+    }
+
+    fn put_copy(&mut self, offset: i8) -> Option<()> {
+        let Some(is) = self.is.filter(|&is| !self.seg.slots[is].is_deleted()) else {
+            return Some(());
+        };
+
+        if let Some(other) = self.slot_at(offset).filter(|&s| s != is) {
+            let s = &self.seg.slots[is];
+            if s.parent.is_some() || s.child.is_some() {
+                return None;
+            }
+
+            let (prev, next) = (s.prev, s.next);
+            let mut copy = self.seg.slots[other].clone();
+            copy.child = None;
+            copy.sibling = None;
+            copy.prev = prev;
+            copy.next = next;
+            let parent = copy.parent;
+            self.seg.slots[is] = copy;
+            if let Some(parent) = parent {
+                self.seg.add_child(parent, is);
+            }
+        }
+
+        let s = &mut self.seg.slots[is];
+        s.mark_copied(false);
+        s.mark_deleted(false);
+        Some(())
+    }
+
+    fn insert(&mut self) -> Option<()> {
+        self.smap.max_size -= 1;
+        if self.smap.max_size <= 0 {
+            return None;
+        }
+
+        let seg = &mut *self.seg;
+        let new = seg.new_slot();
+        let mut iss = self.is;
+        while let Some(i) = iss.filter(|&i| seg.slots[i].is_deleted()) {
+            iss = seg.slots[i].next;
+        }
+
+        match iss {
+            None => {
+                if let Some(last) = seg.last {
+                    seg.slots[last].next = Some(new);
+                    seg.slots[new].prev = Some(last);
+                    seg.slots[new].before = seg.slots[last].before;
+                    seg.last = Some(new);
+                } else {
+                    seg.first = Some(new);
+                    seg.last = Some(new);
+                }
+            }
+            Some(iss) => match seg.slots[iss].prev {
+                Some(prev) => {
+                    seg.slots[prev].next = Some(new);
+                    seg.slots[new].prev = Some(prev);
+                    seg.slots[new].before = seg.slots[prev].after;
+                }
+                None => {
+                    seg.slots[new].before = seg.slots[iss].before;
+                    seg.first = Some(new);
+                }
+            },
+        }
+
+        seg.slots[new].next = iss;
+        if let Some(iss) = iss {
+            seg.slots[iss].prev = Some(new);
+            seg.slots[new].original = seg.slots[iss].original;
+            seg.slots[new].after = seg.slots[iss].before;
+        } else if let Some(prev) = seg.slots[new].prev {
+            seg.slots[new].original = seg.slots[prev].original;
+            seg.slots[new].after = seg.slots[prev].after;
+        }
+
+        if self.is == self.smap.highwater {
+            self.smap.highpassed = false;
+        }
+        self.is = Some(new);
+        seg.slot_count += 1;
+        if self.map != 0 {
+            self.map -= 1;
+        }
+
+        Some(())
+    }
+
+    fn delete(&mut self) -> Option<()> {
+        let is = self.is.filter(|&is| !self.seg.slots[is].is_deleted())?;
+        let seg = &mut *self.seg;
+        seg.slots[is].mark_deleted(true);
+        let (prev, next) = (seg.slots[is].prev, seg.slots[is].next);
+        match prev {
+            Some(prev) => seg.slots[prev].next = next,
+            None => seg.first = next,
+        }
+        match next {
+            Some(next) => seg.slots[next].prev = prev,
+            None => seg.last = prev,
+        }
+
+        if self.is == self.smap.highwater {
+            self.smap.highwater = next;
+        }
+        if prev.is_some() {
+            self.is = prev;
+        }
+        seg.slot_count = seg.slot_count.saturating_sub(1);
+        Some(())
+    }
+
+    fn assoc(&mut self, offsets: &[u8]) {
+        let mut min = -1;
+        let mut max = -1;
+        for &offset in offsets {
+            if let Some(s) = self.slot_at(offset as i8) {
+                let s = &self.seg.slots[s];
+                if min == -1 || s.before < min {
+                    min = s.before;
+                }
+                if s.after > max {
+                    max = s.after;
+                }
+            }
+        }
+
+        if let Some(is) = self.is.filter(|_| min > -1) {
+            self.seg.slots[is].before = min;
+            self.seg.slots[is].after = max;
+        }
+    }
+
+    // Machine::run in graphite2. Returns `None` when the code fails.
+    fn run(&mut self, code: &[u8]) -> Option<()> {
+        let mut ip = 0;
+        while ip < code.len() {
+            let opcode = code[ip];
+            let len = opcode_len(code, ip)?;
+            let p = &code[ip + 1..ip + len];
+            let u16_at = |i: usize| u16::from_be_bytes([p[i], p[i + 1]]);
+            ip += len;
+
+            match opcode {
+                op::PUSH_BYTE => self.push(i32::from(p[0] as i8))?,
+                op::PUSH_BYTE_U => self.push(i32::from(p[0]))?,
+                op::PUSH_SHORT => self.push(i32::from(u16_at(0) as i16))?,
+                op::PUSH_SHORT_U => self.push(i32::from(u16_at(0)))?,
+                op::PUSH_LONG => self.push(i32::from_be_bytes([p[0], p[1], p[2], p[3]]))?,
+                op::ADD => self.binary(|a, b| a.wrapping_add(b))?,
+                op::SUB => self.binary(|a, b| a.wrapping_sub(b))?,
+                op::MUL => self.binary(|a, b| a.wrapping_mul(b))?,
+                op::DIV => {
+                    let b = self.pop()?;
+                    let a = self.top()?;
+                    *a = a.checked_div(b)?;
+                }
+                op::MIN => self.binary(|a, b| a.min(b))?,
+                op::MAX => self.binary(|a, b| a.max(b))?,
+                op::NEG => {
+                    let top = self.top()?;
+                    *top = top.wrapping_neg();
+                }
+                op::TRUNC8 => {
+                    let top = self.top()?;
+                    *top = i32::from(*top as u8);
+                }
+                op::TRUNC16 => {
+                    let top = self.top()?;
+                    *top = i32::from(*top as u16);
+                }
+                op::COND => {
+                    let f = self.pop()?;
+                    let t = self.pop()?;
+                    let c = self.pop()?;
+                    self.push(if c != 0 { t } else { f })?;
+                }
+                op::AND => self.binary(|a, b| i32::from(a != 0 && b != 0))?,
+                op::OR => self.binary(|a, b| i32::from(a != 0 || b != 0))?,
+                op::NOT => {
+                    let top = self.top()?;
+                    *top = i32::from(*top == 0);
+                }
+                op::EQUAL => self.binary(|a, b| i32::from(a == b))?,
+                op::NOT_EQ => self.binary(|a, b| i32::from(a != b))?,
+                op::LESS => self.binary(|a, b| i32::from(a < b))?,
+                op::GTR => self.binary(|a, b| i32::from(a > b))?,
+                op::LESS_EQ => self.binary(|a, b| i32::from(a <= b))?,
+                op::GTR_EQ => self.binary(|a, b| i32::from(a >= b))?,
+                op::NEXT | op::COPY_NEXT => {
+                    if self.map > self.smap.size {
+                        return None;
+                    }
+
+                    if let Some(is) = self.is {
+                        if self.is == self.smap.highwater {
+                            self.smap.highpassed = true;
+                        }
+                        self.is = self.seg.slots[is].next;
+                    }
+                    self.map += 1;
+                }
+                op::PUT_GLYPH_8BIT_OBS => {
+                    let glyph = self.seg.face.silf.class_glyph(u16::from(p[0]), Some(0));
+                    self.set_glyph(glyph);
+                }
+                op::PUT_SUBS_8BIT_OBS | op::PUT_SUBS => {
+                    let (input, output) = if opcode == op::PUT_SUBS {
+                        (u16_at(1), u16_at(3))
+                    } else {
+                        (u16::from(p[1]), u16::from(p[2]))
+                    };
+
+                    if let Some(slot) = self.slot_at(p[0] as i8) {
+                        let silf = &self.seg.face.silf;
+                        let index = silf.find_class_index(input, self.seg.slots[slot].glyph);
+                        let glyph = silf.class_glyph(output, index);
+                        self.set_glyph(glyph);
+                    }
+                }
+                op::PUT_COPY => self.put_copy(p[0] as i8)?,
+                op::INSERT => self.insert()?,
+                op::DELETE => self.delete()?,
+                op::ASSOC => self.assoc(&p[1..]),
+                op::CNTXT_ITEM => {
+                    let start = 1 + usize::from(self.smap.context);
+                    if start.checked_add_signed(isize::from(p[0] as i8)) != Some(self.map) {
+                        ip += usize::from(p[1]);
+                        self.push(1)?;
+                    }
+                }
+                op::ATTR_SET => {
+                    let value = self.pop()?;
+                    self.set_attr(p[0], 0, value);
+                }
+                op::ATTR_ADD | op::ATTR_SUB | op::IATTR_ADD | op::IATTR_SUB => {
+                    let index = if opcode >= op::IATTR_ADD { p[1] } else { 0 };
+                    let value = self.pop()?;
+                    self.position_if_needed(p[0]);
+                    if let Some(is) = self.is {
+                        let current = self.seg.get_attr(is, p[0], index);
+                        let value = if matches!(opcode, op::ATTR_ADD | op::IATTR_ADD) {
+                            current.wrapping_add(value)
+                        } else {
+                            current.wrapping_sub(value)
+                        };
+                        self.set_attr(p[0], index, value);
+                    }
+                }
+                op::ATTR_SET_SLOT | op::IATTR_SET_SLOT => {
+                    let offset = if p[0] == attr::ATT_TO {
+                        self.map as i32 - 1
+                    } else {
+                        0
+                    };
+                    let index = if opcode == op::IATTR_SET_SLOT {
+                        p[1]
+                    } else {
+                        offset as u8
+                    };
+                    let value = self.pop()?.wrapping_add(offset);
+                    self.set_attr(p[0], index, value);
+                }
+                op::PUSH_SLOT_ATTR | op::PUSH_ISLOT_ATTR => {
+                    let index = if opcode == op::PUSH_ISLOT_ATTR {
+                        p[2]
+                    } else {
+                        0
+                    };
+                    self.position_if_needed(p[0]);
+                    if let Some(slot) = self.slot_at(p[1] as i8) {
+                        let value = self.seg.get_attr(slot, p[0], index);
+                        self.push(value)?;
+                    }
+                }
+                op::PUSH_GLYPH_ATTR_OBS
+                | op::PUSH_ATT_TO_GATTR_OBS
+                | op::PUSH_GLYPH_ATTR
+                | op::PUSH_ATT_TO_GLYPH_ATTR => {
+                    let (glyph_attr, offset) = if opcode >= op::PUSH_GLYPH_ATTR {
+                        (u16_at(0), p[2])
+                    } else {
+                        (u16::from(p[0]), p[1])
+                    };
+
+                    if let Some(mut slot) = self.slot_at(offset as i8) {
+                        if matches!(
+                            opcode,
+                            op::PUSH_ATT_TO_GATTR_OBS | op::PUSH_ATT_TO_GLYPH_ATTR
+                        ) {
+                            slot = self.seg.slots[slot].parent.unwrap_or(slot);
+                        }
+                        let glyph = self.seg.slots[slot].glyph;
+                        self.push(i32::from(self.seg.glyph_attr(glyph, glyph_attr)))?;
+                    }
+                }
+                op::PUSH_GLYPH_METRIC | op::PUSH_ATT_TO_GLYPH_METRIC => {
+                    if let Some(mut slot) = self.slot_at(p[1] as i8) {
+                        if opcode == op::PUSH_ATT_TO_GLYPH_METRIC {
+                            slot = self.seg.slots[slot].parent.unwrap_or(slot);
+                        }
+                        let rtl = self.smap.rtl;
+                        let value = self.seg.glyph_metric(slot, p[0], p[2], rtl);
+                        self.push(value)?;
+                    }
+                }
+                op::PUSH_FEAT if self.slot_at(p[1] as i8).is_some() => {
+                    self.push(self.feature(p[0]))?;
+                }
+                op::SET_FEAT if self.slot_at(p[1] as i8).is_some() => {
+                    // Unlike user features, values are clamped to the maximum.
+                    let value = self.pop()? as u32;
+                    let index = usize::from(p[0]);
+                    if let Some(feature) = self.seg.face.features.get(index) {
+                        self.seg.features[index] = value.min(feature.max);
+                    }
+                }
+                op::POP_RET => return Some(()),
+                op::RET_ZERO => return self.push(0),
+                op::RET_TRUE => return self.push(1),
+                op::IATTR_SET => {
+                    let value = self.pop()?;
+                    self.set_attr(p[0], p[1], value);
+                }
+                op::PUSH_PROC_STATE => self.push(1)?,
+                op::PUSH_VERSION => self.push(0x0003_0000)?,
+                op::PUT_GLYPH => {
+                    let glyph = self.seg.face.silf.class_glyph(u16_at(0), Some(0));
+                    self.set_glyph(glyph);
+                }
+                op::BITOR => self.binary(|a, b| a | b)?,
+                op::BITAND => self.binary(|a, b| a & b)?,
+                op::BITNOT => {
+                    let top = self.top()?;
+                    *top = !*top;
+                }
+                op::BITSET => {
+                    let mask = i32::from(u16_at(0));
+                    let value = i32::from(u16_at(2));
+                    let top = self.top()?;
+                    *top = (*top & !mask) | value;
+                }
+                _ => {}
+            }
+        }
+
+        Some(())
+    }
+}
+
+/// Runs code with the current slot at `map` in the slot map.
+///
+/// Returns the value left on the stack, or `None` when the code fails, which stops the
+/// whole segment. `map` is moved to the slot the code ends on.
+pub fn run(
+    seg: &mut gr_segment_t,
+    smap: &mut gr_slot_map_t,
+    code: &[u8],
+    map: &mut usize,
+) -> Option<i32> {
+    let mut m = gr_machine_t {
+        is: smap.get(*map),
+        seg,
+        smap,
+        stack: Vec::new(),
+        map: *map,
+        positioned: false,
+    };
+
+    m.run(code)?;
+
+    // Exactly one value must be left.
+    let ret = m.pop()?;
+    if !m.stack.is_empty() {
+        return None;
+    }
+
+    *map = m.map;
+    m.smap.set(m.map, m.is);
+    Some(ret)
+}
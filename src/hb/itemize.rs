@@ -0,0 +1,497 @@
+use core::ops::Range;
+
+use smallvec::SmallVec;
+
+use super::unicode::{
+    hb_emoji_status_t, hb_unicode_general_category_t, CharExt, GeneralCategoryExt,
+};
+use crate::{script, Direction, Script, UnicodeBuffer};
+
+// Paired brackets nested deeper than this are resolved as regular Common characters.
+const MAX_BRACKET_DEPTH: usize = 64;
+
+const ZWJ: char = '\u{200D}';
+const VS15: char = '\u{FE0E}';
+const VS16: char = '\u{FE0F}';
+const COMBINING_ENCLOSING_KEYCAP: char = '\u{20E3}';
+const CANCEL_TAG: char = '\u{E007F}';
+
+/// A run of text with a single script.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct hb_script_run_t {
+    /// The byte range of the run in the itemized text.
+    pub range: Range<usize>,
+    /// The script of the run.
+    ///
+    /// [`script::COMMON`] when the whole text has no specific script.
+    pub script: Script,
+    /// The horizontal direction of the script.
+    pub direction: Direction,
+}
+
+impl hb_script_run_t {
+    /// Returns a buffer with the run text, properties and context.
+    ///
+    /// Clusters are byte offsets into `text`, the string that was itemized.
+    pub fn to_buffer(&self, text: &str) -> UnicodeBuffer {
+        let mut buffer = UnicodeBuffer::new();
+        for (i, c) in text[self.range.clone()].char_indices() {
+            buffer.add(c, (self.range.start + i) as u32);
+        }
+
+        buffer.set_pre_context(&text[..self.range.start]);
+        buffer.set_post_context(&text[self.range.end..]);
+        buffer.set_script(self.script);
+        buffer.set_direction(self.direction);
+        buffer
+    }
+}
+
+/// An iterator over script runs.
+///
+/// Created by [`script_runs`](crate::script_runs).
+#[derive(Clone, Debug)]
+pub struct hb_script_runs_t<'a> {
+    text: &'a str,
+    offset: usize,
+    // The closing bracket to look for and the script it resolves to, innermost last.
+    brackets: SmallVec<[(char, Script); 8]>,
+}
+
+/// Splits text into runs of a single script, as described in UAX #24.
+///
+/// Common and Inherited characters take the script of the surrounding text.
+/// Paired brackets share the script of the text they were opened in.
+///
+/// Each run can be shaped separately, see [`ScriptRun::to_buffer`](crate::ScriptRun::to_buffer).
+/// Directions are only derived from scripts. Use [`shape_paragraph`](crate::shape_paragraph)
+/// for bidirectional text.
+pub fn script_runs(text: &str) -> hb_script_runs_t<'_> {
+    hb_script_runs_t {
+        text,
+        offset: 0,
+        brackets: SmallVec::new(),
+    }
+}
+
+impl Iterator for hb_script_runs_t<'_> {
+    type Item = hb_script_run_t;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.offset;
+        if start >= self.text.len() {
+            return None;
+        }
+
+        let mut run_script = script::COMMON;
+        // Brackets opened in this run before its script was known.
+        let first_unresolved = self.brackets.len();
+        let mut end = self.text.len();
+
+        for (i, c) in self.text[start..].char_indices() {
+            let mut script = c.script();
+            // A closing bracket is only matched when it stays in this run.
+            let mut matched_bracket = None;
+
+            match bracket_type(c) {
+                BracketType::Open => {
+                    if self.brackets.len() < MAX_BRACKET_DEPTH {
+                        let closing = normalize_bracket(c.mirrored().unwrap_or(c));
+                        self.brackets.push((closing, run_script));
+                    }
+                }
+                BracketType::Close => {
+                    let c = normalize_bracket(c);
+                    if let Some(idx) = self.brackets.iter().rposition(|(b, _)| *b == c) {
+                        script = self.brackets[idx].1;
+                        matched_bracket = Some(idx);
+                    }
+                }
+                BracketType::None => {}
+            }
+
+            if script != script::COMMON && script != script::INHERITED {
+                if run_script == script::COMMON {
+                    run_script = script;
+                    let first_unresolved = first_unresolved.min(self.brackets.len());
+                    for bracket in &mut self.brackets[first_unresolved..] {
+                        bracket.1 = script;
+                    }
+                } else if script != run_script {
+                    end = start + i;
+                    break;
+                }
+            }
+
+            if let Some(idx) = matched_bracket {
+                self.brackets.truncate(idx);
+            }
+        }
+
+        self.offset = end;
+
+        Some(hb_script_run_t {
+            range: start..end,
+            script: run_script,
+            direction: Direction::from_script(run_script).unwrap_or(Direction::LeftToRight),
+        })
+    }
+}
+
+/// The preferred presentation of a run of text.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum hb_emoji_presentation_t {
+    /// Text, including emoji with a text presentation. Should use a text font.
+    Text,
+    /// Emoji with an emoji presentation. Should use a color font.
+    Emoji,
+}
+
+/// A run of text with a single preferred presentation.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct hb_emoji_run_t {
+    /// The byte range of the run in the segmented text.
+    pub range: Range<usize>,
+    /// The preferred presentation of the run.
+    pub presentation: hb_emoji_presentation_t,
+}
+
+/// An iterator over emoji presentation runs.
+///
+/// Created by [`emoji_runs`](crate::emoji_runs).
+#[derive(Clone, Debug)]
+pub struct hb_emoji_runs_t<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+/// Splits text into runs of text and emoji presentation, as described in UTS #51.
+///
+/// Emoji with the `Emoji_Presentation` property, emoji followed by VS16, modifier,
+/// keycap, flag, tag and ZWJ sequences have an emoji presentation.
+/// Other characters, including emoji followed by VS15, have a text presentation.
+/// Marks stay in the run of the character they follow.
+pub fn emoji_runs(text: &str) -> hb_emoji_runs_t<'_> {
+    hb_emoji_runs_t { text, offset: 0 }
+}
+
+impl Iterator for hb_emoji_runs_t<'_> {
+    type Item = hb_emoji_run_t;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.offset;
+        if start >= self.text.len() {
+            return None;
+        }
+
+        let mut scanner = EmojiScanner {
+            text: self.text,
+            pos: start,
+        };
+        let presentation = scanner.sequence();
+        let mut end = scanner.pos;
+        while end < self.text.len() && scanner.sequence() == presentation {
+            end = scanner.pos;
+        }
+
+        self.offset = end;
+
+        Some(hb_emoji_run_t {
+            range: start..end,
+            presentation,
+        })
+    }
+}
+
+// Emoji sequences matching, like Chromium's emoji presentation scanner.
+struct EmojiScanner<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl EmojiScanner<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump_if(&mut self, f: impl Fn(char) -> bool) -> Option<char> {
+        let c = self.peek().filter(|c| f(*c))?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    // Scans a character with everything attached to it.
+    fn sequence(&mut self) -> hb_emoji_presentation_t {
+        let mut presentation = match self.element() {
+            Some(presentation) => presentation,
+            None => {
+                self.bump_if(|_| true);
+                hb_emoji_presentation_t::Text
+            }
+        };
+
+        loop {
+            let pos = self.pos;
+            if self.bump_if(|c| c == ZWJ).is_none() {
+                break;
+            }
+
+            if self.element().is_some() {
+                presentation = hb_emoji_presentation_t::Emoji;
+            } else {
+                self.pos = pos;
+                break;
+            }
+        }
+
+        while self
+            .bump_if(|c| c == ZWJ || c.general_category().is_mark())
+            .is_some()
+        {}
+
+        presentation
+    }
+
+    // Scans an emoji with its modifier, variation selector or tags.
+    fn element(&mut self) -> Option<hb_emoji_presentation_t> {
+        use hb_emoji_presentation_t::*;
+
+        let start = self.pos;
+        let c = self.peek()?;
+
+        if is_regional_indicator(c) {
+            self.bump_if(|_| true);
+            self.bump_if(is_regional_indicator);
+            return Some(Emoji);
+        }
+
+        if is_keycap_base(c) {
+            self.bump_if(|_| true);
+            let selector = self.bump_if(|c| c == VS15 || c == VS16);
+            if self.bump_if(|c| c == COMBINING_ENCLOSING_KEYCAP).is_some() {
+                return Some(if selector == Some(VS15) { Text } else { Emoji });
+            }
+            self.pos = start;
+        }
+
+        let status = c.emoji_status();
+        let is_emoji = !matches!(
+            status,
+            hb_emoji_status_t::NonEmoji | hb_emoji_status_t::NonEmojiButEmojiComponent
+        );
+        if !is_emoji && !c.is_emoji_extended_pictographic() {
+            return None;
+        }
+        self.bump_if(|_| true);
+
+        // Unassigned pictographic code points are reserved for emoji.
+        let mut presentation = if !is_emoji || has_emoji_presentation(status) {
+            Emoji
+        } else {
+            Text
+        };
+
+        if is_emoji_modifier_base(status) && self.bump_if(is_emoji_modifier).is_some() {
+            presentation = Emoji;
+        } else if let Some(selector) = self.bump_if(|c| c == VS15 || c == VS16) {
+            presentation = if selector == VS16 { Emoji } else { Text };
+        }
+
+        if self.bump_if(is_tag).is_some() {
+            while self.bump_if(is_tag).is_some() {}
+            self.bump_if(|c| c == CANCEL_TAG);
+            presentation = Emoji;
+        }
+
+        Some(presentation)
+    }
+}
+
+fn has_emoji_presentation(status: hb_emoji_status_t) -> bool {
+    matches!(
+        status,
+        hb_emoji_status_t::EmojiPresentation
+            | hb_emoji_status_t::EmojiPresentationAndModifierBase
+            | hb_emoji_status_t::EmojiPresentationAndEmojiComponent
+            | hb_emoji_status_t::EmojiPresentationAndModifierAndEmojiComponent
+    )
+}
+
+fn is_emoji_modifier_base(status: hb_emoji_status_t) -> bool {
+    matches!(
+        status,
+        hb_emoji_status_t::EmojiModifierBase | hb_emoji_status_t::EmojiPresentationAndModifierBase
+    )
+}
+
+fn is_emoji_modifier(c: char) -> bool {
+    ('\u{1F3FB}'..='\u{1F3FF}').contains(&c)
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+fn is_keycap_base(c: char) -> bool {
+    matches!(c, '0'..='9' | '#' | '*')
+}
+
+fn is_tag(c: char) -> bool {
+    ('\u{E0020}'..='\u{E007E}').contains(&c)
+}
+
+enum BracketType {
+    None,
+    Open,
+    Close,
+}
+
+// Bidi_Paired_Bracket_Type is derived from the general category
+// of characters with a Bidi_Mirroring_Glyph.
+fn bracket_type(c: char) -> BracketType {
+    match c.general_category() {
+        hb_unicode_general_category_t::OpenPunctuation if c.mirrored().is_some() => {
+            BracketType::Open
+        }
+        hb_unicode_general_category_t::ClosePunctuation if c.mirrored().is_some() => {
+            BracketType::Close
+        }
+        _ => BracketType::None,
+    }
+}
+
+// Angle brackets are canonically equivalent to CJK ones.
+fn normalize_bracket(c: char) -> char {
+    match c {
+        '\u{2329}' => '\u{3008}',
+        '\u{232A}' => '\u{3009}',
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn runs(text: &str) -> Vec<(&str, Script)> {
+        script_runs(text)
+            .map(|run| (&text[run.range], run.script))
+            .collect()
+    }
+
+    #[test]
+    fn mixed_scripts() {
+        assert_eq!(
+            runs("Hello مرحبا नमस्ते"),
+            [
+                ("Hello ", script::LATIN),
+                ("مرحبا ", script::ARABIC),
+                ("नमस्ते", script::DEVANAGARI),
+            ]
+        );
+    }
+
+    #[test]
+    fn leading_common() {
+        assert_eq!(runs("1. Привет"), [("1. Привет", script::CYRILLIC)]);
+        assert_eq!(runs("123 !"), [("123 !", script::COMMON)]);
+        assert_eq!(runs(""), []);
+    }
+
+    #[test]
+    fn paired_brackets() {
+        // The closing bracket belongs to the Latin run.
+        assert_eq!(
+            runs("a (αβ) b"),
+            [
+                ("a (", script::LATIN),
+                ("αβ", script::GREEK),
+                (") b", script::LATIN)
+            ]
+        );
+
+        // Brackets opened before the script is known are resolved with it.
+        assert_eq!(
+            runs("(שלום) abc"),
+            [("(שלום) ", script::HEBREW), ("abc", script::LATIN)]
+        );
+
+        // Mismatched brackets are Common.
+        assert_eq!(
+            runs("αβ] abc"),
+            [("αβ] ", script::GREEK), ("abc", script::LATIN)]
+        );
+    }
+
+    #[test]
+    fn directions() {
+        let directions: Vec<_> = script_runs("abc אבג").map(|run| run.direction).collect();
+        assert_eq!(directions, [Direction::LeftToRight, Direction::RightToLeft]);
+    }
+
+    fn emoji(text: &str) -> Vec<(&str, hb_emoji_presentation_t)> {
+        emoji_runs(text)
+            .map(|run| (&text[run.range], run.presentation))
+            .collect()
+    }
+
+    #[test]
+    fn emoji_presentation() {
+        use hb_emoji_presentation_t::*;
+
+        assert_eq!(emoji("abc"), [("abc", Text)]);
+        assert_eq!(emoji("a😀b"), [("a", Text), ("😀", Emoji), ("b", Text)]);
+        // Emoji with a default text presentation.
+        assert_eq!(emoji("a☺b"), [("a☺b", Text)]);
+        assert_eq!(
+            emoji("a☺\u{FE0F}b"),
+            [("a", Text), ("☺\u{FE0F}", Emoji), ("b", Text)]
+        );
+        assert_eq!(emoji("😀\u{FE0E}😀"), [("😀\u{FE0E}", Text), ("😀", Emoji)]);
+        assert_eq!(emoji(""), []);
+    }
+
+    #[test]
+    fn emoji_sequences() {
+        use hb_emoji_presentation_t::*;
+
+        // Keycaps.
+        assert_eq!(emoji("1#\u{20E3}"), [("1", Text), ("#\u{20E3}", Emoji)]);
+        assert_eq!(emoji("#\u{FE0E}\u{20E3}"), [("#\u{FE0E}\u{20E3}", Text)]);
+        // Modifiers.
+        assert_eq!(
+            emoji("a\u{261D}\u{1F3FD}"),
+            [("a", Text), ("\u{261D}\u{1F3FD}", Emoji)]
+        );
+        // Flags.
+        assert_eq!(
+            emoji("x\u{1F1FA}\u{1F1E6}"),
+            [("x", Text), ("\u{1F1FA}\u{1F1E6}", Emoji)]
+        );
+        // Tags.
+        let england = "\u{1F3F4}\u{E0067}\u{E0062}\u{E0065}\u{E006E}\u{E0067}\u{E007F}";
+        assert_eq!(emoji(england), [(england, Emoji)]);
+        // ZWJ sequences, even of emoji with a default text presentation.
+        let eye_in_bubble = "\u{1F441}\u{200D}\u{1F5E8}";
+        assert_eq!(emoji(eye_in_bubble), [(eye_in_bubble, Emoji)]);
+        assert_eq!(emoji("😀\u{200D}a"), [("😀\u{200D}", Emoji), ("a", Text)]);
+        // Marks stay with their base.
+        assert_eq!(
+            emoji("😀\u{0301}a\u{FE0F}"),
+            [("😀\u{0301}", Emoji), ("a\u{FE0F}", Text)]
+        );
+    }
+
+    #[test]
+    fn to_buffer() {
+        let text = "ab אבג";
+        let run = script_runs(text).nth(1).unwrap();
+        let buffer = run.to_buffer(text);
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.script(), script::HEBREW);
+        assert_eq!(buffer.direction(), Direction::RightToLeft);
+        assert_eq!(buffer.0.info[0].cluster, 3);
+        assert_eq!(buffer.0.context[0][0], ' ');
+    }
+}
@@ -0,0 +1,254 @@
+use alloc::vec::Vec;
+
+use ttf_parser::Tag;
+
+use super::buffer::glyph_flag::SAFE_TO_INSERT_TATWEEL;
+use super::hb_font_t;
+use super::ot_shape_plan::hb_ot_shape_plan_t;
+use super::shape::shape_with_plan;
+use crate::{BufferFlags, GlyphBuffer, UnicodeBuffer, Variation};
+
+const TATWEEL: u32 = 0x0640;
+
+/// Justification options for [`shape_justify`](crate::shape_justify).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct hb_justify_options_t {
+    /// A variation axis to vary, like `wdth` or `GRAD`.
+    ///
+    /// The run advance is expected to grow with the axis value.
+    /// Ignored when the face has no such axis.
+    pub axis: Option<Tag>,
+    /// Whether U+0640 TATWEEL can be inserted at positions marked as
+    /// safe by the shaper to widen the run further.
+    pub insert_tatweel: bool,
+    /// An advance difference from the target that is good enough, in font units.
+    pub tolerance: u32,
+    /// The maximum number of additional shaping passes for each justification step.
+    pub max_iterations: u32,
+}
+
+impl Default for hb_justify_options_t {
+    fn default() -> Self {
+        hb_justify_options_t {
+            axis: None,
+            insert_tatweel: false,
+            tolerance: 0,
+            max_iterations: 16,
+        }
+    }
+}
+
+/// A result of [`shape_justify`](crate::shape_justify).
+pub struct hb_justify_result_t {
+    /// Shaped glyphs.
+    pub glyphs: GlyphBuffer,
+    /// The achieved run advance, in font units.
+    pub advance: i32,
+    /// The final value of the justification axis, if it was varied.
+    pub variation: Option<Variation>,
+    /// The number of inserted tatweels.
+    pub tatweel_count: usize,
+}
+
+/// Shapes the buffer content using the provided font and plan,
+/// bringing the run advance as close to `target_advance` as possible without exceeding it.
+///
+/// The justification axis is searched first, starting from the current face coordinates.
+/// Then, tatweels are inserted if the run is still too short.
+/// The run can only exceed the target when it cannot be narrowed enough.
+///
+/// Inserted tatweels have the cluster of the character they precede.
+///
+/// The provided face is left untouched, use the returned variation to render the glyphs.
+pub fn shape_justify(
+    face: &hb_font_t,
+    plan: &hb_ot_shape_plan_t,
+    mut buffer: UnicodeBuffer,
+    target_advance: i32,
+    options: &hb_justify_options_t,
+) -> hb_justify_result_t {
+    buffer.guess_segment_properties();
+    if options.insert_tatweel {
+        buffer.0.flags |= BufferFlags::PRODUCE_SAFE_TO_INSERT_TATWEEL;
+    }
+
+    let axis = options
+        .axis
+        .and_then(|tag| face.variation_axes().into_iter().find(|a| a.tag == tag));
+
+    let mut face = face.clone();
+    let tolerance = i64::from(options.tolerance);
+    let target = i64::from(target_advance);
+    let is_good_enough = |advance: i32| (target - i64::from(advance)).abs() <= tolerance;
+
+    let mut glyphs = shape_with_plan(&face, plan, clone_buffer(&buffer));
+    let mut advance = run_advance(&glyphs);
+    let mut variation = None;
+
+    if let Some(axis) = axis {
+        if !is_good_enough(advance) {
+            let current = face.variation_value(axis.tag).unwrap_or(axis.def_value);
+
+            let mut shape_at = |value: f32| {
+                face.set_variation(axis.tag, value);
+                let glyphs = shape_with_plan(&face, plan, clone_buffer(&buffer));
+                let advance = run_advance(&glyphs);
+                (value, glyphs, advance)
+            };
+
+            // The best fitting value that does not exceed the target and the closest one that does.
+            let (mut lo, hi_value) = if advance <= target_advance {
+                let max = shape_at(axis.max_value);
+                if max.2 <= target_advance {
+                    (max, None)
+                } else {
+                    ((current, glyphs, advance), Some(axis.max_value))
+                }
+            } else {
+                let min = shape_at(axis.min_value);
+                if min.2 > target_advance {
+                    (min, None)
+                } else {
+                    (min, Some(current))
+                }
+            };
+
+            if let Some(mut hi_value) = hi_value {
+                for _ in 0..options.max_iterations {
+                    if is_good_enough(lo.2) {
+                        break;
+                    }
+
+                    let mid = shape_at((lo.0 + hi_value) / 2.0);
+                    if mid.0 == lo.0 || mid.0 == hi_value {
+                        break;
+                    }
+
+                    if mid.2 <= target_advance {
+                        lo = mid;
+                    } else {
+                        hi_value = mid.0;
+                    }
+                }
+            }
+
+            face.set_variation(axis.tag, lo.0);
+            variation = Some(Variation {
+                tag: axis.tag,
+                value: lo.0,
+            });
+            (_, glyphs, advance) = lo;
+        }
+    }
+
+    let mut tatweel_count = 0;
+    if options.insert_tatweel && advance < target_advance && !is_good_enough(advance) {
+        if let Some(result) =
+            insert_tatweels(&face, plan, &buffer, &glyphs, target_advance, options)
+        {
+            (glyphs, advance, tatweel_count) = result;
+        }
+    }
+
+    hb_justify_result_t {
+        glyphs,
+        advance,
+        variation,
+        tatweel_count,
+    }
+}
+
+fn insert_tatweels(
+    face: &hb_font_t,
+    plan: &hb_ot_shape_plan_t,
+    buffer: &UnicodeBuffer,
+    glyphs: &GlyphBuffer,
+    target_advance: i32,
+    options: &hb_justify_options_t,
+) -> Option<(GlyphBuffer, i32, usize)> {
+    if !buffer.0.direction.is_horizontal() {
+        return None;
+    }
+
+    let tatweel_advance = face.glyph_h_advance(face.get_nominal_glyph(TATWEEL)?);
+    if tatweel_advance <= 0 {
+        return None;
+    }
+
+    let mut clusters: Vec<u32> = glyphs
+        .glyph_infos()
+        .iter()
+        .filter(|info| info.mask & SAFE_TO_INSERT_TATWEEL != 0)
+        .map(|info| info.cluster)
+        .collect();
+    clusters.sort_unstable();
+    clusters.dedup();
+
+    if clusters.is_empty() {
+        return None;
+    }
+
+    let gap = target_advance - run_advance(glyphs);
+    let mut count = usize::try_from(gap / tatweel_advance).ok()?;
+
+    // Tatweels can be wider after shaping, so remove them until the run fits.
+    for _ in 0..=options.max_iterations {
+        if count == 0 {
+            break;
+        }
+
+        let glyphs = shape_with_plan(face, plan, with_tatweels(buffer, &clusters, count));
+        let advance = run_advance(&glyphs);
+        if advance <= target_advance {
+            return Some((glyphs, advance, count));
+        }
+
+        count -= 1;
+    }
+
+    None
+}
+
+/// Returns a copy of the buffer with `count` tatweels spread over the given clusters.
+fn with_tatweels(buffer: &UnicodeBuffer, clusters: &[u32], count: usize) -> UnicodeBuffer {
+    let mut result = clone_buffer(buffer);
+    result.0.len = 0;
+
+    let mut prev_cluster = None;
+    let mut position = 0;
+    for info in &buffer.0.info[..buffer.0.len] {
+        // Insert only before the first character of a cluster.
+        if prev_cluster != Some(info.cluster) && clusters.contains(&info.cluster) {
+            let n = count / clusters.len() + usize::from(position < count % clusters.len());
+            for _ in 0..n {
+                result.0.add(TATWEEL, info.cluster);
+            }
+
+            position += 1;
+        }
+
+        result.0.add(info.glyph_id, info.cluster);
+        prev_cluster = Some(info.cluster);
+    }
+
+    result
+}
+
+fn clone_buffer(buffer: &UnicodeBuffer) -> UnicodeBuffer {
+    UnicodeBuffer(buffer.0.clone())
+}
+
+fn run_advance(glyphs: &GlyphBuffer) -> i32 {
+    let horizontal = glyphs.0.direction.is_horizontal();
+    glyphs
+        .glyph_positions()
+        .iter()
+        .map(|pos| {
+            if horizontal {
+                pos.x_advance
+            } else {
+                -pos.y_advance
+            }
+        })
+        .sum()
+}
@@ -1,5 +1,8 @@
-use ttf_parser::{apple_layout, kern, GlyphId};
+#[cfg(not(feature = "std"))]
+use core_maths::CoreFloat;
+use ttf_parser::{apple_layout, kern, GlyphId, Tag};
 
+use super::algs::read_at;
 use super::buffer::*;
 use super::ot_layout::TableIndex;
 use super::ot_layout_common::lookup_flags;
@@ -15,10 +18,16 @@ pub fn hb_ot_layout_kern(plan: &hb_ot_shape_plan_t, face: &hb_font_t, buffer: &m
     };
 
     let mut seen_cross_stream = false;
-    for subtable in subtables {
-        if subtable.variable {
-            continue;
-        }
+    for (subtable, tuple_index) in subtables.into_iter().zip(tuple_indices(face)) {
+        // Variable subtables contain deltas for a single `gvar` shared tuple.
+        let scale = if subtable.variable {
+            match tuple_scalar(face, tuple_index) {
+                Some(scalar) if scalar != 0.0 => scalar,
+                _ => continue,
+            }
+        } else {
+            1.0
+        };
 
         if buffer.direction.is_horizontal() != subtable.horizontal {
             continue;
@@ -44,13 +53,13 @@ pub fn hb_ot_layout_kern(plan: &hb_ot_shape_plan_t, face: &hb_font_t, buffer: &m
         }
 
         if subtable.has_state_machine {
-            apply_state_machine_kerning(&subtable, plan.kern_mask, buffer);
+            apply_state_machine_kerning(&subtable, plan.kern_mask, scale, buffer);
         } else {
             if !plan.requested_kerning {
                 continue;
             }
 
-            apply_simple_kerning(&subtable, face, plan.kern_mask, buffer);
+            apply_simple_kerning(&subtable, face, plan.kern_mask, scale, buffer);
         }
 
         if reverse {
@@ -59,6 +68,71 @@ pub fn hb_ot_layout_kern(plan: &hb_ot_shape_plan_t, face: &hb_font_t, buffer: &m
     }
 }
 
+/// Returns variation tuple indices of `kern` subtables, which are not exposed by `ttf-parser`.
+///
+/// OpenType subtables cannot be variable, so they always get 0.
+fn tuple_indices<'a>(face: &hb_font_t<'a>) -> impl Iterator<Item = u16> + 'a {
+    let data = face
+        .raw_face()
+        .table(Tag::from_bytes(b"kern"))
+        .unwrap_or_default();
+
+    // Apple tables start with a 1.0 version, while OpenType ones start with 0.
+    let is_aat = read_at::<u16>(data, 0) == Some(1);
+    let mut offset = 8;
+    core::iter::from_fn(move || {
+        if !is_aat {
+            return Some(0);
+        }
+
+        let length = read_at::<u32>(data, offset)?;
+        let tuple_index = read_at::<u16>(data, offset + 6)?;
+        offset = offset.checked_add(usize::try_from(length).ok()?)?;
+        Some(tuple_index)
+    })
+}
+
+/// Returns the scalar of a `gvar` shared tuple for the current variation coordinates.
+fn tuple_scalar(face: &hb_font_t, tuple_index: u16) -> Option<f32> {
+    let data = face.raw_face().table(Tag::from_bytes(b"gvar"))?;
+    let axis_count = usize::from(read_at::<u16>(data, 4)?);
+    let shared_tuple_count = read_at::<u16>(data, 6)?;
+    let shared_tuples_offset = usize::try_from(read_at::<u32>(data, 8)?).ok()?;
+    if tuple_index >= shared_tuple_count {
+        return None;
+    }
+
+    let tuple_offset = shared_tuples_offset + usize::from(tuple_index) * axis_count * 2;
+    let coords = face.variation_coordinates();
+
+    let mut scalar = 1.0;
+    for i in 0..axis_count {
+        let peak = read_at::<i16>(data, tuple_offset + i * 2)?;
+        if peak == 0 {
+            continue;
+        }
+
+        let v = coords.get(i).map(|c| c.get()).unwrap_or(0);
+        if v == 0 || (v < 0) != (peak < 0) {
+            return Some(0.0);
+        }
+
+        if v.unsigned_abs() < peak.unsigned_abs() {
+            scalar *= f32::from(v) / f32::from(peak);
+        }
+    }
+
+    Some(scalar)
+}
+
+fn scale_kerning(value: i32, scale: f32) -> i32 {
+    if scale == 1.0 {
+        value
+    } else {
+        (value as f32 * scale).round() as i32
+    }
+}
+
 // TODO: remove
 fn machine_kern(
     face: &hb_font_t,
@@ -131,6 +205,7 @@ fn apply_simple_kerning(
     subtable: &kern::Subtable,
     face: &hb_font_t,
     kern_mask: hb_mask_t,
+    scale: f32,
     buffer: &mut hb_buffer_t,
 ) {
     machine_kern(
@@ -141,7 +216,7 @@ fn apply_simple_kerning(
         |left, right| {
             subtable
                 .glyphs_kerning(GlyphId(left as u16), GlyphId(right as u16))
-                .map(i32::from)
+                .map(|value| scale_kerning(i32::from(value), scale))
                 .unwrap_or(0)
         },
     );
@@ -155,6 +230,7 @@ struct StateMachineDriver {
 fn apply_state_machine_kerning(
     subtable: &kern::Subtable,
     kern_mask: hb_mask_t,
+    scale: f32,
     buffer: &mut hb_buffer_t,
 ) {
     let state_table = match subtable.format {
@@ -216,6 +292,7 @@ fn apply_state_machine_kerning(
             entry,
             subtable.has_cross_stream,
             kern_mask,
+            scale,
             state_table,
             &mut driver,
             buffer,
@@ -238,6 +315,7 @@ fn state_machine_transition(
     entry: apple_layout::StateEntry,
     has_cross_stream: bool,
     kern_mask: hb_mask_t,
+    scale: f32,
     state_table: &apple_layout::StateTable,
     driver: &mut StateMachineDriver,
     buffer: &mut hb_buffer_t,
@@ -279,6 +357,11 @@ fn state_machine_transition(
             last = v & 1 != 0;
             v &= !1;
 
+            // The reset value is a flag and must not be scaled.
+            if v != -0x8000 {
+                v = scale_kerning(v, scale);
+            }
+
             // Testing shows that CoreText only applies kern (cross-stream or not)
             // if none has been applied by previous subtables. That is, it does
             // NOT seem to accumulate as otherwise implied by specs.
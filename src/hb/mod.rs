@@ -6,28 +6,45 @@
 mod algs;
 #[macro_use]
 pub mod buffer;
-mod aat_layout;
+pub mod aat_layout;
 mod aat_layout_kerx_table;
+mod aat_layout_mort_table;
 mod aat_layout_morx_table;
+mod aat_layout_opbd_table;
 mod aat_layout_trak_table;
 mod aat_map;
 pub mod common;
 pub mod face;
+#[cfg(feature = "graphite")]
+mod graphite;
+#[cfg(feature = "graphite")]
+mod graphite_face;
+#[cfg(feature = "graphite")]
+mod graphite_pass;
+#[cfg(feature = "graphite")]
+mod graphite_segment;
+#[cfg(feature = "graphite")]
+mod graphite_vm;
+pub mod itemize;
+pub mod justify;
 mod kerning;
 mod machine_cursor;
+pub mod optical_bounds;
 mod ot;
-mod ot_layout;
+pub mod ot_layout;
 mod ot_layout_common;
 mod ot_layout_gpos_table;
 mod ot_layout_gsub_table;
 mod ot_layout_gsubgpos;
 mod ot_map;
+pub mod ot_math;
 mod ot_shape;
 mod ot_shape_fallback;
-mod ot_shape_normalize;
+pub mod ot_shape_normalize;
 pub mod ot_shape_plan;
 mod ot_shaper;
 mod ot_shaper_arabic;
+mod ot_shaper_arabic_fallback;
 mod ot_shaper_arabic_table;
 mod ot_shaper_hangul;
 mod ot_shaper_hebrew;
@@ -47,15 +64,19 @@ mod ot_shaper_use_machine;
 mod ot_shaper_use_table;
 mod aat_layout_common;
 mod ot_shaper_vowel_constraints;
+pub mod ot_var;
 mod paint_extents;
+pub mod paragraph;
 mod set_digest;
 pub mod shape;
+pub mod shape_hooks;
+pub mod shape_plan_cache;
 #[cfg(feature = "wasm-shaper")]
-mod shape_wasm;
-mod tag;
+pub mod shape_wasm;
+pub mod tag;
 mod tag_table;
 mod text_parser;
-mod unicode;
+pub mod unicode;
 mod unicode_norm;
 
 use ttf_parser::Tag as hb_tag_t;
@@ -65,4 +86,4 @@ use self::face::hb_font_t;
 
 type hb_mask_t = u32;
 
-use self::common::{script, Direction, Feature, Language, Script};
+use self::common::{script, Direction, Feature, Language, LayoutTags, Script};
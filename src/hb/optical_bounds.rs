@@ -0,0 +1,148 @@
+use ttf_parser::gpos::{PositioningSubtable, SingleAdjustment, ValueRecord};
+use ttf_parser::{GlyphId, Tag};
+
+use super::aat_layout_opbd_table;
+use super::ot_layout::{LayoutTableExt, TableIndex};
+use super::ot_layout_gpos_table::DeviceExt;
+use super::ot_map::find_variation_index;
+use super::{hb_font_t, tag, Language, Script};
+use crate::{GlyphBuffer, GlyphPosition};
+
+/// Optical bounds of a shaped line.
+///
+/// Values are in font units and positive when the outermost glyph is allowed
+/// to protrude into the margin, like punctuation in hanging punctuation.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct hb_optical_bounds_t {
+    /// How far the leftmost glyph can be moved past the left margin.
+    pub left: i32,
+    /// How far the rightmost glyph can be moved past the right margin.
+    pub right: i32,
+}
+
+impl hb_font_t<'_> {
+    /// Returns the optical bounds adjustments for the first and the last glyph of a shaped line.
+    ///
+    /// The AAT `opbd` table is used when present, either in the distance or
+    /// in the control point format. Otherwise, the OpenType `lfbd` and `rtbd`
+    /// features are applied to the outermost glyphs as single positioning lookups.
+    /// The features are looked up in the script and language system selected by the
+    /// buffer script and language, like during shaping.
+    ///
+    /// Control points are read from the default outline of simple `glyf` glyphs,
+    /// so `gvar` variations are not applied and composite glyphs fall back to `ankr`.
+    ///
+    /// Only horizontal buffers are supported. Returns zero bounds for vertical ones.
+    pub fn optical_bounds(&self, buffer: &GlyphBuffer) -> hb_optical_bounds_t {
+        let mut bounds = hb_optical_bounds_t::default();
+        if !buffer.0.direction.is_horizontal() {
+            return bounds;
+        }
+
+        let infos = buffer.glyph_infos();
+        let positions = buffer.glyph_positions();
+        let (Some(first), Some(last)) = (infos.first(), infos.last()) else {
+            return bounds;
+        };
+
+        let first_pos = positions[0];
+        let last_pos = positions[positions.len() - 1];
+        let first = GlyphId(first.glyph_id as u16);
+        let last = GlyphId(last.glyph_id as u16);
+        let script = buffer.0.script;
+        let language = buffer.0.language.as_ref();
+
+        bounds.left = match aat_layout_opbd_table::optical_edges(self, first) {
+            Some((left, _)) => first_pos.x_offset + left,
+            None => self
+                .bounds_adjustment(script, language, Tag::from_bytes(b"lfbd"), first)
+                .map(|record| -self.x_placement(&record))
+                .unwrap_or(0),
+        };
+
+        bounds.right = match aat_layout_opbd_table::optical_edges(self, last) {
+            Some((_, right)) => right_protrusion(&last_pos, right),
+            None => self
+                .bounds_adjustment(script, language, Tag::from_bytes(b"rtbd"), last)
+                .map(|record| -self.x_advance(&record))
+                .unwrap_or(0),
+        };
+
+        bounds
+    }
+
+    // Finds the first single positioning value for the glyph in the lookups of a feature.
+    // The feature is resolved the same way `hb_ot_map_builder_t` does it.
+    fn bounds_adjustment(
+        &self,
+        script: Option<Script>,
+        language: Option<&Language>,
+        feature_tag: Tag,
+        glyph: GlyphId,
+    ) -> Option<ValueRecord<'_>> {
+        let gpos = self.gpos.as_ref()?;
+        let table = &gpos.inner;
+
+        let (script_tags, lang_tags) = tag::hb_ot_tags_from_script_and_language(script, language);
+        let (_, script_index, _) = table.select_script(&script_tags)?;
+        let lang_index = table.select_script_language(script_index, &lang_tags);
+        let feature_index = table.find_language_feature(script_index, lang_index, feature_tag)?;
+
+        let variation_index = find_variation_index(self)[TableIndex::GPOS];
+        let feature = match variation_index {
+            Some(idx) => table
+                .variations
+                .and_then(|var| var.find_substitute(feature_index, idx))
+                .or_else(|| table.features.get(feature_index))?,
+            None => table.features.get(feature_index)?,
+        };
+
+        for lookup_index in feature.lookup_indices {
+            let Some(lookup) = gpos.lookups.get(usize::from(lookup_index)) else {
+                continue;
+            };
+
+            for subtable in &lookup.subtables {
+                if let PositioningSubtable::Single(single) = subtable {
+                    if let Some(record) = single_value(single, glyph) {
+                        return Some(record);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    fn x_placement(&self, record: &ValueRecord) -> i32 {
+        i32::from(record.x_placement)
+            + record
+                .x_placement_device
+                .and_then(|device| device.get_x_delta(self))
+                .unwrap_or(0)
+    }
+
+    fn x_advance(&self, record: &ValueRecord) -> i32 {
+        i32::from(record.x_advance)
+            + record
+                .x_advance_device
+                .and_then(|device| device.get_x_delta(self))
+                .unwrap_or(0)
+    }
+}
+
+fn single_value<'a>(single: &SingleAdjustment<'a>, glyph: GlyphId) -> Option<ValueRecord<'a>> {
+    match single {
+        SingleAdjustment::Format1 { coverage, value } => {
+            coverage.get(glyph)?;
+            Some(*value)
+        }
+        SingleAdjustment::Format2 { coverage, values } => values.get(coverage.get(glyph)?),
+    }
+}
+
+// The right optical edge is relative to the glyph origin, while the protrusion
+// is measured from the end of the glyph advance.
+fn right_protrusion(pos: &GlyphPosition, right_edge: i32) -> i32 {
+    pos.x_advance - (pos.x_offset + right_edge)
+}
@@ -52,13 +52,17 @@ pub fn hb_ot_layout_has_glyph_classes(face: &hb_font_t) -> bool {
 
 // get_gsubgpos_table
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A lookup-based OpenType layout table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum TableIndex {
+    /// The glyph substitution table.
     GSUB = 0,
+    /// The glyph positioning table.
     GPOS = 1,
 }
 
 impl TableIndex {
+    /// Returns an iterator over both tables, GSUB first.
     pub fn iter() -> impl Iterator<Item = TableIndex> {
         [Self::GSUB, Self::GPOS].iter().copied()
     }
@@ -266,7 +270,14 @@ pub fn apply_layout_table<T: LayoutTable>(
     }
 }
 
-fn apply_string<T: LayoutTable>(ctx: &mut OT::hb_ot_apply_context_t, lookup: &T::Lookup) {
+/// Applies a single lookup to the whole buffer.
+///
+/// The lookup does not have to come from the font, which allows shapers
+/// to apply lookups synthesised from other data.
+pub fn apply_string<T: LayoutTable>(
+    ctx: &mut OT::hb_ot_apply_context_t,
+    lookup: &impl LayoutLookup,
+) {
     if ctx.buffer.is_empty() || ctx.lookup_mask() == 0 {
         return;
     }
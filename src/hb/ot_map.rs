@@ -1,6 +1,7 @@
 use alloc::vec::Vec;
 use core::cmp::Ordering;
 use core::ops::Range;
+use smallvec::SmallVec;
 use ttf_parser::FromData;
 
 use ttf_parser::opentype_layout::{
@@ -10,14 +11,18 @@ use ttf_parser::opentype_layout::{
 use super::buffer::{glyph_flag, hb_buffer_t};
 use super::ot_layout::{LayoutTableExt, TableIndex};
 use super::ot_shape_plan::hb_ot_shape_plan_t;
-use super::{hb_font_t, hb_mask_t, hb_tag_t, tag, Language, Script};
+use super::{hb_font_t, hb_mask_t, hb_tag_t, tag, Language, LayoutTags, Script};
 
 pub struct hb_ot_map_t {
     found_script: [bool; 2],
     chosen_script: [Option<hb_tag_t>; 2],
+    chosen_language: [Option<hb_tag_t>; 2],
+    variation_index: [Option<VariationIndex>; 2],
     global_mask: hb_mask_t,
     features: Vec<feature_map_t>,
     lookups: [Vec<lookup_map_t>; 2],
+    // Tags of the features each lookup in `lookups` was added for.
+    lookup_features: [Vec<LookupFeatures>; 2],
     stages: [Vec<StageMap>; 2],
 }
 
@@ -35,6 +40,8 @@ pub struct feature_map_t {
     auto_zwj: bool,
     random: bool,
     per_syllable: bool,
+    // Feature not found in the font, but has a fallback implementation.
+    needs_fallback: bool,
 }
 
 impl Ord for feature_map_t {
@@ -60,6 +67,14 @@ pub struct lookup_map_t {
     pub per_syllable: bool,
 }
 
+type LookupFeatures = SmallVec<[hb_tag_t; 2]>;
+
+type LookupStages = (
+    [Vec<lookup_map_t>; 2],
+    [Vec<LookupFeatures>; 2],
+    [Vec<StageMap>; 2],
+);
+
 #[derive(Clone, Copy)]
 pub struct StageMap {
     // Cumulative
@@ -85,6 +100,17 @@ impl hb_ot_map_t {
         self.chosen_script[table_index]
     }
 
+    /// Returns the tag of the selected language system, or `None` for the default one.
+    #[inline]
+    pub fn chosen_language(&self, table_index: TableIndex) -> Option<hb_tag_t> {
+        self.chosen_language[table_index]
+    }
+
+    #[inline]
+    pub fn variation_index(&self, table_index: TableIndex) -> Option<VariationIndex> {
+        self.variation_index[table_index]
+    }
+
     #[inline]
     pub fn get_global_mask(&self) -> hb_mask_t {
         self.global_mask
@@ -106,6 +132,13 @@ impl hb_ot_map_t {
             .map_or(0, |idx| self.features[idx].one_mask)
     }
 
+    #[inline]
+    pub fn needs_fallback(&self, feature_tag: hb_tag_t) -> bool {
+        self.features
+            .binary_search_by_key(&feature_tag, |f| f.tag)
+            .is_ok_and(|idx| self.features[idx].needs_fallback)
+    }
+
     #[inline]
     pub fn get_feature_index(
         &self,
@@ -130,6 +163,19 @@ impl hb_ot_map_t {
             .ok()
     }
 
+    /// Returns the tags of the features that were found in the table, in tag order.
+    pub fn found_features(&self, table_index: TableIndex) -> impl Iterator<Item = hb_tag_t> + '_ {
+        self.features
+            .iter()
+            .filter(move |f| f.index[table_index].is_some())
+            .map(|f| f.tag)
+    }
+
+    #[inline]
+    pub fn lookup_features(&self, table_index: TableIndex, index: usize) -> &[hb_tag_t] {
+        &self.lookup_features[table_index][index]
+    }
+
     #[inline]
     pub fn stages(&self, table_index: TableIndex) -> &[StageMap] {
         &self.stages[table_index]
@@ -178,6 +224,8 @@ pub struct hb_ot_map_builder_t<'a> {
     script_index: [Option<ScriptIndex>; 2],
     chosen_script: [Option<hb_tag_t>; 2],
     lang_index: [Option<LanguageIndex>; 2],
+    chosen_language: [Option<hb_tag_t>; 2],
+    variation_index: [Option<VariationIndex>; 2],
     current_stage: [usize; 2],
     feature_infos: Vec<feature_info_t>,
     stages: [Vec<stage_info_t>; 2],
@@ -211,15 +259,26 @@ impl<'a> hb_ot_map_builder_t<'a> {
         face: &'a hb_font_t<'a>,
         script: Option<Script>,
         language: Option<&Language>,
+        layout_tags: LayoutTags,
     ) -> Self {
         // Fetch script/language indices for GSUB/GPOS.  We need these later to skip
         // features not available in either table and not waste precious bits for them.
-        let (script_tags, lang_tags) = tag::tags_from_script_and_language(script, language);
+        let (mut script_tags, mut lang_tags) =
+            tag::hb_ot_tags_from_script_and_language(script, language);
+
+        // Explicit tags take precedence over the derived ones.
+        if let Some(tag) = layout_tags.script {
+            script_tags = smallvec::smallvec![tag];
+        }
+        if let Some(tag) = layout_tags.language {
+            lang_tags = smallvec::smallvec![tag];
+        }
 
         let mut found_script = [false; 2];
         let mut script_index = [None; 2];
         let mut chosen_script = [None; 2];
         let mut lang_index = [None; 2];
+        let mut chosen_language = [None; 2];
 
         for (table_index, table) in face.layout_tables() {
             if let Some((found, idx, tag)) = table.select_script(&script_tags) {
@@ -227,8 +286,13 @@ impl<'a> hb_ot_map_builder_t<'a> {
                 found_script[table_index] = found;
                 script_index[table_index] = Some(idx);
 
-                if let Some(idx) = table.select_script_language(idx, &lang_tags) {
-                    lang_index[table_index] = Some(idx);
+                if let Some(lang_idx) = table.select_script_language(idx, &lang_tags) {
+                    lang_index[table_index] = Some(lang_idx);
+                    chosen_language[table_index] = table
+                        .scripts
+                        .get(idx)
+                        .and_then(|script| script.languages.get(lang_idx))
+                        .map(|lang| lang.tag);
                 }
             }
         }
@@ -239,6 +303,8 @@ impl<'a> hb_ot_map_builder_t<'a> {
             script_index,
             chosen_script,
             lang_index,
+            chosen_language,
+            variation_index: find_variation_index(face),
             current_stage: [0, 0],
             feature_infos: Vec::new(),
             stages: [Vec::new(), Vec::new()],
@@ -333,15 +399,18 @@ impl<'a> hb_ot_map_builder_t<'a> {
         self.add_gsub_pause(None);
         self.add_gpos_pause(None);
 
-        let (lookups, stages) =
-            self.collect_lookup_stages(&features, required_index, required_stage);
+        let (lookups, lookup_features, stages) =
+            self.collect_lookup_stages(&features, required_index, required_tag, required_stage);
 
         hb_ot_map_t {
             found_script: self.found_script,
             chosen_script: self.chosen_script,
+            chosen_language: self.chosen_language,
+            variation_index: self.variation_index,
             global_mask,
             features,
             lookups,
+            lookup_features,
             stages,
         }
     }
@@ -427,6 +496,7 @@ impl<'a> hb_ot_map_builder_t<'a> {
                 auto_zwj: info.flags & F_MANUAL_ZWJ == 0,
                 random: info.flags & F_RANDOM != 0,
                 per_syllable: info.flags & F_PER_SYLLABLE != 0,
+                needs_fallback: !found,
             });
         }
 
@@ -481,9 +551,10 @@ impl<'a> hb_ot_map_builder_t<'a> {
         &self,
         map_features: &[feature_map_t],
         required_feature_index: [Option<FeatureIndex>; 2],
+        required_feature_tag: [Option<hb_tag_t>; 2],
         required_feature_stage: [usize; 2],
-    ) -> ([Vec<lookup_map_t>; 2], [Vec<StageMap>; 2]) {
-        let mut map_lookups = [Vec::new(), Vec::new()];
+    ) -> LookupStages {
+        let mut map_lookups: [Vec<(lookup_map_t, LookupFeatures)>; 2] = [Vec::new(), Vec::new()];
         let mut map_stages = [Vec::new(), Vec::new()];
 
         for table_index in TableIndex::iter() {
@@ -491,25 +562,33 @@ impl<'a> hb_ot_map_builder_t<'a> {
             let mut stage_index = 0;
             let mut last_lookup = 0;
 
-            let coords = self.face.ttfp_face.variation_coordinates();
-            let variation_index = self
-                .face
-                .layout_table(table_index)
-                .and_then(|t| t.variations?.find_index(coords));
+            let variation_index = self.variation_index[table_index];
 
             for stage in 0..self.current_stage[table_index] {
-                if let Some(feature_index) = required_feature_index[table_index] {
+                if let (Some(feature_index), Some(feature_tag)) = (
+                    required_feature_index[table_index],
+                    required_feature_tag[table_index],
+                ) {
                     if required_feature_stage[table_index] == stage {
+                        let required_feature = feature_map_t {
+                            tag: feature_tag,
+                            index: [None; 2],
+                            stage: [stage; 2],
+                            shift: 0,
+                            mask: GLOBAL_BIT_MASK,
+                            one_mask: GLOBAL_BIT_MASK,
+                            auto_zwnj: true,
+                            auto_zwj: true,
+                            random: false,
+                            per_syllable: false,
+                            needs_fallback: false,
+                        };
                         self.add_lookups(
                             &mut map_lookups[table_index],
                             table_index,
                             feature_index,
                             variation_index,
-                            GLOBAL_BIT_MASK,
-                            true,
-                            true,
-                            false,
-                            false,
+                            &required_feature,
                         );
                     }
                 }
@@ -522,11 +601,7 @@ impl<'a> hb_ot_map_builder_t<'a> {
                                 table_index,
                                 feature_index,
                                 variation_index,
-                                feature.mask,
-                                feature.auto_zwnj,
-                                feature.auto_zwj,
-                                feature.random,
-                                feature.per_syllable,
+                                feature,
                             );
                         }
                     }
@@ -537,17 +612,25 @@ impl<'a> hb_ot_map_builder_t<'a> {
                 let len = lookups.len();
 
                 if last_lookup + 1 < len {
-                    lookups[last_lookup..].sort();
+                    lookups[last_lookup..].sort_by_key(|a| a.0);
 
                     let mut j = last_lookup;
                     for i in j + 1..len {
-                        if lookups[i].index != lookups[j].index {
+                        if lookups[i].0.index != lookups[j].0.index {
                             j += 1;
-                            lookups[j] = lookups[i];
+                            lookups.swap(i, j);
                         } else {
-                            lookups[j].mask |= lookups[i].mask;
-                            lookups[j].auto_zwnj &= lookups[i].auto_zwnj;
-                            lookups[j].auto_zwj &= lookups[i].auto_zwj;
+                            let other = lookups[i].0;
+                            let features = core::mem::take(&mut lookups[i].1);
+                            let (lookup, merged_features) = &mut lookups[j];
+                            lookup.mask |= other.mask;
+                            lookup.auto_zwnj &= other.auto_zwnj;
+                            lookup.auto_zwj &= other.auto_zwj;
+                            for tag in features {
+                                if !merged_features.contains(&tag) {
+                                    merged_features.push(tag);
+                                }
+                            }
                         }
                     }
 
@@ -569,20 +652,24 @@ impl<'a> hb_ot_map_builder_t<'a> {
             }
         }
 
-        (map_lookups, map_stages)
+        let [gsub_lookups, gpos_lookups] = map_lookups;
+        let (gsub_lookups, gsub_features) = gsub_lookups.into_iter().unzip();
+        let (gpos_lookups, gpos_features) = gpos_lookups.into_iter().unzip();
+
+        (
+            [gsub_lookups, gpos_lookups],
+            [gsub_features, gpos_features],
+            map_stages,
+        )
     }
 
     fn add_lookups(
         &self,
-        lookups: &mut Vec<lookup_map_t>,
+        lookups: &mut Vec<(lookup_map_t, LookupFeatures)>,
         table_index: TableIndex,
         feature_index: FeatureIndex,
         variation_index: Option<VariationIndex>,
-        mask: hb_mask_t,
-        auto_zwnj: bool,
-        auto_zwj: bool,
-        random: bool,
-        per_syllable: bool,
+        feature_map: &feature_map_t,
     ) -> Option<()> {
         let table = self.face.layout_table(table_index)?;
 
@@ -597,17 +684,31 @@ impl<'a> hb_ot_map_builder_t<'a> {
 
         for index in feature.lookup_indices {
             if index < lookup_count {
-                lookups.push(lookup_map_t {
-                    mask,
-                    index,
-                    auto_zwnj,
-                    auto_zwj,
-                    random,
-                    per_syllable,
-                });
+                lookups.push((
+                    lookup_map_t {
+                        mask: feature_map.mask,
+                        index,
+                        auto_zwnj: feature_map.auto_zwnj,
+                        auto_zwj: feature_map.auto_zwj,
+                        random: feature_map.random,
+                        per_syllable: feature_map.per_syllable,
+                    },
+                    smallvec::smallvec![feature_map.tag],
+                ));
             }
         }
 
         Some(())
     }
 }
+
+/// Finds the `FeatureVariations` records matching the current face coordinates.
+pub fn find_variation_index(face: &hb_font_t) -> [Option<VariationIndex>; 2] {
+    let coords = face.ttfp_face.variation_coordinates();
+    let mut variation_index = [None; 2];
+    for (table_index, table) in face.layout_tables() {
+        variation_index[table_index] = table.variations.and_then(|v| v.find_index(coords));
+    }
+
+    variation_index
+}
@@ -0,0 +1,595 @@
+use alloc::vec::Vec;
+
+use ttf_parser::math::{GlyphAssembly, GlyphConstruction, Kern, MathValue};
+use ttf_parser::GlyphId;
+
+use super::face::hb_glyph_extents_t;
+use super::hb_font_t;
+use super::ot_layout_gpos_table::DeviceExt;
+use crate::Direction;
+
+/// A math constant from the `MATH` table.
+///
+/// The order matches the `MathConstants` table layout.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum hb_ot_math_constant_t {
+    ScriptPercentScaleDown,
+    ScriptScriptPercentScaleDown,
+    DelimitedSubFormulaMinHeight,
+    DisplayOperatorMinHeight,
+    MathLeading,
+    AxisHeight,
+    AccentBaseHeight,
+    FlattenedAccentBaseHeight,
+    SubscriptShiftDown,
+    SubscriptTopMax,
+    SubscriptBaselineDropMin,
+    SuperscriptShiftUp,
+    SuperscriptShiftUpCramped,
+    SuperscriptBottomMin,
+    SuperscriptBaselineDropMax,
+    SubSuperscriptGapMin,
+    SuperscriptBottomMaxWithSubscript,
+    SpaceAfterScript,
+    UpperLimitGapMin,
+    UpperLimitBaselineRiseMin,
+    LowerLimitGapMin,
+    LowerLimitBaselineDropMin,
+    StackTopShiftUp,
+    StackTopDisplayStyleShiftUp,
+    StackBottomShiftDown,
+    StackBottomDisplayStyleShiftDown,
+    StackGapMin,
+    StackDisplayStyleGapMin,
+    StretchStackTopShiftUp,
+    StretchStackBottomShiftDown,
+    StretchStackGapAboveMin,
+    StretchStackGapBelowMin,
+    FractionNumeratorShiftUp,
+    FractionNumeratorDisplayStyleShiftUp,
+    FractionDenominatorShiftDown,
+    FractionDenominatorDisplayStyleShiftDown,
+    FractionNumeratorGapMin,
+    FractionNumDisplayStyleGapMin,
+    FractionRuleThickness,
+    FractionDenominatorGapMin,
+    FractionDenomDisplayStyleGapMin,
+    SkewedFractionHorizontalGap,
+    SkewedFractionVerticalGap,
+    OverbarVerticalGap,
+    OverbarRuleThickness,
+    OverbarExtraAscender,
+    UnderbarVerticalGap,
+    UnderbarRuleThickness,
+    UnderbarExtraDescender,
+    RadicalVerticalGap,
+    RadicalDisplayStyleVerticalGap,
+    RadicalRuleThickness,
+    RadicalExtraAscender,
+    RadicalKernBeforeDegree,
+    RadicalKernAfterDegree,
+    RadicalDegreeBottomRaisePercent,
+}
+
+/// A corner of a glyph used for math kerning.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum hb_ot_math_kern_t {
+    TopRight,
+    TopLeft,
+    BottomRight,
+    BottomLeft,
+}
+
+/// A math kerning entry.
+///
+/// The kern value applies to all correction heights up to `max_correction_height`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct hb_ot_math_kern_entry_t {
+    /// The upper bound of the correction height range. `i32::MAX` for the last entry.
+    pub max_correction_height: i32,
+    /// The kern value for this range.
+    pub kern_value: i32,
+}
+
+/// A pre-made size variant of a math glyph.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct hb_ot_math_glyph_variant_t {
+    /// The variant glyph.
+    pub glyph: GlyphId,
+    /// The advance of the variant in the stretch direction.
+    pub advance: i32,
+}
+
+/// A part of a math glyph assembly recipe.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct hb_ot_math_glyph_part_t {
+    /// The part glyph.
+    pub glyph: GlyphId,
+    /// The length of the connector at the start of the part.
+    pub start_connector_length: i32,
+    /// The length of the connector at the end of the part.
+    pub end_connector_length: i32,
+    /// The full advance of the part in the stretch direction.
+    pub full_advance: i32,
+    /// Whether this part can be repeated (or skipped) to make the assembly longer.
+    pub is_extender: bool,
+}
+
+/// A glyph placed as part of a stretched assembly.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct hb_ot_math_assembly_glyph_t {
+    /// The part glyph.
+    pub glyph: GlyphId,
+    /// The position of the part start along the stretch direction,
+    /// measured from the start of the assembly.
+    ///
+    /// Parts are ordered bottom-to-top for vertical and left-to-right for horizontal assemblies.
+    pub offset: i32,
+    /// The full advance of the part.
+    pub advance: i32,
+    /// The overlap with the previous part. Zero for the first part.
+    pub overlap: i32,
+}
+
+/// A math glyph stretched by [`hb_ot_math_t::stretch_glyph`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum hb_ot_math_stretched_glyph_t {
+    /// A single glyph: either a pre-made variant or the original glyph.
+    Variant(hb_ot_math_glyph_variant_t),
+    /// Multiple glyphs assembled from parts.
+    Assembly {
+        /// Placed parts in assembly order.
+        parts: Vec<hb_ot_math_assembly_glyph_t>,
+        /// The total size of the assembly in the stretch direction.
+        size: i32,
+        /// The italics correction of the assembled glyph.
+        italics_correction: i32,
+    },
+}
+
+/// Access to the OpenType `MATH` table of a face.
+///
+/// All values are in font units, with variation and device deltas applied.
+#[derive(Clone, Copy)]
+pub struct hb_ot_math_t<'a, 'b> {
+    face: &'b hb_font_t<'a>,
+    table: ttf_parser::math::Table<'a>,
+}
+
+impl<'a> hb_font_t<'a> {
+    /// Returns the `MATH` table of the face, if any.
+    pub fn math(&self) -> Option<hb_ot_math_t<'a, '_>> {
+        Some(hb_ot_math_t {
+            face: self,
+            table: self.tables().math?,
+        })
+    }
+}
+
+impl<'a, 'b> hb_ot_math_t<'a, 'b> {
+    fn x_value(&self, value: MathValue) -> i32 {
+        i32::from(value.value)
+            + value
+                .device
+                .and_then(|device| device.get_x_delta(self.face))
+                .unwrap_or(0)
+    }
+
+    fn y_value(&self, value: MathValue) -> i32 {
+        i32::from(value.value)
+            + value
+                .device
+                .and_then(|device| device.get_y_delta(self.face))
+                .unwrap_or(0)
+    }
+
+    /// Returns the value of a math constant.
+    ///
+    /// Percentages are returned as is, everything else is in font units.
+    pub fn constant(&self, constant: hb_ot_math_constant_t) -> i32 {
+        use hb_ot_math_constant_t as C;
+
+        let Some(c) = self.table.constants else {
+            return 0;
+        };
+
+        match constant {
+            C::ScriptPercentScaleDown => i32::from(c.script_percent_scale_down()),
+            C::ScriptScriptPercentScaleDown => i32::from(c.script_script_percent_scale_down()),
+            C::DelimitedSubFormulaMinHeight => i32::from(c.delimited_sub_formula_min_height()),
+            C::DisplayOperatorMinHeight => i32::from(c.display_operator_min_height()),
+            C::MathLeading => self.y_value(c.math_leading()),
+            C::AxisHeight => self.y_value(c.axis_height()),
+            C::AccentBaseHeight => self.y_value(c.accent_base_height()),
+            C::FlattenedAccentBaseHeight => self.y_value(c.flattened_accent_base_height()),
+            C::SubscriptShiftDown => self.y_value(c.subscript_shift_down()),
+            C::SubscriptTopMax => self.y_value(c.subscript_top_max()),
+            C::SubscriptBaselineDropMin => self.y_value(c.subscript_baseline_drop_min()),
+            C::SuperscriptShiftUp => self.y_value(c.superscript_shift_up()),
+            C::SuperscriptShiftUpCramped => self.y_value(c.superscript_shift_up_cramped()),
+            C::SuperscriptBottomMin => self.y_value(c.superscript_bottom_min()),
+            C::SuperscriptBaselineDropMax => self.y_value(c.superscript_baseline_drop_max()),
+            C::SubSuperscriptGapMin => self.y_value(c.sub_superscript_gap_min()),
+            C::SuperscriptBottomMaxWithSubscript => {
+                self.y_value(c.superscript_bottom_max_with_subscript())
+            }
+            C::SpaceAfterScript => self.x_value(c.space_after_script()),
+            C::UpperLimitGapMin => self.y_value(c.upper_limit_gap_min()),
+            C::UpperLimitBaselineRiseMin => self.y_value(c.upper_limit_baseline_rise_min()),
+            C::LowerLimitGapMin => self.y_value(c.lower_limit_gap_min()),
+            C::LowerLimitBaselineDropMin => self.y_value(c.lower_limit_baseline_drop_min()),
+            C::StackTopShiftUp => self.y_value(c.stack_top_shift_up()),
+            C::StackTopDisplayStyleShiftUp => self.y_value(c.stack_top_display_style_shift_up()),
+            C::StackBottomShiftDown => self.y_value(c.stack_bottom_shift_down()),
+            C::StackBottomDisplayStyleShiftDown => {
+                self.y_value(c.stack_bottom_display_style_shift_down())
+            }
+            C::StackGapMin => self.y_value(c.stack_gap_min()),
+            C::StackDisplayStyleGapMin => self.y_value(c.stack_display_style_gap_min()),
+            C::StretchStackTopShiftUp => self.y_value(c.stretch_stack_top_shift_up()),
+            C::StretchStackBottomShiftDown => self.y_value(c.stretch_stack_bottom_shift_down()),
+            C::StretchStackGapAboveMin => self.y_value(c.stretch_stack_gap_above_min()),
+            C::StretchStackGapBelowMin => self.y_value(c.stretch_stack_gap_below_min()),
+            C::FractionNumeratorShiftUp => self.y_value(c.fraction_numerator_shift_up()),
+            C::FractionNumeratorDisplayStyleShiftUp => {
+                self.y_value(c.fraction_numerator_display_style_shift_up())
+            }
+            C::FractionDenominatorShiftDown => self.y_value(c.fraction_denominator_shift_down()),
+            C::FractionDenominatorDisplayStyleShiftDown => {
+                self.y_value(c.fraction_denominator_display_style_shift_down())
+            }
+            C::FractionNumeratorGapMin => self.y_value(c.fraction_numerator_gap_min()),
+            C::FractionNumDisplayStyleGapMin => {
+                self.y_value(c.fraction_num_display_style_gap_min())
+            }
+            C::FractionRuleThickness => self.y_value(c.fraction_rule_thickness()),
+            C::FractionDenominatorGapMin => self.y_value(c.fraction_denominator_gap_min()),
+            C::FractionDenomDisplayStyleGapMin => {
+                self.y_value(c.fraction_denom_display_style_gap_min())
+            }
+            C::SkewedFractionHorizontalGap => self.x_value(c.skewed_fraction_horizontal_gap()),
+            C::SkewedFractionVerticalGap => self.y_value(c.skewed_fraction_vertical_gap()),
+            C::OverbarVerticalGap => self.y_value(c.overbar_vertical_gap()),
+            C::OverbarRuleThickness => self.y_value(c.overbar_rule_thickness()),
+            C::OverbarExtraAscender => self.y_value(c.overbar_extra_ascender()),
+            C::UnderbarVerticalGap => self.y_value(c.underbar_vertical_gap()),
+            C::UnderbarRuleThickness => self.y_value(c.underbar_rule_thickness()),
+            C::UnderbarExtraDescender => self.y_value(c.underbar_extra_descender()),
+            C::RadicalVerticalGap => self.y_value(c.radical_vertical_gap()),
+            C::RadicalDisplayStyleVerticalGap => {
+                self.y_value(c.radical_display_style_vertical_gap())
+            }
+            C::RadicalRuleThickness => self.y_value(c.radical_rule_thickness()),
+            C::RadicalExtraAscender => self.y_value(c.radical_extra_ascender()),
+            C::RadicalKernBeforeDegree => self.x_value(c.radical_kern_before_degree()),
+            C::RadicalKernAfterDegree => self.x_value(c.radical_kern_after_degree()),
+            C::RadicalDegreeBottomRaisePercent => {
+                i32::from(c.radical_degree_bottom_raise_percent())
+            }
+        }
+    }
+
+    /// Returns the italics correction of a glyph, or zero if it has none.
+    pub fn italics_correction(&self, glyph: GlyphId) -> i32 {
+        self.table
+            .glyph_info
+            .and_then(|info| info.italic_corrections)
+            .and_then(|values| values.get(glyph))
+            .map(|value| self.x_value(value))
+            .unwrap_or(0)
+    }
+
+    /// Returns the top accent attachment position of a glyph.
+    ///
+    /// Falls back to half of the glyph advance, like harfbuzz does.
+    pub fn top_accent_attachment(&self, glyph: GlyphId) -> i32 {
+        self.table
+            .glyph_info
+            .and_then(|info| info.top_accent_attachments)
+            .and_then(|values| values.get(glyph))
+            .map(|value| self.x_value(value))
+            .unwrap_or_else(|| self.face.glyph_h_advance(glyph) / 2)
+    }
+
+    /// Checks that a glyph is an extended shape.
+    pub fn is_extended_shape(&self, glyph: GlyphId) -> bool {
+        self.table
+            .glyph_info
+            .and_then(|info| info.extended_shapes)
+            .map(|coverage| coverage.contains(glyph))
+            .unwrap_or(false)
+    }
+
+    fn kern_table(&self, glyph: GlyphId, corner: hb_ot_math_kern_t) -> Option<Kern<'a>> {
+        let info = self.table.glyph_info?.kern_infos?.get(glyph)?;
+        match corner {
+            hb_ot_math_kern_t::TopRight => info.top_right,
+            hb_ot_math_kern_t::TopLeft => info.top_left,
+            hb_ot_math_kern_t::BottomRight => info.bottom_right,
+            hb_ot_math_kern_t::BottomLeft => info.bottom_left,
+        }
+    }
+
+    /// Returns the kerning value of a glyph corner at the given correction height.
+    pub fn kerning(
+        &self,
+        glyph: GlyphId,
+        corner: hb_ot_math_kern_t,
+        correction_height: i32,
+    ) -> i32 {
+        let Some(kern) = self.kern_table(glyph, corner) else {
+            return 0;
+        };
+
+        // Find the first height that is not below the correction height.
+        let mut i = 0;
+        let mut count = kern.count();
+        while count > 0 {
+            let half = count / 2;
+            let height = kern.height(i + half).map(|v| self.y_value(v)).unwrap_or(0);
+            if height < correction_height {
+                i += half + 1;
+                count -= half + 1;
+            } else {
+                count = half;
+            }
+        }
+
+        kern.kern(i).map(|v| self.x_value(v)).unwrap_or(0)
+    }
+
+    /// Returns all kerning entries of a glyph corner.
+    pub fn kernings(
+        &self,
+        glyph: GlyphId,
+        corner: hb_ot_math_kern_t,
+    ) -> Vec<hb_ot_math_kern_entry_t> {
+        let Some(kern) = self.kern_table(glyph, corner) else {
+            return Vec::new();
+        };
+
+        (0..=kern.count())
+            .map(|i| hb_ot_math_kern_entry_t {
+                max_correction_height: if i < kern.count() {
+                    kern.height(i).map(|v| self.y_value(v)).unwrap_or(0)
+                } else {
+                    i32::MAX
+                },
+                kern_value: kern.kern(i).map(|v| self.x_value(v)).unwrap_or(0),
+            })
+            .collect()
+    }
+
+    fn construction(&self, glyph: GlyphId, direction: Direction) -> Option<GlyphConstruction<'a>> {
+        let variants = self.table.variants?;
+        if direction.is_horizontal() {
+            variants.horizontal_constructions.get(glyph)
+        } else {
+            variants.vertical_constructions.get(glyph)
+        }
+    }
+
+    /// Returns the minimum overlap of connecting parts in a glyph assembly.
+    pub fn min_connector_overlap(&self) -> i32 {
+        self.table
+            .variants
+            .map(|v| i32::from(v.min_connector_overlap))
+            .unwrap_or(0)
+    }
+
+    /// Returns the pre-made size variants of a glyph in the given direction.
+    ///
+    /// Variants are ordered by increasing size.
+    pub fn glyph_variants(
+        &self,
+        glyph: GlyphId,
+        direction: Direction,
+    ) -> Vec<hb_ot_math_glyph_variant_t> {
+        let Some(construction) = self.construction(glyph, direction) else {
+            return Vec::new();
+        };
+
+        construction
+            .variants
+            .into_iter()
+            .map(|v| hb_ot_math_glyph_variant_t {
+                glyph: v.variant_glyph,
+                advance: i32::from(v.advance_measurement),
+            })
+            .collect()
+    }
+
+    /// Returns the assembly recipe of a glyph in the given direction
+    /// and the italics correction of the assembled glyph.
+    pub fn glyph_assembly(
+        &self,
+        glyph: GlyphId,
+        direction: Direction,
+    ) -> Option<(Vec<hb_ot_math_glyph_part_t>, i32)> {
+        let assembly = self.construction(glyph, direction)?.assembly?;
+        Some((
+            collect_parts(&assembly),
+            self.x_value(assembly.italics_correction),
+        ))
+    }
+
+    /// Stretches a glyph to cover at least `target_size` in the given direction.
+    ///
+    /// The smallest pre-made variant that is large enough is preferred.
+    /// When there is none, the glyph is assembled from parts, repeating extenders
+    /// as needed and distributing the overlaps between connectors. Assemblies are limited
+    /// to 1024 parts.
+    /// If the glyph cannot be assembled either, the largest available variant is returned.
+    pub fn stretch_glyph(
+        &self,
+        glyph: GlyphId,
+        direction: Direction,
+        target_size: i32,
+    ) -> hb_ot_math_stretched_glyph_t {
+        let mut largest = None;
+        if let Some(construction) = self.construction(glyph, direction) {
+            for v in construction.variants {
+                let variant = hb_ot_math_glyph_variant_t {
+                    glyph: v.variant_glyph,
+                    advance: i32::from(v.advance_measurement),
+                };
+
+                if variant.advance >= target_size {
+                    return hb_ot_math_stretched_glyph_t::Variant(variant);
+                }
+
+                largest = Some(variant);
+            }
+
+            if let Some(assembly) = construction.assembly {
+                if let Some(stretched) = self.assemble(&assembly, target_size) {
+                    return stretched;
+                }
+            }
+        }
+
+        let variant = largest.unwrap_or_else(|| hb_ot_math_glyph_variant_t {
+            glyph,
+            advance: self.glyph_size(glyph, direction),
+        });
+
+        hb_ot_math_stretched_glyph_t::Variant(variant)
+    }
+
+    fn glyph_size(&self, glyph: GlyphId, direction: Direction) -> i32 {
+        if direction.is_horizontal() {
+            self.face.glyph_h_advance(glyph)
+        } else {
+            let mut extents = hb_glyph_extents_t::default();
+            self.face.glyph_extents(glyph, &mut extents);
+            -extents.height
+        }
+    }
+
+    fn assemble(
+        &self,
+        assembly: &GlyphAssembly,
+        target_size: i32,
+    ) -> Option<hb_ot_math_stretched_glyph_t> {
+        let recipe = collect_parts(assembly);
+        // Limit the number of parts to keep hostile fonts in check.
+        if recipe.is_empty() || recipe.len() > MAX_ASSEMBLY_PARTS {
+            return None;
+        }
+
+        let min_overlap = self.min_connector_overlap();
+
+        // Sizes are computed in 64 bits, since they can exceed the target one by far.
+        let mut fixed_count = 0i64;
+        let mut fixed_advance = 0i64;
+        let mut extender_count = 0i64;
+        let mut extender_advance = 0i64;
+        for part in &recipe {
+            if part.is_extender {
+                extender_count += 1;
+                extender_advance += i64::from(part.full_advance);
+            } else {
+                fixed_count += 1;
+                fixed_advance += i64::from(part.full_advance);
+            }
+        }
+
+        // The largest size reachable with `n` repeats, using the minimal overlap everywhere.
+        let max_size = |n: i64| {
+            let count = fixed_count + n * extender_count;
+            fixed_advance + n * extender_advance - (count - 1).max(0) * i64::from(min_overlap)
+        };
+
+        // Each repeat of all extenders grows the assembly by this amount.
+        let target_size = i64::from(target_size);
+        let growth = extender_advance - extender_count * i64::from(min_overlap);
+        let repeats = if max_size(0) >= target_size || growth <= 0 {
+            0
+        } else {
+            // A positive growth implies at least one extender.
+            let max_repeats = (MAX_ASSEMBLY_PARTS as i64 - fixed_count) / extender_count;
+            ((target_size - max_size(0) + growth - 1) / growth).min(max_repeats)
+        };
+
+        let mut parts = Vec::new();
+        for part in &recipe {
+            let count = if part.is_extender { repeats } else { 1 };
+            for _ in 0..count {
+                parts.push(*part);
+            }
+        }
+
+        if parts.is_empty() {
+            return None;
+        }
+
+        // Start with the minimal overlaps and then shrink the assembly towards the target size
+        // by increasing them, without exceeding the connector lengths.
+        let max_overlaps: Vec<i32> = parts
+            .windows(2)
+            .map(|w| {
+                w[0].end_connector_length
+                    .min(w[1].start_connector_length)
+                    .max(min_overlap)
+            })
+            .collect();
+        let mut overlaps = alloc::vec![min_overlap; max_overlaps.len()];
+        let mut excess = max_size(repeats) - target_size;
+        while excess > 0 {
+            let open = overlaps
+                .iter()
+                .zip(&max_overlaps)
+                .filter(|(o, max)| o < max)
+                .count() as i64;
+            if open == 0 {
+                break;
+            }
+
+            let share = (excess / open).max(1);
+            for (overlap, max) in overlaps.iter_mut().zip(&max_overlaps) {
+                let add = share.min(i64::from(*max - *overlap)).min(excess);
+                // Bounded by the connector length.
+                *overlap += add as i32;
+                excess -= add;
+            }
+        }
+
+        let mut placed = Vec::with_capacity(parts.len());
+        let mut offset = 0;
+        for (i, part) in parts.iter().enumerate() {
+            let overlap = if i == 0 { 0 } else { overlaps[i - 1] };
+            offset -= overlap;
+            placed.push(hb_ot_math_assembly_glyph_t {
+                glyph: part.glyph,
+                offset,
+                advance: part.full_advance,
+                overlap,
+            });
+            offset += part.full_advance;
+        }
+
+        Some(hb_ot_math_stretched_glyph_t::Assembly {
+            parts: placed,
+            size: offset,
+            italics_correction: self.x_value(assembly.italics_correction),
+        })
+    }
+}
+
+const MAX_ASSEMBLY_PARTS: usize = 1024;
+
+fn collect_parts(assembly: &GlyphAssembly) -> Vec<hb_ot_math_glyph_part_t> {
+    assembly
+        .parts
+        .into_iter()
+        .map(|part| hb_ot_math_glyph_part_t {
+            glyph: part.glyph_id,
+            start_connector_length: i32::from(part.start_connector_length),
+            end_connector_length: i32::from(part.end_connector_length),
+            full_advance: i32::from(part.full_advance),
+            is_extender: part.part_flags.extender(),
+        })
+        .collect()
+}
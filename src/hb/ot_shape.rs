@@ -4,10 +4,13 @@ use super::ot_layout_gpos_table::GPOS;
 use super::ot_map::*;
 use super::ot_shape_plan::hb_ot_shape_plan_t;
 use super::ot_shaper::*;
-use super::unicode::{hb_unicode_general_category_t, CharExt, GeneralCategoryExt};
+use super::shape_hooks::hb_hook_buffer_t;
+use super::unicode::{
+    hb_unicode_funcs_default_t, hb_unicode_general_category_t, CharExt, GeneralCategoryExt,
+};
 use super::*;
 use super::{hb_font_t, hb_tag_t};
-use crate::hb::aat_layout::hb_aat_layout_remove_deleted_glyphs;
+use crate::hb::aat_layout::{hb_aat_layout_has_substitution, hb_aat_layout_remove_deleted_glyphs};
 use crate::hb::algs::{rb_flag, rb_flag_unsafe};
 use crate::hb::buffer::glyph_flag::{SAFE_TO_INSERT_TATWEEL, UNSAFE_TO_BREAK, UNSAFE_TO_CONCAT};
 use crate::hb::unicode::hb_gc::{
@@ -16,12 +19,14 @@ use crate::hb::unicode::hb_gc::{
     RB_UNICODE_GENERAL_CATEGORY_UPPERCASE_LETTER,
 };
 use crate::BufferFlags;
-use crate::{Direction, Feature, Language, Script};
+use crate::{Direction, Feature, Language, LayoutTags, Script};
 
 pub struct hb_ot_shape_planner_t<'a> {
     pub face: &'a hb_font_t<'a>,
     pub direction: Direction,
     pub script: Option<Script>,
+    pub language: Option<Language>,
+    pub layout_tags: LayoutTags,
     pub ot_map: hb_ot_map_builder_t<'a>,
     pub apply_morx: bool,
     pub script_zero_marks: bool,
@@ -35,8 +40,9 @@ impl<'a> hb_ot_shape_planner_t<'a> {
         direction: Direction,
         script: Option<Script>,
         language: Option<&Language>,
+        layout_tags: LayoutTags,
     ) -> Self {
-        let ot_map = hb_ot_map_builder_t::new(face, script, language);
+        let ot_map = hb_ot_map_builder_t::new(face, script, language, layout_tags);
 
         let mut shaper = match script {
             Some(script) => hb_ot_shape_complex_categorize(
@@ -51,8 +57,8 @@ impl<'a> hb_ot_shape_planner_t<'a> {
         let script_fallback_mark_positioning = shaper.fallback_position;
 
         // https://github.com/harfbuzz/harfbuzz/issues/2124
-        let apply_morx =
-            face.tables().morx.is_some() && (direction.is_horizontal() || face.gsub.is_none());
+        let apply_morx = hb_aat_layout_has_substitution(face)
+            && (direction.is_horizontal() || face.gsub.is_none());
 
         // https://github.com/harfbuzz/harfbuzz/issues/1528
         if apply_morx && shaper as *const _ != &DEFAULT_SHAPER as *const _ {
@@ -63,6 +69,8 @@ impl<'a> hb_ot_shape_planner_t<'a> {
             face,
             direction,
             script,
+            language: language.cloned(),
+            layout_tags,
             ot_map,
             apply_morx,
             script_zero_marks,
@@ -178,6 +186,11 @@ impl<'a> hb_ot_shape_planner_t<'a> {
         }
 
         for feature in user_features {
+            // AAT feature requests are handled by `hb_aat_map_builder_t`.
+            if feature.aat_selector.is_some() {
+                continue;
+            }
+
             let flags = if feature.is_global() { F_GLOBAL } else { empty };
             self.ot_map.add_feature(feature.tag, flags, feature.value);
         }
@@ -273,7 +286,11 @@ impl<'a> hb_ot_shape_planner_t<'a> {
         let mut plan = hb_ot_shape_plan_t {
             direction: self.direction,
             script: self.script,
+            language: self.language,
+            layout_tags: self.layout_tags,
             shaper: self.shaper,
+            normalization_mode: None,
+            hooks: None,
             ot_map,
             data: None,
             frac_mask,
@@ -300,7 +317,7 @@ impl<'a> hb_ot_shape_planner_t<'a> {
         };
 
         if let Some(func) = self.shaper.create_data {
-            plan.data = Some(func(&plan));
+            plan.data = Some(func(&plan, self.face));
         }
 
         plan
@@ -331,6 +348,12 @@ pub fn shape_internal(ctx: &mut hb_ot_shape_context_t) {
         func(ctx.plan, ctx.face, ctx.buffer);
     }
 
+    if let Some(hooks) = &ctx.plan.hooks {
+        let mut buffer = hb_hook_buffer_t::new(ctx.buffer, false);
+        hooks.pre_normalize(ctx.face, &mut buffer);
+        buffer.replace_invalid_codepoints();
+    }
+
     substitute_pre(ctx);
     position(ctx);
     substitute_post(ctx);
@@ -348,6 +371,12 @@ fn substitute_pre(ctx: &mut hb_ot_shape_context_t) {
     if ctx.plan.apply_morx && ctx.plan.apply_gpos {
         hb_aat_layout_remove_deleted_glyphs(&mut ctx.buffer);
     }
+
+    if let Some(hooks) = &ctx.plan.hooks {
+        let mut buffer = hb_hook_buffer_t::new(ctx.buffer, false);
+        hooks.post_substitute(ctx.face, &mut buffer);
+        buffer.replace_invalid_glyphs();
+    }
 }
 
 fn substitute_post(ctx: &mut hb_ot_shape_context_t) {
@@ -401,6 +430,16 @@ fn position(ctx: &mut hb_ot_shape_context_t) {
 
     position_complex(ctx);
 
+    if let Some(hooks) = &ctx.plan.hooks {
+        let mut buffer = hb_hook_buffer_t::new(ctx.buffer, true);
+        hooks.post_position(ctx.face, &mut buffer);
+        buffer.replace_invalid_glyphs();
+    }
+
+    if ctx.buffer.letter_spacing != 0 || ctx.buffer.word_spacing != 0 {
+        apply_spacing(ctx.buffer);
+    }
+
     if ctx.buffer.direction.is_backward() {
         ctx.buffer.reverse();
     }
@@ -500,6 +539,47 @@ fn position_by_plan(plan: &hb_ot_shape_plan_t, face: &hb_font_t, buffer: &mut hb
     }
 }
 
+// Like `trak`, letter spacing is added to each grapheme cluster and split around it.
+// Word spacing is added to the advance of space characters only.
+//
+// Applied to final positions, so that the rest of the cluster can be moved along.
+fn apply_spacing(buffer: &mut hb_buffer_t) {
+    let letter_spacing = buffer.letter_spacing;
+    let word_spacing = buffer.word_spacing;
+    let horizontal = buffer.direction.is_horizontal();
+    let forward = buffer.direction.is_forward();
+
+    foreach_grapheme!(buffer, start, end, {
+        let mut advance_to_add = letter_spacing;
+        if _hb_glyph_info_is_unicode_space(&buffer.info[start]) {
+            advance_to_add += word_spacing;
+        }
+        let offset_to_add = letter_spacing / 2;
+
+        for i in start..end {
+            // In forward directions, glyphs after the first one are past the added advance.
+            let mut delta = offset_to_add;
+            if forward && i > start {
+                delta -= advance_to_add;
+            }
+
+            let pos = &mut buffer.pos[i];
+            if horizontal {
+                if i == start {
+                    pos.x_advance += advance_to_add;
+                }
+                pos.x_offset += delta;
+            } else {
+                // Vertical advances grow downward.
+                if i == start {
+                    pos.y_advance -= advance_to_add;
+                }
+                pos.y_offset -= delta;
+            }
+        }
+    });
+}
+
 fn initialize_masks(ctx: &mut hb_ot_shape_context_t) {
     let global_mask = ctx.plan.ot_map.get_global_mask();
     ctx.buffer.reset_masks(global_mask);
@@ -513,7 +593,7 @@ fn setup_masks(ctx: &mut hb_ot_shape_context_t) {
     }
 
     for feature in &ctx.plan.user_features {
-        if !feature.is_global() {
+        if !feature.is_global() && feature.aat_selector.is_none() {
             let (mask, shift) = ctx.plan.ot_map.get_mask(feature.tag);
             ctx.buffer
                 .set_masks(feature.value << shift, mask, feature.start, feature.end);
@@ -602,6 +682,10 @@ fn set_unicode_props(buffer: &mut hb_buffer_t) {
     // https://www.unicode.org/reports/tr29/#Regex_Definitions
 
     let len = buffer.len;
+    let unicode = buffer
+        .unicode
+        .as_deref()
+        .unwrap_or(&hb_unicode_funcs_default_t);
 
     let mut i = 0;
     while i < len {
@@ -610,7 +694,7 @@ fn set_unicode_props(buffer: &mut hb_buffer_t) {
         // checker can understand.
         let (prior, later) = buffer.info.split_at_mut(i);
         let info = &mut later[0];
-        info.init_unicode_props(&mut buffer.scratch_flags);
+        info.init_unicode_props(unicode, &mut buffer.scratch_flags);
 
         let gen_cat = _hb_glyph_info_get_general_category(&info);
 
@@ -643,7 +727,7 @@ fn set_unicode_props(buffer: &mut hb_buffer_t) {
             _hb_glyph_info_set_continuation(info);
             if let Some(next) = buffer.info[..len].get_mut(i + 1) {
                 if next.as_char().is_emoji_extended_pictographic() {
-                    next.init_unicode_props(&mut buffer.scratch_flags);
+                    next.init_unicode_props(unicode, &mut buffer.scratch_flags);
                     _hb_glyph_info_set_continuation(next);
                     i += 1;
                 }
@@ -697,7 +781,9 @@ fn insert_dotted_circle(buffer: &mut hb_buffer_t, face: &hb_font_t) {
             ..hb_glyph_info_t::default()
         };
 
-        info.init_unicode_props(&mut buffer.scratch_flags);
+        let mut flags = buffer.scratch_flags;
+        info.init_unicode_props(buffer.unicode(), &mut flags);
+        buffer.scratch_flags = flags;
         buffer.clear_output();
         buffer.output_info(info);
         buffer.sync();
@@ -777,9 +863,14 @@ fn rotate_chars(ctx: &mut hb_ot_shape_context_t) {
 
     if ctx.target_direction.is_backward() {
         let rtlm_mask = ctx.plan.rtlm_mask;
+        let unicode = ctx
+            .buffer
+            .unicode
+            .as_deref()
+            .unwrap_or(&hb_unicode_funcs_default_t);
 
         for info in &mut ctx.buffer.info[..len] {
-            if let Some(c) = info.as_char().mirrored().map(u32::from) {
+            if let Some(c) = unicode.mirroring(info.as_char()).map(u32::from) {
                 if ctx.face.has_glyph(c) {
                     info.glyph_id = c;
                     continue;
@@ -30,14 +30,46 @@ impl hb_ot_shape_normalize_context_t<'_> {
     }
 }
 
-pub type hb_ot_shape_normalization_mode_t = i32;
-pub const HB_OT_SHAPE_NORMALIZATION_MODE_NONE: i32 = 0;
-pub const HB_OT_SHAPE_NORMALIZATION_MODE_DECOMPOSED: i32 = 1;
-pub const HB_OT_SHAPE_NORMALIZATION_MODE_COMPOSED_DIACRITICS: i32 = 2; /* Never composes base-to-base */
-pub const HB_OT_SHAPE_NORMALIZATION_MODE_COMPOSED_DIACRITICS_NO_SHORT_CIRCUIT: i32 = 3; /* Always fully decomposes and then recompose back */
-pub const HB_OT_SHAPE_NORMALIZATION_MODE_AUTO: i32 = 4; /* See hb-ot-shape-normalize.cc for logic. */
+/// How text is normalized before shaping.
+///
+/// Normalization only ever changes the text into a canonically equivalent one
+/// that the font supports better.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum hb_ot_shape_normalization_mode_t {
+    /// Characters are only decomposed when the font doesn't support them,
+    /// and never recomposed. Combining marks are still reordered.
+    None,
+    /// Characters are decomposed whenever the font supports their decomposition.
+    Decomposed,
+    /// Characters are kept as they are when the font supports them, and marks
+    /// are composed with their base when the font supports the result.
+    /// A base is never composed with a preceding base.
+    ComposedDiacritics,
+    /// Like [`ComposedDiacritics`](Self::ComposedDiacritics), but characters are
+    /// always fully decomposed before being recomposed.
+    ComposedDiacriticsNoShortCircuit,
+    /// Let the shaper decide. Currently the same as
+    /// [`ComposedDiacritics`](Self::ComposedDiacritics).
+    Auto,
+}
+
+pub const HB_OT_SHAPE_NORMALIZATION_MODE_NONE: hb_ot_shape_normalization_mode_t =
+    hb_ot_shape_normalization_mode_t::None;
+pub const HB_OT_SHAPE_NORMALIZATION_MODE_DECOMPOSED: hb_ot_shape_normalization_mode_t =
+    hb_ot_shape_normalization_mode_t::Decomposed;
+/* Never composes base-to-base */
+pub const HB_OT_SHAPE_NORMALIZATION_MODE_COMPOSED_DIACRITICS: hb_ot_shape_normalization_mode_t =
+    hb_ot_shape_normalization_mode_t::ComposedDiacritics;
+/* Always fully decomposes and then recompose back */
+pub const HB_OT_SHAPE_NORMALIZATION_MODE_COMPOSED_DIACRITICS_NO_SHORT_CIRCUIT:
+    hb_ot_shape_normalization_mode_t =
+    hb_ot_shape_normalization_mode_t::ComposedDiacriticsNoShortCircuit;
+/* See hb-ot-shape-normalize.cc for logic. */
+pub const HB_OT_SHAPE_NORMALIZATION_MODE_AUTO: hb_ot_shape_normalization_mode_t =
+    hb_ot_shape_normalization_mode_t::Auto;
 #[allow(dead_code)]
-pub const HB_OT_SHAPE_NORMALIZATION_MODE_DEFAULT: i32 = HB_OT_SHAPE_NORMALIZATION_MODE_AUTO;
+pub const HB_OT_SHAPE_NORMALIZATION_MODE_DEFAULT: hb_ot_shape_normalization_mode_t =
+    HB_OT_SHAPE_NORMALIZATION_MODE_AUTO;
 
 // HIGHLEVEL DESIGN:
 //
@@ -79,18 +111,18 @@ pub const HB_OT_SHAPE_NORMALIZATION_MODE_DEFAULT: i32 = HB_OT_SHAPE_NORMALIZATIO
 //     Indic shaper may want to disallow recomposing of two matras.
 
 fn decompose_unicode(
-    _: &hb_ot_shape_normalize_context_t,
+    ctx: &hb_ot_shape_normalize_context_t,
     ab: hb_codepoint_t,
 ) -> Option<(hb_codepoint_t, hb_codepoint_t)> {
-    super::unicode::decompose(ab)
+    ctx.buffer.unicode().decompose(ab)
 }
 
 fn compose_unicode(
-    _: &hb_ot_shape_normalize_context_t,
+    ctx: &hb_ot_shape_normalize_context_t,
     a: hb_codepoint_t,
     b: hb_codepoint_t,
 ) -> Option<hb_codepoint_t> {
-    super::unicode::compose(a, b)
+    ctx.buffer.unicode().compose(a, b)
 }
 
 fn set_glyph(info: &mut hb_glyph_info_t, font: &hb_font_t) {
@@ -103,10 +135,7 @@ fn output_char(buffer: &mut hb_buffer_t, unichar: u32, glyph: u32) {
     // This is very confusing indeed.
     buffer.cur_mut(0).set_glyph_index(glyph);
     buffer.output_glyph(unichar);
-    // TODO: should be _hb_glyph_info_set_unicode_props (&buffer->prev(), buffer);
-    let mut flags = buffer.scratch_flags;
-    buffer.prev_mut().init_unicode_props(&mut flags);
-    buffer.scratch_flags = flags;
+    buffer.init_out_unicode_props(buffer.out_len.saturating_sub(1));
 }
 
 fn next_char(buffer: &mut hb_buffer_t, glyph: u32) {
@@ -289,7 +318,9 @@ pub fn _hb_ot_shape_normalize(
         return;
     }
 
-    let mut mode = plan.shaper.normalization_preference;
+    let mut mode = plan
+        .normalization_mode
+        .unwrap_or(plan.shaper.normalization_preference);
     if mode == HB_OT_SHAPE_NORMALIZATION_MODE_AUTO {
         if plan.has_gpos_mark {
             // https://github.com/harfbuzz/harfbuzz/issues/653#issuecomment-423905920
@@ -316,6 +347,26 @@ pub fn _hb_ot_shape_normalize(
         || (mode != HB_OT_SHAPE_NORMALIZATION_MODE_DECOMPOSED
             && mode != HB_OT_SHAPE_NORMALIZATION_MODE_COMPOSED_DIACRITICS_NO_SHORT_CIRCUIT);
 
+    // Fast path: without marks there is nothing to reorder or recompose, so only the
+    // per-character decomposition of the first round is left. The buffer already knows
+    // whether it is pure ASCII, which never decomposes and has no marks unless custom
+    // Unicode functions say so, in which case it isn't flagged as ASCII.
+    let is_ascii = buffer.scratch_flags & HB_BUFFER_SCRATCH_FLAG_HAS_NON_ASCII == 0;
+    if is_ascii
+        || (might_short_circuit
+            && !buffer.info[..buffer.len]
+                .iter()
+                .any(_hb_glyph_info_is_unicode_mark))
+    {
+        buffer.clear_output();
+        buffer.idx = 0;
+        while ctx.buffer.idx < ctx.buffer.len && ctx.buffer.successful {
+            decompose_current_character(&mut ctx, might_short_circuit);
+        }
+        ctx.buffer.sync();
+        return;
+    }
+
     // We do a fairly straightforward yet custom normalization process in three
     // separate rounds: decompose, reorder, recompose (if desired).  Currently
     // this makes two buffer swaps.  We can make it faster by moving the last
@@ -469,12 +520,10 @@ pub fn _hb_ot_shape_normalize(
                         buffer.out_len -= 1;
 
                         // Modify starter and carry on.
-                        let mut flags = buffer.scratch_flags;
                         let info = &mut buffer.out_info_mut()[starter];
                         info.glyph_id = u32::from(composed);
                         info.set_glyph_index(u32::from(glyph_id.0));
-                        info.init_unicode_props(&mut flags);
-                        buffer.scratch_flags = flags;
+                        buffer.init_out_unicode_props(starter);
 
                         continue;
                     }
@@ -1,17 +1,25 @@
 use alloc::boxed::Box;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::any::Any;
 
+use super::ot_layout::TableIndex;
 use super::ot_map::*;
 use super::ot_shape::*;
+use super::ot_shape_normalize::hb_ot_shape_normalization_mode_t;
 use super::ot_shaper::*;
-use super::{hb_font_t, hb_mask_t, Direction, Feature, Language, Script};
+use super::shape_hooks::ShapeHooks;
+use super::{hb_font_t, hb_mask_t, hb_tag_t, Direction, Feature, Language, LayoutTags, Script};
 
 /// A reusable plan for shaping a text buffer.
 pub struct hb_ot_shape_plan_t {
     pub(crate) direction: Direction,
     pub(crate) script: Option<Script>,
+    pub(crate) language: Option<Language>,
+    pub(crate) layout_tags: LayoutTags,
     pub(crate) shaper: &'static hb_ot_shaper_t,
+    pub(crate) normalization_mode: Option<hb_ot_shape_normalization_mode_t>,
+    pub(crate) hooks: Option<Arc<dyn ShapeHooks>>,
     pub(crate) ot_map: hb_ot_map_t,
     pub(crate) data: Option<Box<dyn Any + Send + Sync>>,
 
@@ -50,18 +58,160 @@ impl hb_ot_shape_plan_t {
         script: Option<Script>,
         language: Option<&Language>,
         user_features: &[Feature],
+    ) -> Self {
+        Self::with_layout_tags(
+            face,
+            direction,
+            script,
+            language,
+            LayoutTags::default(),
+            user_features,
+        )
+    }
+
+    /// Returns a plan that uses explicit OpenType script and language system tags
+    /// instead of the ones derived from `script` and `language`.
+    ///
+    /// Tags that are not set in `layout_tags` are still derived.
+    /// See [`UnicodeBuffer::set_layout_tags`](crate::UnicodeBuffer::set_layout_tags).
+    pub fn with_layout_tags(
+        face: &hb_font_t,
+        direction: Direction,
+        script: Option<Script>,
+        language: Option<&Language>,
+        layout_tags: LayoutTags,
+        user_features: &[Feature],
     ) -> Self {
         assert_ne!(direction, Direction::Invalid);
-        let mut planner = hb_ot_shape_planner_t::new(face, direction, script, language);
+        let mut planner =
+            hb_ot_shape_planner_t::new(face, direction, script, language, layout_tags);
         planner.collect_features(user_features);
         planner.compile(user_features)
     }
 
+    /// Checks that the plan was built for the face's current variation coordinates.
+    ///
+    /// `FeatureVariations` records are resolved when a plan is built, so a plan can only be
+    /// reused with faces whose coordinates select the same records.
+    /// [`shape_with_plan`](crate::shape_with_plan) rebuilds mismatched plans on the fly,
+    /// which is slow.
+    pub fn matches_variations(&self, face: &hb_font_t) -> bool {
+        let variation_index = find_variation_index(face);
+        TableIndex::iter().all(|table_index| {
+            self.ot_map.variation_index(table_index) == variation_index[table_index]
+        })
+    }
+
+    /// Overrides how text is normalized before shaping.
+    ///
+    /// `None` restores the shaper's preference, which depends on the script.
+    /// Text that is already normalized the way the font expects can use
+    /// [`NormalizationMode::None`](crate::NormalizationMode::None) to skip most of the work.
+    pub fn set_normalization_mode(&mut self, mode: Option<hb_ot_shape_normalization_mode_t>) {
+        self.normalization_mode = mode;
+    }
+
+    /// Returns the normalization mode set with
+    /// [`set_normalization_mode`](Self::set_normalization_mode).
+    pub fn normalization_mode(&self) -> Option<hb_ot_shape_normalization_mode_t> {
+        self.normalization_mode
+    }
+
+    /// Attaches callbacks that run at fixed points of shaping.
+    ///
+    /// `None` removes them.
+    pub fn set_hooks(&mut self, hooks: Option<Arc<dyn ShapeHooks>>) {
+        self.hooks = hooks;
+    }
+
+    /// Returns the callbacks set with [`set_hooks`](Self::set_hooks).
+    pub fn hooks(&self) -> Option<&Arc<dyn ShapeHooks>> {
+        self.hooks.as_ref()
+    }
+
+    /// Returns the name of the shaper the plan uses.
+    ///
+    /// Names match HarfBuzz: `default`, `dumber`, `arabic`, `hangul`, `hebrew`,
+    /// `indic`, `khmer`, `myanmar`, `myanmar_zawgyi`, `thai` and `use`.
+    pub fn shaper_name(&self) -> &'static str {
+        self.shaper.name
+    }
+
+    /// Returns the script tag selected in the table.
+    ///
+    /// This can be a fallback like `DFLT` or `latn` when the font doesn't have
+    /// any of the tags requested for the plan's script,
+    /// see [`found_script`](Self::found_script).
+    /// Returns `None` if the font has no such table or the table has no usable script.
+    pub fn script_tag(&self, table_index: TableIndex) -> Option<hb_tag_t> {
+        self.ot_map.chosen_script(table_index)
+    }
+
+    /// Checks that the selected script tag is one requested for the plan's script.
+    pub fn found_script(&self, table_index: TableIndex) -> bool {
+        self.ot_map.found_script(table_index)
+    }
+
+    /// Returns the language system tag selected in the table.
+    ///
+    /// Returns `None` when the script's default language system is used.
+    pub fn language_tag(&self, table_index: TableIndex) -> Option<hb_tag_t> {
+        self.ot_map.chosen_language(table_index)
+    }
+
+    /// Returns the tags of the requested features that were found in the table,
+    /// in tag order.
+    pub fn found_features(&self, table_index: TableIndex) -> impl Iterator<Item = hb_tag_t> + '_ {
+        self.ot_map.found_features(table_index)
+    }
+
+    /// Returns the lookups applied from the table, grouped by stage, in application order.
+    ///
+    /// Stages are separated by pauses in which shapers process the buffer,
+    /// like Indic reordering between GSUB feature groups.
+    pub fn stages(&self, table_index: TableIndex) -> Vec<Vec<hb_ot_shape_plan_lookup_t>> {
+        (0..self.ot_map.stages(table_index).len())
+            .map(|stage| {
+                self.ot_map
+                    .stage_lookup_range(table_index, stage)
+                    .map(|i| hb_ot_shape_plan_lookup_t {
+                        index: self.ot_map.lookup(table_index, i).index,
+                        features: self.ot_map.lookup_features(table_index, i).to_vec(),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the sorted indices of all lookups applied from the table.
+    ///
+    /// Like `hb_ot_shape_plan_collect_lookups` in HarfBuzz.
+    pub fn lookups(&self, table_index: TableIndex) -> Vec<u16> {
+        let mut lookups: Vec<u16> = (0..self.ot_map.stages(table_index).len())
+            .flat_map(|stage| self.ot_map.stage_lookups(table_index, stage))
+            .map(|lookup| lookup.index)
+            .collect();
+        lookups.sort_unstable();
+        lookups.dedup();
+        lookups
+    }
+
     pub(crate) fn data<T: 'static>(&self) -> &T {
         self.data.as_ref().unwrap().downcast_ref().unwrap()
     }
 }
 
+/// A lookup applied by a [`hb_ot_shape_plan_t`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct hb_ot_shape_plan_lookup_t {
+    /// The lookup index in the GSUB or GPOS table.
+    pub index: u16,
+    /// The features the lookup is applied for.
+    ///
+    /// A lookup that is shared by several features is only applied once.
+    pub features: Vec<hb_tag_t>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::hb_ot_shape_plan_t;
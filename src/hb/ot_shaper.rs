@@ -41,6 +41,7 @@ pub type DecomposeFn = fn(&hb_ot_shape_normalize_context_t, char) -> Option<(cha
 pub type ComposeFn = fn(&hb_ot_shape_normalize_context_t, char, char) -> Option<char>;
 
 pub const DEFAULT_SHAPER: hb_ot_shaper_t = hb_ot_shaper_t {
+    name: "default",
     collect_features: None,
     override_features: None,
     create_data: None,
@@ -57,6 +58,9 @@ pub const DEFAULT_SHAPER: hb_ot_shaper_t = hb_ot_shaper_t {
 };
 
 pub struct hb_ot_shaper_t {
+    /// The shaper name, as reported by [`hb_ot_shape_plan_t::shaper_name`].
+    pub name: &'static str,
+
     /// Called during `shape_plan()`.
     /// Shapers should use plan.map to add their features and callbacks.
     pub collect_features: Option<fn(&mut hb_ot_shape_planner_t)>,
@@ -68,7 +72,7 @@ pub struct hb_ot_shaper_t {
 
     /// Called at the end of `shape_plan()`.
     /// Whatever shapers return will be accessible through `plan.data()` later.
-    pub create_data: Option<fn(&hb_ot_shape_plan_t) -> Box<dyn Any + Send + Sync>>,
+    pub create_data: Option<fn(&hb_ot_shape_plan_t, &hb_font_t) -> Box<dyn Any + Send + Sync>>,
 
     /// Called during `shape()`.
     /// Shapers can use to modify text before shaping starts.
@@ -110,6 +114,7 @@ pub struct hb_ot_shaper_t {
 // Same as default but no mark advance zeroing / fallback positioning.
 // Dumbest shaper ever, basically.
 pub const DUMBER_SHAPER: hb_ot_shaper_t = hb_ot_shaper_t {
+    name: "dumber",
     collect_features: None,
     override_features: None,
     create_data: None,
@@ -140,8 +145,6 @@ pub fn hb_ot_shape_complex_categorize(
             // This is because we do fallback shaping for Arabic script (and not others).
             // But note that Arabic shaping is applicable only to horizontal layout; for
             // vertical text, just use the generic shaper instead.
-            //
-            // TODO: Does this still apply? Arabic fallback shaping was removed.
             if (gsub_script != Some(hb_tag_t::default_script()) || script == script::ARABIC)
                 && direction.is_horizontal()
             {
@@ -9,6 +9,7 @@ use super::ot_shape::*;
 use super::ot_shape_normalize::HB_OT_SHAPE_NORMALIZATION_MODE_AUTO;
 use super::ot_shape_plan::hb_ot_shape_plan_t;
 use super::ot_shaper::*;
+use super::ot_shaper_arabic_fallback::arabic_fallback_plan_t;
 use super::unicode::*;
 use super::{hb_font_t, hb_glyph_info_t, hb_mask_t, hb_tag_t, script, Script};
 
@@ -277,19 +278,25 @@ pub struct arabic_shape_plan_t {
     // having to do a "if (... < NONE) ..." and just rely on the fact that
     // mask_array[NONE] == 0.
     mask_array: [hb_mask_t; ARABIC_FEATURES.len() + 1],
+    fallback_plan: Option<arabic_fallback_plan_t>,
     has_stch: bool,
 }
 
-pub fn data_create_arabic(plan: &hb_ot_shape_plan_t) -> arabic_shape_plan_t {
+pub fn data_create_arabic(plan: &hb_ot_shape_plan_t, face: &hb_font_t) -> arabic_shape_plan_t {
     let has_stch = plan.ot_map.get_1_mask(hb_tag_t::from_bytes(b"stch")) != 0;
 
+    let mut do_fallback = plan.script == Some(script::ARABIC);
     let mut mask_array = [0; ARABIC_FEATURES.len() + 1];
     for i in 0..ARABIC_FEATURES.len() {
         mask_array[i] = plan.ot_map.get_1_mask(ARABIC_FEATURES[i]);
+        do_fallback = do_fallback
+            && (feature_is_syriac(ARABIC_FEATURES[i])
+                || plan.ot_map.needs_fallback(ARABIC_FEATURES[i]));
     }
 
     arabic_shape_plan_t {
         mask_array,
+        fallback_plan: do_fallback.then(|| arabic_fallback_plan_t::new(plan, face)),
         has_stch,
     }
 }
@@ -301,7 +308,7 @@ fn arabic_joining(buffer: &mut hb_buffer_t) {
     // Check pre-context.
     for i in 0..buffer.context_len[0] {
         let c = buffer.context[0][i];
-        let this_type = get_joining_type(c, c.general_category());
+        let this_type = get_joining_type(c, buffer.unicode().general_category(c));
         if this_type == hb_arabic_joining_type_t::T {
             continue;
         }
@@ -348,7 +355,7 @@ fn arabic_joining(buffer: &mut hb_buffer_t) {
 
     for i in 0..buffer.context_len[1] {
         let c = buffer.context[1][i];
-        let this_type = get_joining_type(c, c.general_category());
+        let this_type = get_joining_type(c, buffer.unicode().general_category(c));
         if this_type == hb_arabic_joining_type_t::T {
             continue;
         }
@@ -403,8 +410,18 @@ pub fn setup_masks_inner(
     }
 }
 
-fn arabic_fallback_shape(_: &hb_ot_shape_plan_t, _: &hb_font_t, _: &mut hb_buffer_t) -> bool {
-    false
+fn arabic_fallback_shape(
+    plan: &hb_ot_shape_plan_t,
+    face: &hb_font_t,
+    buffer: &mut hb_buffer_t,
+) -> bool {
+    let arabic_plan = plan.data::<arabic_shape_plan_t>();
+    let Some(fallback_plan) = &arabic_plan.fallback_plan else {
+        return false;
+    };
+
+    fallback_plan.shape(face, buffer);
+    true
 }
 
 // Stretch feature: "stch".
@@ -693,9 +710,10 @@ fn reorder_marks_arabic(
 }
 
 pub const ARABIC_SHAPER: hb_ot_shaper_t = hb_ot_shaper_t {
+    name: "arabic",
     collect_features: Some(collect_features),
     override_features: None,
-    create_data: Some(|plan| Box::new(data_create_arabic(plan))),
+    create_data: Some(|plan, face| Box::new(data_create_arabic(plan, face))),
     preprocess_text: None,
     postprocess_glyphs: Some(postprocess_glyphs_arabic),
     normalization_preference: HB_OT_SHAPE_NORMALIZATION_MODE_AUTO,
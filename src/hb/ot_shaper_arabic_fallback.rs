@@ -0,0 +1,217 @@
+use alloc::vec::Vec;
+use ttf_parser::GlyphId;
+
+use super::buffer::hb_buffer_t;
+use super::ot_layout::{apply_string, LayoutLookup, TableIndex};
+use super::ot_layout_common::{lookup_flags, SubstitutionTable};
+use super::ot_layout_gsubgpos::{ligate_input, match_input, Apply, OT::hb_ot_apply_context_t};
+use super::ot_shape_plan::hb_ot_shape_plan_t;
+use super::ot_shaper_arabic_table::{
+    LIGATURE_TABLE, SHAPING_TABLE, SHAPING_TABLE_FIRST, SHAPING_TABLE_LAST,
+};
+use super::set_digest::{hb_set_digest_ext, hb_set_digest_t};
+use super::{hb_font_t, hb_mask_t, hb_tag_t};
+
+// Features emulated by the fallback. The first four are in the same order
+// as the presentation forms in `SHAPING_TABLE`.
+const ARABIC_FALLBACK_FEATURES: &[hb_tag_t] = &[
+    hb_tag_t::from_bytes(b"init"),
+    hb_tag_t::from_bytes(b"medi"),
+    hb_tag_t::from_bytes(b"fina"),
+    hb_tag_t::from_bytes(b"isol"),
+    hb_tag_t::from_bytes(b"rlig"),
+];
+
+enum fallback_subtable_t {
+    // Pairs of a glyph and its substitute, sorted by the glyph.
+    Single(Vec<(GlyphId, GlyphId)>),
+    // First component, second component and ligature, sorted by the components.
+    Ligature(Vec<(GlyphId, GlyphId, GlyphId)>),
+}
+
+/// A GSUB lookup synthesised from the presentation forms mapped by the font's cmap.
+struct fallback_lookup_t {
+    subtable: fallback_subtable_t,
+    set_digest: hb_set_digest_t,
+}
+
+impl fallback_lookup_t {
+    fn single(face: &hb_font_t, feature_index: usize) -> Option<Self> {
+        let mut substitutions = Vec::new();
+        for u in SHAPING_TABLE_FIRST..=SHAPING_TABLE_LAST {
+            let s = SHAPING_TABLE[(u - SHAPING_TABLE_FIRST) as usize][feature_index];
+            if s == 0 {
+                continue;
+            }
+
+            let (Some(u_glyph), Some(s_glyph)) = (
+                face.get_nominal_glyph(u),
+                face.get_nominal_glyph(u32::from(s)),
+            ) else {
+                continue;
+            };
+
+            if u_glyph != s_glyph {
+                substitutions.push((u_glyph, s_glyph));
+            }
+        }
+
+        // Different characters can be mapped to the same glyph.
+        // Keep the first substitute, like a coverage table would.
+        substitutions.sort_by_key(|(glyph, _)| *glyph);
+        substitutions.dedup_by_key(|(glyph, _)| *glyph);
+
+        Self::new(fallback_subtable_t::Single(substitutions))
+    }
+
+    fn ligature(face: &hb_font_t) -> Option<Self> {
+        let mut ligatures = Vec::new();
+        for (first, pairs) in LIGATURE_TABLE {
+            let Some(first_glyph) = face.get_nominal_glyph(u32::from(*first)) else {
+                continue;
+            };
+
+            for (second, ligature) in pairs.iter() {
+                if let (Some(second_glyph), Some(ligature_glyph)) = (
+                    face.get_nominal_glyph(u32::from(*second)),
+                    face.get_nominal_glyph(u32::from(*ligature)),
+                ) {
+                    ligatures.push((first_glyph, second_glyph, ligature_glyph));
+                }
+            }
+        }
+
+        ligatures.sort_by_key(|(first, second, _)| (*first, *second));
+        ligatures.dedup_by_key(|(first, second, _)| (*first, *second));
+
+        Self::new(fallback_subtable_t::Ligature(ligatures))
+    }
+
+    fn new(subtable: fallback_subtable_t) -> Option<Self> {
+        let mut set_digest = hb_set_digest_t::new();
+        match &subtable {
+            fallback_subtable_t::Single(substitutions) if !substitutions.is_empty() => {
+                set_digest.add_array(substitutions.iter().map(|(glyph, _)| *glyph));
+            }
+            fallback_subtable_t::Ligature(ligatures) if !ligatures.is_empty() => {
+                set_digest.add_array(ligatures.iter().map(|(first, _, _)| *first));
+            }
+            _ => return None,
+        }
+
+        Some(Self {
+            subtable,
+            set_digest,
+        })
+    }
+}
+
+impl Apply for fallback_lookup_t {
+    fn apply(&self, ctx: &mut hb_ot_apply_context_t) -> Option<()> {
+        let glyph = ctx.buffer.cur(0).as_glyph();
+        match &self.subtable {
+            fallback_subtable_t::Single(substitutions) => {
+                let index = substitutions
+                    .binary_search_by_key(&glyph, |(glyph, _)| *glyph)
+                    .ok()?;
+                ctx.replace_glyph(substitutions[index].1);
+                Some(())
+            }
+            fallback_subtable_t::Ligature(ligatures) => {
+                let start = ligatures.partition_point(|(first, _, _)| *first < glyph);
+                for (_, second, ligature) in ligatures[start..]
+                    .iter()
+                    .take_while(|(first, _, _)| *first == glyph)
+                {
+                    let f = |glyph, _| glyph == *second;
+
+                    let mut match_end = 0;
+                    let mut match_positions = smallvec::SmallVec::from_elem(0, 4);
+                    let mut total_component_count = 0;
+
+                    if !match_input(
+                        ctx,
+                        1,
+                        &f,
+                        &mut match_end,
+                        &mut match_positions,
+                        Some(&mut total_component_count),
+                    ) {
+                        ctx.buffer
+                            .unsafe_to_concat(Some(ctx.buffer.idx), Some(match_end));
+                        continue;
+                    }
+
+                    ligate_input(
+                        ctx,
+                        2,
+                        &match_positions,
+                        match_end,
+                        total_component_count,
+                        *ligature,
+                    );
+                    return Some(());
+                }
+
+                None
+            }
+        }
+    }
+}
+
+impl LayoutLookup for fallback_lookup_t {
+    fn props(&self) -> u32 {
+        u32::from(lookup_flags::IGNORE_MARKS)
+    }
+
+    fn is_reverse(&self) -> bool {
+        false
+    }
+
+    fn digest(&self) -> &hb_set_digest_t {
+        &self.set_digest
+    }
+}
+
+/// Lookups emulating the Arabic positional forms and lam-alef ligatures
+/// for fonts that only provide the Unicode presentation forms.
+pub struct arabic_fallback_plan_t {
+    lookups: Vec<(hb_mask_t, fallback_lookup_t)>,
+}
+
+impl arabic_fallback_plan_t {
+    pub fn new(plan: &hb_ot_shape_plan_t, face: &hb_font_t) -> Self {
+        let mut lookups = Vec::new();
+        for (i, feature) in ARABIC_FALLBACK_FEATURES.iter().enumerate() {
+            let mask = plan.ot_map.get_1_mask(*feature);
+            if mask == 0 {
+                continue;
+            }
+
+            let lookup = if i < 4 {
+                fallback_lookup_t::single(face, i)
+            } else {
+                fallback_lookup_t::ligature(face)
+            };
+
+            if let Some(lookup) = lookup {
+                lookups.push((mask, lookup));
+            }
+        }
+
+        Self { lookups }
+    }
+
+    pub fn shape(&self, face: &hb_font_t, buffer: &mut hb_buffer_t) {
+        let mut ctx = hb_ot_apply_context_t::new(TableIndex::GSUB, face, buffer);
+        // Arabic features are applied with manual ZWJ handling.
+        ctx.auto_zwj = false;
+
+        for (mask, lookup) in &self.lookups {
+            if lookup.digest().may_have(&ctx.digest) {
+                ctx.set_lookup_mask(*mask);
+                apply_string::<SubstitutionTable>(&mut ctx, lookup);
+            }
+        }
+    }
+}
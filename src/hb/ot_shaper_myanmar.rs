@@ -9,6 +9,7 @@ use super::{hb_font_t, hb_glyph_info_t, hb_tag_t};
 use crate::hb::ot_shaper_indic::ot_category_t::OT_VPre;
 
 pub const MYANMAR_SHAPER: hb_ot_shaper_t = hb_ot_shaper_t {
+    name: "myanmar",
     collect_features: Some(collect_features),
     override_features: None,
     create_data: None,
@@ -28,6 +29,7 @@ pub const MYANMAR_SHAPER: hb_ot_shaper_t = hb_ot_shaper_t {
 // Disable all auto processing.
 // https://github.com/harfbuzz/harfbuzz/issues/1162
 pub const MYANMAR_ZAWGYI_SHAPER: hb_ot_shaper_t = hb_ot_shaper_t {
+    name: "myanmar_zawgyi",
     collect_features: None,
     override_features: None,
     create_data: None,
@@ -9,13 +9,14 @@ use super::ot_shape_normalize::*;
 use super::ot_shape_plan::hb_ot_shape_plan_t;
 use super::ot_shaper::*;
 use super::ot_shaper_arabic::arabic_shape_plan_t;
-use super::unicode::{CharExt, GeneralCategoryExt};
+use super::unicode::GeneralCategoryExt;
 use super::{hb_font_t, hb_glyph_info_t, hb_mask_t, hb_tag_t, script, Script};
 
 pub const UNIVERSAL_SHAPER: hb_ot_shaper_t = hb_ot_shaper_t {
+    name: "use",
     collect_features: Some(collect_features),
     override_features: None,
-    create_data: Some(|plan| Box::new(UniversalShapePlan::new(plan))),
+    create_data: Some(|plan, face| Box::new(UniversalShapePlan::new(plan, face))),
     preprocess_text: Some(preprocess_text),
     postprocess_glyphs: None,
     normalization_preference: HB_OT_SHAPE_NORMALIZATION_MODE_COMPOSED_DIACRITICS_NO_SHORT_CIRCUIT,
@@ -163,11 +164,11 @@ struct UniversalShapePlan {
 }
 
 impl UniversalShapePlan {
-    fn new(plan: &hb_ot_shape_plan_t) -> UniversalShapePlan {
+    fn new(plan: &hb_ot_shape_plan_t, face: &hb_font_t) -> UniversalShapePlan {
         let mut arabic_plan = None;
 
         if plan.script.map_or(false, has_arabic_joining) {
-            arabic_plan = Some(crate::hb::ot_shaper_arabic::data_create_arabic(plan));
+            arabic_plan = Some(crate::hb::ot_shaper_arabic::data_create_arabic(plan, face));
         }
 
         UniversalShapePlan {
@@ -545,13 +546,13 @@ fn preprocess_text(_: &hb_ot_shape_plan_t, _: &hb_font_t, buffer: &mut hb_buffer
     super::ot_shaper_vowel_constraints::preprocess_text_vowel_constraints(buffer);
 }
 
-fn compose(_: &hb_ot_shape_normalize_context_t, a: char, b: char) -> Option<char> {
+fn compose(ctx: &hb_ot_shape_normalize_context_t, a: char, b: char) -> Option<char> {
     // Avoid recomposing split matras.
-    if a.general_category().is_mark() {
+    if ctx.buffer.unicode().general_category(a).is_mark() {
         return None;
     }
 
-    crate::hb::unicode::compose(a, b)
+    ctx.buffer.unicode().compose(a, b)
 }
 
 fn setup_masks(plan: &hb_ot_shape_plan_t, _: &hb_font_t, buffer: &mut hb_buffer_t) {
@@ -0,0 +1,204 @@
+use alloc::vec::Vec;
+
+use ttf_parser::{Fixed, FromData, NormalizedCoordinate, Tag};
+
+use super::algs::read_at;
+use super::hb_font_t;
+use crate::Variation;
+
+/// A named instance from the `fvar` table.
+#[derive(Clone, PartialEq, Debug)]
+pub struct hb_ot_var_named_instance_t {
+    /// The instance index in the `fvar` table.
+    pub index: usize,
+    /// The `name` table ID of the instance subfamily name, like "Bold Condensed".
+    pub subfamily_name_id: u16,
+    /// The `name` table ID of the instance PostScript name, if any.
+    pub postscript_name_id: Option<u16>,
+    /// User-space coordinates of the instance, one per axis in `fvar` order.
+    pub coordinates: Vec<Variation>,
+    /// `STAT` axis value name IDs matching the instance coordinates,
+    /// in `STAT` axis ordering. Elidable values are skipped.
+    pub axis_value_name_ids: Vec<u16>,
+}
+
+impl<'a> hb_font_t<'a> {
+    /// Returns the named instances from the `fvar` table.
+    ///
+    /// Name IDs can be resolved via [`ttf_parser::Face::names`].
+    pub fn named_instances(&self) -> Vec<hb_ot_var_named_instance_t> {
+        let count = self.fvar_instances().map(|(count, ..)| count).unwrap_or(0);
+        (0..count)
+            .filter_map(|index| self.named_instance(index))
+            .collect()
+    }
+
+    /// Returns a named instance by index.
+    pub fn named_instance(&self, index: usize) -> Option<hb_ot_var_named_instance_t> {
+        let (count, size, data) = self.fvar_instances()?;
+        if index >= count {
+            return None;
+        }
+
+        let axes = self.variation_axes();
+        let axis_count = usize::from(axes.len());
+        let record = data.get(index * size..(index + 1) * size)?;
+
+        let subfamily_name_id = read_at::<u16>(record, 0)?;
+        // Skip subfamily name ID and flags.
+        let coords_data = record.get(4..4 + axis_count * Fixed::SIZE)?;
+        let postscript_name_id = if size >= axis_count * Fixed::SIZE + 6 {
+            read_at::<u16>(record, 4 + axis_count * Fixed::SIZE)
+        } else {
+            None
+        };
+
+        let mut coordinates = Vec::with_capacity(axis_count);
+        for (axis, value) in axes.into_iter().zip(coords_data.chunks(Fixed::SIZE)) {
+            coordinates.push(Variation {
+                tag: axis.tag,
+                value: Fixed::parse(value)?.0,
+            });
+        }
+
+        let axis_value_name_ids = self.stat_name_ids(&coordinates);
+
+        Some(hb_ot_var_named_instance_t {
+            index,
+            subfamily_name_id,
+            postscript_name_id,
+            coordinates,
+            axis_value_name_ids,
+        })
+    }
+
+    /// Sets font variations to the ones of a named instance.
+    ///
+    /// The `opsz` axis is still controlled by automatic optical sizing, when enabled.
+    ///
+    /// Returns `None` when there is no such instance.
+    pub fn set_named_instance(&mut self, index: usize) -> Option<()> {
+        let instance = self.named_instance(index)?;
+        for variation in instance.coordinates {
+            self.set_variation(variation.tag, variation.value);
+        }
+
+        self.reset_optical_size();
+        Some(())
+    }
+
+    /// Sets normalized variation coordinates, one per axis in `fvar` order.
+    ///
+    /// Coordinates are expected after the `avar` mapping, as returned by
+    /// [`ttf_parser::Face::variation_coordinates`].
+    /// Axes without a coordinate are reset to their default.
+    /// The `opsz` axis is still controlled by automatic optical sizing, when enabled.
+    ///
+    /// Since coordinates are stored as user-space values internally,
+    /// a coordinate that cannot be produced by the `avar` mapping is set to the closest one that can.
+    pub fn set_variation_coordinates(&mut self, coords: &[NormalizedCoordinate]) {
+        let axes = self.variation_axes();
+        for (i, axis) in axes.into_iter().enumerate() {
+            let target = coords.get(i).map(|c| c.get()).unwrap_or(0);
+            let value = self.unmap_coordinate(i, usize::from(axes.len()), target);
+            self.set_variation(axis.tag, value);
+        }
+
+        self.reset_optical_size();
+    }
+
+    /// Returns the current user-space value of an axis.
+    pub(crate) fn variation_value(&self, tag: Tag) -> Option<f32> {
+        let axes = self.variation_axes();
+        let index = axes.into_iter().position(|axis| axis.tag == tag)?;
+        let target = self
+            .variation_coordinates()
+            .get(index)
+            .map(|c| c.get())
+            .unwrap_or(0);
+        Some(self.unmap_coordinate(index, usize::from(axes.len()), target))
+    }
+
+    fn fvar_instances(&self) -> Option<(usize, usize, &'a [u8])> {
+        let data = self.raw_face().table(Tag::from_bytes(b"fvar"))?;
+        let axes_offset = usize::from(read_at::<u16>(data, 4)?);
+        let axis_count = usize::from(read_at::<u16>(data, 8)?);
+        let axis_size = usize::from(read_at::<u16>(data, 10)?);
+        let instance_count = usize::from(read_at::<u16>(data, 12)?);
+        let instance_size = usize::from(read_at::<u16>(data, 14)?);
+
+        if instance_size < axis_count * Fixed::SIZE + 4 {
+            return None;
+        }
+
+        let data = data.get(axes_offset + axis_count * axis_size..)?;
+        Some((instance_count, instance_size, data))
+    }
+
+    fn stat_name_ids(&self, coordinates: &[Variation]) -> Vec<u16> {
+        let Some(stat) = self.tables().stat else {
+            return Vec::new();
+        };
+
+        let mut axes: Vec<_> = stat.axes.into_iter().collect();
+        axes.sort_by_key(|axis| axis.ordering);
+
+        axes.iter()
+            .filter_map(|axis| {
+                let value = coordinates.iter().find(|v| v.tag == axis.tag)?.value;
+                stat.subtable_for_axis(axis.tag, Some(Fixed(value)))
+            })
+            .filter(|subtable| !subtable.is_elidable())
+            .map(|subtable| subtable.name_id())
+            .collect()
+    }
+
+    /// Maps a normalized coordinate back to a user-space value of an axis,
+    /// undoing the `avar` mapping.
+    fn unmap_coordinate(&self, axis_index: usize, axis_count: usize, target: i16) -> f32 {
+        let map = |n: i16| -> i16 {
+            let Some(avar) = self.tables().avar else {
+                return n;
+            };
+
+            let mut coords = alloc::vec![NormalizedCoordinate::default(); axis_count];
+            coords[axis_index] = NormalizedCoordinate::from(n);
+            match avar.map_coordinate(&mut coords, axis_index) {
+                Some(()) => coords[axis_index].get(),
+                None => n,
+            }
+        };
+
+        // `avar` maps are monotonic, so look for the first coordinate that reaches the target.
+        let (mut lo, mut hi) = (-16384i32, 16384i32);
+        while lo < hi {
+            let mid = (lo + hi).div_euclid(2);
+            if map(mid as i16) < target {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+
+        let mut n = lo as i16;
+        let distance = |n: i16| (i32::from(map(n)) - i32::from(target)).abs();
+        if n > -16384 && distance(n - 1) < distance(n) {
+            n -= 1;
+        }
+
+        let Some(axis) = self.variation_axes().get(axis_index as u16) else {
+            return 0.0;
+        };
+
+        // Normalization truncates, so aim at the middle of the coordinate step.
+        if n > 0 {
+            let v = (f32::from(n) + 0.5).min(16384.0) / 16384.0;
+            axis.def_value + v * (axis.max_value - axis.def_value)
+        } else if n < 0 {
+            let v = (f32::from(n) - 0.5).max(-16384.0) / 16384.0;
+            axis.def_value + v * (axis.def_value - axis.min_value)
+        } else {
+            axis.def_value
+        }
+    }
+}
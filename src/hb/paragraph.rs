@@ -0,0 +1,103 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use unicode_bidi::{Level, ParagraphBidiInfo};
+
+use super::hb_font_t;
+use super::itemize::{hb_script_run_t, script_runs};
+use super::shape::shape;
+use crate::{Direction, Feature, GlyphBuffer, Script};
+
+/// A shaped run of a paragraph.
+pub struct hb_paragraph_run_t {
+    /// The byte range of the run in the paragraph text.
+    pub range: Range<usize>,
+    /// The resolved bidi embedding level. Odd levels are right-to-left.
+    pub level: u8,
+    /// The script of the run.
+    pub script: Script,
+    /// Shaped glyphs, in visual order.
+    pub glyphs: GlyphBuffer,
+}
+
+impl hb_paragraph_run_t {
+    /// Returns the direction the run was shaped with.
+    pub fn direction(&self) -> Direction {
+        if self.level % 2 == 1 {
+            Direction::RightToLeft
+        } else {
+            Direction::LeftToRight
+        }
+    }
+}
+
+/// Shapes a paragraph of bidirectional, mixed-script text.
+///
+/// Resolves embedding levels using the Unicode Bidirectional Algorithm (UAX #9),
+/// splits the text into runs of a single level and script, shapes each run
+/// with the surrounding text as context and returns the runs in visual order.
+///
+/// `base_direction` sets the paragraph direction, which is detected from
+/// the first strong character when it is [`Direction::Invalid`].
+/// Vertical directions are not supported and are detected as well.
+///
+/// The whole paragraph is treated as a single line. Glyph clusters are byte offsets into `text`.
+pub fn shape_paragraph(
+    face: &hb_font_t,
+    text: &str,
+    base_direction: Direction,
+    features: &[Feature],
+) -> Vec<hb_paragraph_run_t> {
+    if text.is_empty() {
+        return Vec::new();
+    }
+
+    let base_level = match base_direction {
+        Direction::LeftToRight => Some(Level::ltr()),
+        Direction::RightToLeft => Some(Level::rtl()),
+        _ => None,
+    };
+
+    let bidi = ParagraphBidiInfo::new(text, base_level);
+    let (levels, level_runs) = bidi.visual_runs(0..text.len());
+    let scripts: Vec<_> = script_runs(text).collect();
+
+    let mut runs = Vec::new();
+    for level_run in level_runs {
+        let level = levels[level_run.start];
+        let direction = if level.is_rtl() {
+            Direction::RightToLeft
+        } else {
+            Direction::LeftToRight
+        };
+
+        // Script runs within a level run, in logical order.
+        let first_run = runs.len();
+        for script_run in &scripts {
+            let start = script_run.range.start.max(level_run.start);
+            let end = script_run.range.end.min(level_run.end);
+            if start >= end {
+                continue;
+            }
+
+            let run = hb_script_run_t {
+                range: start..end,
+                script: script_run.script,
+                direction,
+            };
+            let glyphs = shape(face, features, run.to_buffer(text));
+            runs.push(hb_paragraph_run_t {
+                range: run.range,
+                level: level.number(),
+                script: run.script,
+                glyphs,
+            });
+        }
+
+        if level.is_rtl() {
+            runs[first_run..].reverse();
+        }
+    }
+
+    runs
+}
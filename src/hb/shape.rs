@@ -1,6 +1,12 @@
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+
+use ttf_parser::Tag;
+
 use super::hb_font_t;
 use super::ot_shape::{hb_ot_shape_context_t, shape_internal};
 use super::ot_shape_plan::hb_ot_shape_plan_t;
+use super::shape_plan_cache::hb_shape_plan_cache_t;
 use crate::{script, Feature, GlyphBuffer, UnicodeBuffer};
 
 /// Shapes the buffer content using provided font and features.
@@ -13,12 +19,38 @@ use crate::{script, Feature, GlyphBuffer, UnicodeBuffer};
 /// once for each [`Face`].
 pub fn shape(face: &hb_font_t, features: &[Feature], mut buffer: UnicodeBuffer) -> GlyphBuffer {
     buffer.0.guess_segment_properties();
-    let plan = hb_ot_shape_plan_t::new(
+    let plan = hb_ot_shape_plan_t::with_layout_tags(
         face,
         buffer.0.direction,
         buffer.0.script,
         buffer.0.language.as_ref(),
-        features,
+        buffer.0.layout_tags,
+        &spacing_features(&buffer, features),
+    );
+    shape_with_plan(face, &plan, buffer)
+}
+
+/// Shapes the buffer content using provided font and features, reusing plans from the cache.
+///
+/// Consumes the buffer. You can then run [`GlyphBuffer::clear`] to get the [`UnicodeBuffer`] back
+/// without allocating a new one.
+///
+/// Unlike [`shape`], a plan is created only once for each combination of face, buffer properties,
+/// features and feature variations. See [`ShapePlanCache`](crate::ShapePlanCache).
+pub fn shape_cached(
+    face: &hb_font_t,
+    cache: &hb_shape_plan_cache_t,
+    features: &[Feature],
+    mut buffer: UnicodeBuffer,
+) -> GlyphBuffer {
+    buffer.0.guess_segment_properties();
+    let plan = cache.get_with_layout_tags(
+        face,
+        buffer.0.direction,
+        buffer.0.script,
+        buffer.0.language.as_ref(),
+        buffer.0.layout_tags,
+        &spacing_features(&buffer, features),
     );
     shape_with_plan(face, &plan, buffer)
 }
@@ -31,6 +63,10 @@ pub fn shape(face: &hb_font_t, features: &[Feature], mut buffer: UnicodeBuffer)
 /// It is up to the caller to ensure that the shape plan matches the properties of the provided
 /// buffer, otherwise the shaping result will likely be incorrect.
 ///
+/// If the face variation coordinates select different `FeatureVariations` records than the ones
+/// the plan was built for, a matching plan is built on the fly.
+/// See [`ShapePlan::matches_variations`](crate::ShapePlan::matches_variations).
+///
 /// # Panics
 ///
 /// Will panic when debugging assertions are enabled if the buffer and plan have mismatched
@@ -43,6 +79,26 @@ pub fn shape_with_plan(
     let mut buffer = buffer.0;
     buffer.guess_segment_properties();
 
+    // The plan has to be rebuilt when the face coordinates select different feature variations.
+    let remapped_plan;
+    let plan = if plan.matches_variations(face) {
+        plan
+    } else {
+        log::debug!("Shape plan variations do not match the face. Rebuilding the plan.");
+        let mut new_plan = hb_ot_shape_plan_t::with_layout_tags(
+            face,
+            plan.direction,
+            plan.script,
+            plan.language.as_ref(),
+            plan.layout_tags,
+            &plan.user_features,
+        );
+        new_plan.set_normalization_mode(plan.normalization_mode);
+        new_plan.set_hooks(plan.hooks.clone());
+        remapped_plan = new_plan;
+        &remapped_plan
+    };
+
     buffer.enter();
 
     debug_assert_eq!(buffer.direction, plan.direction);
@@ -56,18 +112,15 @@ pub fn shape_with_plan(
         let target_direction = buffer.direction;
 
         #[cfg(feature = "wasm-shaper")]
-        {
-            super::shape_wasm::shape_with_wasm(face, plan, &mut buffer).unwrap_or_else(|| {
-                shape_internal(&mut hb_ot_shape_context_t {
-                    plan,
-                    face,
-                    buffer: &mut buffer,
-                    target_direction,
-                });
-            });
-        }
+        let shaped = super::shape_wasm::shape_with_wasm(face, plan, &mut buffer).is_some();
         #[cfg(not(feature = "wasm-shaper"))]
-        {
+        let shaped = false;
+
+        #[cfg(feature = "graphite")]
+        let shaped =
+            shaped || super::graphite::shape_with_graphite(face, plan, &mut buffer).is_some();
+
+        if !shaped {
             shape_internal(&mut hb_ot_shape_context_t {
                 plan,
                 face,
@@ -79,3 +132,18 @@ pub fn shape_with_plan(
 
     GlyphBuffer(buffer)
 }
+
+// Optional ligatures are disabled for letter-spaced text, like in CSS.
+// User features come last, so they can enable them back.
+fn spacing_features<'a>(buffer: &UnicodeBuffer, features: &'a [Feature]) -> Cow<'a, [Feature]> {
+    if buffer.0.letter_spacing == 0 {
+        return Cow::Borrowed(features);
+    }
+
+    let mut spacing_features: Vec<Feature> = [b"liga", b"clig", b"dlig", b"calt"]
+        .iter()
+        .map(|tag| Feature::new(Tag::from_bytes(tag), 0, ..))
+        .collect();
+    spacing_features.extend_from_slice(features);
+    Cow::Owned(spacing_features)
+}
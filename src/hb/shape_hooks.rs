@@ -0,0 +1,138 @@
+use super::buffer::{hb_buffer_t, hb_glyph_info_t, GlyphPosition};
+use super::hb_font_t;
+use super::{Direction, Script};
+
+/// Callbacks that run at fixed points of shaping.
+///
+/// Attach them to a plan with
+/// [`ShapePlan::set_hooks`](crate::ShapePlan::set_hooks). All methods do nothing by default.
+///
+/// Hooks run for every buffer the OpenType shaper shapes with the plan, including plans that
+/// [`shape_with_plan`](crate::shape_with_plan) rebuilds for different variation coordinates.
+/// Fonts shaped by their `Wasm` or Graphite tables skip them, unless a Wasm shaper
+/// runs the OpenType shaper itself.
+pub trait ShapeHooks: Send + Sync {
+    /// Called before normalization, after the shaper has preprocessed the text.
+    ///
+    /// [`GlyphInfo::glyph_id`](crate::GlyphInfo::glyph_id) still holds a Unicode code point
+    /// at this point and the buffer is in logical order. Values that aren't Unicode scalar
+    /// values are replaced with U+FFFD afterwards.
+    fn pre_normalize(&self, face: &hb_font_t, buffer: &mut hb_hook_buffer_t) {
+        let _ = (face, buffer);
+    }
+
+    /// Called after `GSUB` or `morx` substitutions, before positioning.
+    ///
+    /// The buffer holds glyph ids in logical order. Glyph ids above `u16::MAX`
+    /// are replaced with 0 afterwards.
+    fn post_substitute(&self, face: &hb_font_t, buffer: &mut hb_hook_buffer_t) {
+        let _ = (face, buffer);
+    }
+
+    /// Called after `GPOS`, `kerx` or `kern` positioning.
+    ///
+    /// The buffer is still in logical order. Letter and word spacing are applied afterwards.
+    /// Glyph ids above `u16::MAX` are replaced with 0.
+    fn post_position(&self, face: &hb_font_t, buffer: &mut hb_hook_buffer_t) {
+        let _ = (face, buffer);
+    }
+}
+
+/// A view of the buffer being shaped, passed to [`ShapeHooks`].
+///
+/// Glyphs can be modified and reordered in place, but not inserted or removed.
+/// Swapping two [`GlyphInfo`](crate::GlyphInfo) values keeps the properties the shaper
+/// has computed for them; changing a `glyph_id` does not update those properties.
+pub struct hb_hook_buffer_t<'a> {
+    buffer: &'a mut hb_buffer_t,
+    positioned: bool,
+}
+
+impl<'a> hb_hook_buffer_t<'a> {
+    pub(crate) fn new(buffer: &'a mut hb_buffer_t, positioned: bool) -> Self {
+        hb_hook_buffer_t { buffer, positioned }
+    }
+
+    /// Returns the number of glyphs in the buffer.
+    pub fn len(&self) -> usize {
+        self.buffer.len
+    }
+
+    /// Checks that the buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.len == 0
+    }
+
+    /// Returns the direction the buffer is shaped in.
+    pub fn direction(&self) -> Direction {
+        self.buffer.direction
+    }
+
+    /// Returns the script of the buffer.
+    pub fn script(&self) -> Option<Script> {
+        self.buffer.script
+    }
+
+    /// Returns the glyph infos.
+    pub fn glyph_infos(&self) -> &[hb_glyph_info_t] {
+        &self.buffer.info[..self.buffer.len]
+    }
+
+    /// Returns the glyph infos for modification.
+    pub fn glyph_infos_mut(&mut self) -> &mut [hb_glyph_info_t] {
+        &mut self.buffer.info[..self.buffer.len]
+    }
+
+    /// Returns the glyph positions.
+    ///
+    /// Empty before positioning.
+    pub fn glyph_positions(&self) -> &[GlyphPosition] {
+        if self.positioned {
+            &self.buffer.pos[..self.buffer.len]
+        } else {
+            &[]
+        }
+    }
+
+    /// Returns the glyph positions for modification.
+    ///
+    /// Empty before positioning.
+    pub fn glyph_positions_mut(&mut self) -> &mut [GlyphPosition] {
+        if self.positioned {
+            &mut self.buffer.pos[..self.buffer.len]
+        } else {
+            &mut []
+        }
+    }
+
+    // Hooks can write any value to a `GlyphInfo`, so the shaper
+    // replaces the ones later stages can't handle after each hook.
+
+    pub(crate) fn replace_invalid_codepoints(&mut self) {
+        for info in self.glyph_infos_mut() {
+            if char::from_u32(info.glyph_id).is_none() {
+                info.glyph_id = 0xFFFD;
+            }
+        }
+    }
+
+    pub(crate) fn replace_invalid_glyphs(&mut self) {
+        for info in self.glyph_infos_mut() {
+            if info.glyph_id > u32::from(u16::MAX) {
+                info.glyph_id = 0;
+            }
+        }
+    }
+
+    /// Swaps two glyphs along with their positions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either index is out of bounds.
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.glyph_infos_mut().swap(a, b);
+        if self.positioned {
+            self.buffer.pos.swap(a, b);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+#[cfg(not(feature = "std"))]
+use core::cell::RefCell;
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+
+use ttf_parser::opentype_layout::VariationIndex;
+
+use super::ot_map::find_variation_index;
+use super::ot_shape_plan::hb_ot_shape_plan_t;
+use super::{hb_font_t, Direction, Feature, Language, LayoutTags, Script};
+
+#[derive(Clone, PartialEq, Eq)]
+struct hb_shape_plan_key_t {
+    face_data: (usize, usize),
+    // Faces of a collection share data, but not table directories.
+    face_tables: u64,
+    direction: Direction,
+    script: Option<Script>,
+    language: Option<Language>,
+    layout_tags: LayoutTags,
+    user_features: Vec<Feature>,
+    variation_index: [Option<VariationIndex>; 2],
+}
+
+impl hb_shape_plan_key_t {
+    fn new(
+        face: &hb_font_t,
+        direction: Direction,
+        script: Option<Script>,
+        language: Option<&Language>,
+        layout_tags: LayoutTags,
+        user_features: &[Feature],
+    ) -> Self {
+        let raw_face = face.raw_face();
+        hb_shape_plan_key_t {
+            face_data: (raw_face.data.as_ptr() as usize, raw_face.data.len()),
+            face_tables: raw_face
+                .table_records
+                .into_iter()
+                .fold(0xcbf29ce484222325, |hash, record| {
+                    (hash ^ u64::from(record.offset)).wrapping_mul(0x100000001b3)
+                }),
+            direction,
+            script,
+            language: language.cloned(),
+            layout_tags,
+            user_features: user_features.to_vec(),
+            variation_index: find_variation_index(face),
+        }
+    }
+}
+
+/// A bounded cache of shape plans.
+///
+/// Plans are keyed by the face, segment properties, user features and
+/// the `FeatureVariations` records selected by the face variation coordinates.
+/// Coordinates that select the same records share a plan.
+///
+/// Faces are identified by their data address, so a cache must not be reused
+/// after the data of a face it has seen is freed.
+///
+/// When the least recently used plan has to be evicted, plans that are still in use
+/// by other threads stay alive until they are dropped.
+///
+/// The cache is thread-safe when the `std` feature is enabled.
+pub struct hb_shape_plan_cache_t {
+    capacity: usize,
+    #[cfg(feature = "std")]
+    entries: Mutex<Vec<(hb_shape_plan_key_t, Arc<hb_ot_shape_plan_t>)>>,
+    #[cfg(not(feature = "std"))]
+    entries: RefCell<Vec<(hb_shape_plan_key_t, Arc<hb_ot_shape_plan_t>)>>,
+}
+
+impl Default for hb_shape_plan_cache_t {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+impl hb_shape_plan_cache_t {
+    /// The number of plans kept by a default cache.
+    pub const DEFAULT_CAPACITY: usize = 32;
+
+    /// Creates a new cache holding at most `capacity` plans.
+    pub fn new(capacity: usize) -> Self {
+        hb_shape_plan_cache_t {
+            capacity: capacity.max(1),
+            entries: Default::default(),
+        }
+    }
+
+    /// Returns a cached plan with the provided properties or creates a new one.
+    pub fn get(
+        &self,
+        face: &hb_font_t,
+        direction: Direction,
+        script: Option<Script>,
+        language: Option<&Language>,
+        user_features: &[Feature],
+    ) -> Arc<hb_ot_shape_plan_t> {
+        self.get_with_layout_tags(
+            face,
+            direction,
+            script,
+            language,
+            LayoutTags::default(),
+            user_features,
+        )
+    }
+
+    /// Returns a cached plan with the provided properties and explicit OpenType tags
+    /// or creates a new one.
+    ///
+    /// See [`ShapePlan::with_layout_tags`](crate::ShapePlan::with_layout_tags).
+    pub fn get_with_layout_tags(
+        &self,
+        face: &hb_font_t,
+        direction: Direction,
+        script: Option<Script>,
+        language: Option<&Language>,
+        layout_tags: LayoutTags,
+        user_features: &[Feature],
+    ) -> Arc<hb_ot_shape_plan_t> {
+        let key = hb_shape_plan_key_t::new(
+            face,
+            direction,
+            script,
+            language,
+            layout_tags,
+            user_features,
+        );
+
+        if let Some(plan) = self.with_entries(|entries| {
+            let idx = entries.iter().position(|(k, _)| *k == key)?;
+            // Move to the front, so the least recently used plan is at the back.
+            let entry = entries.remove(idx);
+            let plan = entry.1.clone();
+            entries.insert(0, entry);
+            Some(plan)
+        }) {
+            return plan;
+        }
+
+        // Plan creation is slow, so do not block other threads meanwhile.
+        let plan = Arc::new(hb_ot_shape_plan_t::with_layout_tags(
+            face,
+            direction,
+            script,
+            language,
+            layout_tags,
+            user_features,
+        ));
+
+        self.with_entries(|entries| {
+            // Another thread could have created the same plan meanwhile.
+            if let Some(idx) = entries.iter().position(|(k, _)| *k == key) {
+                entries.remove(idx);
+            }
+
+            entries.insert(0, (key, plan.clone()));
+            entries.truncate(self.capacity);
+        });
+
+        plan
+    }
+
+    /// Returns the number of cached plans.
+    pub fn len(&self) -> usize {
+        self.with_entries(|entries| entries.len())
+    }
+
+    /// Checks that the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all cached plans.
+    pub fn clear(&self) {
+        self.with_entries(|entries| entries.clear());
+    }
+
+    #[cfg(feature = "std")]
+    fn with_entries<T>(
+        &self,
+        f: impl FnOnce(&mut Vec<(hb_shape_plan_key_t, Arc<hb_ot_shape_plan_t>)>) -> T,
+    ) -> T {
+        // A panic while holding the lock cannot leave the list in an invalid state.
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut entries)
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn with_entries<T>(
+        &self,
+        f: impl FnOnce(&mut Vec<(hb_shape_plan_key_t, Arc<hb_ot_shape_plan_t>)>) -> T,
+    ) -> T {
+        f(&mut self.entries.borrow_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hb_shape_plan_cache_t;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_shape_plan_cache_is_send_and_sync() {
+        fn ensure_send_and_sync<T: Send + Sync>() {}
+        ensure_send_and_sync::<hb_shape_plan_cache_t>();
+    }
+}
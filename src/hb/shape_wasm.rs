@@ -1,7 +1,10 @@
-use alloc::{borrow::ToOwned, ffi::CString, format};
+use alloc::{borrow::ToOwned, format, vec::Vec};
 use core::ffi::CStr;
 use ttf_parser::{GlyphId, Tag};
-use wasmi::{self, AsContextMut, Caller, Config, Engine, Linker, Module, Store};
+use wasmi::{
+    self, core::TrapCode, AsContextMut, Caller, Config, Engine, Extern, Linker, Memory, Module,
+    Store, StoreLimits, StoreLimitsBuilder,
+};
 
 use super::{
     buffer::{hb_buffer_t, GlyphPosition},
@@ -11,10 +14,34 @@ use super::{
     ot_shape_plan::hb_ot_shape_plan_t,
 };
 
+/// Resource limits for the Wasm shaper.
+///
+/// A font's `Wasm` table runs arbitrary code, so shaping with it is bounded.
+/// When a limit is hit, the buffer is shaped with the OpenType shaper instead.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct hb_wasm_limits_t {
+    /// The amount of fuel a single shaping call can consume.
+    ///
+    /// Most Wasm instructions consume one unit of fuel. `None` means unlimited.
+    pub fuel: Option<u64>,
+    /// The maximum size of the guest memory, in bytes. `None` means unlimited.
+    pub max_memory: Option<usize>,
+}
+
+impl Default for hb_wasm_limits_t {
+    fn default() -> Self {
+        hb_wasm_limits_t {
+            fuel: Some(1 << 30),
+            max_memory: Some(64 << 20),
+        }
+    }
+}
+
 struct ShapingData<'a> {
     font: &'a hb_font_t<'a>,
     plan: &'a hb_ot_shape_plan_t,
     buffer: &'a mut hb_buffer_t,
+    limits: StoreLimits,
 }
 
 pub(crate) fn shape_with_wasm(
@@ -23,17 +50,46 @@ pub(crate) fn shape_with_wasm(
     buffer: &mut hb_buffer_t,
 ) -> Option<()> {
     // If font has no Wasm blob just return None to carry on as usual.
-    let wasm_blob = font
-        .raw_face()
-        .table(ttf_parser::Tag::from_bytes(b"Wasm"))?;
+    let module = font
+        .wasm_module
+        .get_or_init(|| compile_module(font))
+        .as_ref()?;
+
+    // The guest can modify the buffer before failing, so keep a copy to fall back to.
+    let saved = buffer.clone();
+    let shaped = match run_module(module, font, plan, buffer) {
+        Ok(true) => true,
+        Ok(false) => {
+            log::info!("Wasm Shaper return with failure.");
+            false
+        }
+        Err(e) if e.as_trap_code() == Some(TrapCode::OutOfFuel) => {
+            log::error!("Wasm Shaper ran out of fuel.");
+            false
+        }
+        Err(e) => {
+            log::error!("Wasm Module Error: {e}");
+            false
+        }
+    };
 
-    let mut config = Config::default();
-    config.compilation_mode(wasmi::CompilationMode::Lazy);
-    let engine = Engine::new(&config);
+    if !shaped {
+        *buffer = saved;
+        return None;
+    }
 
-    let module = Module::new(&engine, wasm_blob).ok()?;
+    Some(())
+}
 
-    let mut linker = Linker::new(&engine);
+// Returns `Ok(false)` when the module doesn't shape the buffer.
+fn run_module(
+    module: &Module,
+    font: &hb_font_t,
+    plan: &hb_ot_shape_plan_t,
+    buffer: &mut hb_buffer_t,
+) -> Result<bool, wasmi::Error> {
+    let engine = module.engine();
+    let mut linker = Linker::new(engine);
 
     // Not every function defined by HarfBuzz is defined here.
     // Only the ones used by the harfbuzz_wasm crate
@@ -42,63 +98,84 @@ pub(crate) fn shape_with_wasm(
     // "Spec": https://github.com/harfbuzz/harfbuzz/blob/main/docs/wasm-shaper.md
     // crate: https://github.com/harfbuzz/harfbuzz-wasm-examples/blob/main/harfbuzz-wasm/src/lib.rs
     linker
-        .func_wrap("env", "face_get_upem", face_get_upem)
-        .ok()?
-        .func_wrap("env", "font_get_face", font_get_face)
-        .ok()?
-        .func_wrap("env", "font_get_glyph", font_get_glyph)
-        .ok()?
-        .func_wrap("env", "font_get_scale", font_get_scale)
-        .ok()?
-        .func_wrap("env", "font_get_glyph_extents", font_get_glyph_extents)
-        .ok()?
-        .func_wrap("env", "font_glyph_to_string", font_glyph_to_string)
-        .ok()?
-        .func_wrap("env", "font_get_glyph_h_advance", font_get_glyph_h_advance)
-        .ok()?
-        .func_wrap("env", "font_get_glyph_v_advance", font_get_glyph_v_advance)
-        .ok()?
-        .func_wrap("env", "font_copy_glyph_outline", font_copy_glyph_outline)
-        .ok()?
-        .func_wrap("env", "face_copy_table", face_copy_table)
-        .ok()?
-        .func_wrap("env", "buffer_copy_contents", buffer_copy_contents)
-        .ok()?
-        .func_wrap("env", "buffer_set_contents", buffer_set_contents)
-        .ok()?
-        .func_wrap("env", "debugprint", debugprint)
-        .ok()?
-        .func_wrap("env", "shape_with", shape_with)
-        .ok()?;
-
-    let data = ShapingData { font, plan, buffer };
-    let mut store = Store::new(&engine, data);
-
-    let instance = linker
-        .instantiate(&mut store, &module)
-        .ok()?
-        .start(&mut store)
-        .ok()?;
+        .func_wrap("env", "face_get_upem", face_get_upem)?
+        .func_wrap("env", "font_get_face", font_get_face)?
+        .func_wrap("env", "font_get_glyph", font_get_glyph)?
+        .func_wrap("env", "font_get_scale", font_get_scale)?
+        .func_wrap("env", "font_get_glyph_extents", font_get_glyph_extents)?
+        .func_wrap("env", "font_glyph_to_string", font_glyph_to_string)?
+        .func_wrap("env", "font_get_glyph_h_advance", font_get_glyph_h_advance)?
+        .func_wrap("env", "font_get_glyph_v_advance", font_get_glyph_v_advance)?
+        .func_wrap("env", "font_copy_glyph_outline", font_copy_glyph_outline)?
+        .func_wrap("env", "face_copy_table", face_copy_table)?
+        .func_wrap("env", "buffer_copy_contents", buffer_copy_contents)?
+        .func_wrap("env", "buffer_set_contents", buffer_set_contents)?
+        .func_wrap("env", "debugprint", debugprint)?
+        .func_wrap("env", "shape_with", shape_with)?;
+
+    let wasm_limits = font.wasm_limits;
+    let mut limits = StoreLimitsBuilder::new().trap_on_grow_failure(true);
+    if let Some(max_memory) = wasm_limits.max_memory {
+        limits = limits.memory_size(max_memory);
+    }
 
-    // return early if no "memory" or "shape" exports.
-    instance.get_memory(&mut store, "memory")?;
-    let shape = instance
-        .get_typed_func::<(u32, u32, u32, u32, u32), i32>(&mut store, "shape")
-        .ok()?;
+    let data = ShapingData {
+        font,
+        plan,
+        buffer,
+        limits: limits.build(),
+    };
+    let mut store = Store::new(engine, data);
+    store.limiter(|data| &mut data.limits);
+    // Fuel metering is always compiled in, so no limit is as much fuel as possible.
+    store.set_fuel(wasm_limits.fuel.unwrap_or(u64::MAX))?;
 
-    match shape.call(&mut store, (0, 0, 0, 0, 0)) {
-        Ok(0) => {
-            log::info!("Wasm Shaper return with failure.");
-            return None;
-        }
-        Err(e) => {
-            log::error!("Wasm Module Error: {e}");
-            return None;
-        }
-        _ => (),
+    let instance = linker.instantiate(&mut store, module)?.start(&mut store)?;
+
+    // return early if no "memory" or "shape" exports.
+    if instance.get_memory(&mut store, "memory").is_none() {
+        return Ok(false);
     }
+    let Ok(shape) = instance.get_typed_func::<(u32, u32, u32, u32, u32), i32>(&mut store, "shape")
+    else {
+        return Ok(false);
+    };
 
-    Some(())
+    Ok(shape.call(&mut store, (0, 0, 0, 0, 0))? != 0)
+}
+
+// Compiles the font's `Wasm` table. The module is cached on the face, while every
+// shaping call gets a fresh store and instance, so guest memory starts out clean.
+fn compile_module(font: &hb_font_t) -> Option<Module> {
+    let wasm_blob = font
+        .raw_face()
+        .table(ttf_parser::Tag::from_bytes(b"Wasm"))?;
+
+    let mut config = Config::default();
+    config.compilation_mode(wasmi::CompilationMode::Lazy);
+    config.consume_fuel(true);
+    let engine = Engine::new(&config);
+
+    Module::new(&engine, wasm_blob).ok()
+}
+
+// The host functions below only touch guest memory through `get` and checked arithmetic,
+// as every pointer and length comes from the font. Out of bounds accesses return 0.
+
+// The module's memory. Host functions can run from the start function, before
+// `run_module` checks the exports, so a missing memory traps rather than panics.
+fn guest_memory(caller: &Caller<'_, ShapingData>) -> Result<Memory, wasmi::Error> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmi::Error::new("Wasm module has no memory export"))
+}
+
+// Returns `count` records of `size` bytes at a guest pointer, if they are in bounds.
+fn guest_slice(mem_data: &[u8], ptr: u32, count: usize, size: usize) -> Option<&[u8]> {
+    let start = ptr as usize;
+    let end = start.checked_add(count.checked_mul(size)?)?;
+    mem_data.get(start..end)
 }
 
 // Definition in comments in the definition in harfbuzz_wasm crate.
@@ -133,9 +210,14 @@ fn font_get_glyph(caller: Caller<'_, ShapingData>, _font: u32, codepoint: u32, u
 
 // fn font_get_scale(font: u32, x_scale: *mut i32, y_scale: *mut i32);
 // Returns the scale of the current font.
-fn font_get_scale(mut caller: Caller<'_, ShapingData>, _font: u32, x_scale: u32, y_scale: u32) {
+fn font_get_scale(
+    mut caller: Caller<'_, ShapingData>,
+    _font: u32,
+    x_scale: u32,
+    y_scale: u32,
+) -> Result<(), wasmi::Error> {
     // Return upem as rustybuzz has no scale.
-    let memory = caller.get_export("memory").unwrap().into_memory().unwrap();
+    let memory = guest_memory(&caller)?;
     let upem = caller.data().font.units_per_em();
 
     _ = memory.write(
@@ -148,6 +230,8 @@ fn font_get_scale(mut caller: Caller<'_, ShapingData>, _font: u32, x_scale: u32,
         y_scale as usize,
         &upem.to_le_bytes(),
     );
+
+    Ok(())
 }
 
 // fn font_get_glyph_extents(font: u32, glyph: u32, extents: *mut CGlyphExtents) -> bool;
@@ -157,8 +241,8 @@ fn font_get_glyph_extents(
     _font: u32,
     glyph: u32,
     extents: u32,
-) -> u32 {
-    let memory = caller.get_export("memory").unwrap().into_memory().unwrap();
+) -> Result<u32, wasmi::Error> {
+    let memory = guest_memory(&caller)?;
 
     let mut glyph_extents = hb_glyph_extents_t::default();
     let ret = caller
@@ -173,7 +257,7 @@ fn font_get_glyph_extents(
         );
     }
 
-    ret as u32
+    Ok(ret as u32)
 }
 
 // fn font_glyph_to_string(font: u32, glyph: u32, str: *const u8, len: u32);
@@ -184,22 +268,31 @@ fn font_glyph_to_string(
     glyph: u32,
     str: u32,
     len: u32,
-) {
+) -> Result<(), wasmi::Error> {
     // len is the assigned heap memory. We should not allocate more than that.
     // Should not assume we are not writing over anything.
-    let memory = caller.get_export("memory").unwrap().into_memory().unwrap();
+    let memory = guest_memory(&caller)?;
+    if len == 0 {
+        return Ok(());
+    }
 
-    let mut name = caller
+    let name = caller
         .data()
         .font
         .glyph_name(GlyphId(glyph as u16))
         .map(ToOwned::to_owned)
         .unwrap_or(format!("g{:0>4}", glyph));
-    name.truncate(len as usize - 1);
-    let name = CString::new(name).unwrap();
-    let name = name.as_bytes_with_nul();
+    // Glyph names aren't necessarily ASCII, so truncate bytes rather than chars.
+    let name: Vec<u8> = name
+        .bytes()
+        .take_while(|&b| b != 0)
+        .take(len as usize - 1)
+        .chain([0])
+        .collect();
 
-    _ = memory.write(caller.as_context_mut(), str as usize, name);
+    _ = memory.write(caller.as_context_mut(), str as usize, &name);
+
+    Ok(())
 }
 
 // fn font_get_glyph_h_advance(font: u32, glyph: u32) -> i32;
@@ -297,8 +390,8 @@ fn font_copy_glyph_outline(
     _font: u32,
     glyph: u32,
     outline: u32,
-) -> u32 {
-    let memory = caller.get_export("memory").unwrap().into_memory().unwrap();
+) -> Result<u32, wasmi::Error> {
+    let memory = guest_memory(&caller)?;
 
     let mut builder = GlyphOutline::default();
     if caller
@@ -307,7 +400,7 @@ fn font_copy_glyph_outline(
         .outline_glyph(GlyphId(glyph as u16), &mut builder)
         .is_none()
     {
-        return 0;
+        return Ok(0);
     };
 
     let points_size = builder.points.len() * core::mem::size_of::<OutlinePoint>();
@@ -321,14 +414,16 @@ fn font_copy_glyph_outline(
         .grow(&mut caller.as_context_mut(), page_growth_needed as u32)
         .is_err()
     {
-        return 0;
+        return Ok(0);
     };
 
     let mem_data = memory.data_mut(&mut caller);
-
-    mem_data[eom..eom + points_size].copy_from_slice(bytemuck::cast_slice(&builder.points));
-    mem_data[eom + points_size..eom + needed_size]
-        .copy_from_slice(bytemuck::cast_slice(&builder.contours));
+    let Some(dest) = mem_data.get_mut(eom..eom + needed_size) else {
+        return Ok(0);
+    };
+    let (points, contours) = dest.split_at_mut(points_size);
+    points.copy_from_slice(bytemuck::cast_slice(&builder.points));
+    contours.copy_from_slice(bytemuck::cast_slice(&builder.contours));
 
     let builder = CGlyphOutline {
         n_points: builder.points.len() as u32,
@@ -345,10 +440,10 @@ fn font_copy_glyph_outline(
         )
         .is_err()
     {
-        return 0;
+        return Ok(0);
     };
 
-    1
+    Ok(1)
 }
 
 #[repr(C)]
@@ -364,18 +459,23 @@ unsafe impl bytemuck::Pod for Blob {}
 
 // fn face_copy_table(font: u32, tag: u32, blob: *mut Blob) -> bool;
 // Copies the binary data in the OpenType table referenced by tag into the supplied blob structure.
-fn face_copy_table(mut caller: Caller<'_, ShapingData>, _font: u32, tag: u32, blob: u32) -> u32 {
+fn face_copy_table(
+    mut caller: Caller<'_, ShapingData>,
+    _font: u32,
+    tag: u32,
+    blob: u32,
+) -> Result<u32, wasmi::Error> {
     // So here to copy stuff INTO the module, we need to copy it into its heap
     // We should not assume that there is an area that's not written to,
     // so the most straightforward way to get "clean" memory is to grow it by one page,
     // and allocate there. This is not idiomatic to either Rust or Wasm but it is the
     // best we can do with the given API.
 
-    let memory = caller.get_export("memory").unwrap().into_memory().unwrap();
+    let memory = guest_memory(&caller)?;
 
     let tag = tag.to_be_bytes();
     let Some(table) = caller.data().font.raw_face().table(Tag::from_bytes(&tag)) else {
-        return 0;
+        return Ok(0);
     };
 
     // 1 page is 65536 or 0x10000 bytes.
@@ -386,7 +486,7 @@ fn face_copy_table(mut caller: Caller<'_, ShapingData>, _font: u32, tag: u32, bl
         .grow(&mut caller.as_context_mut(), page_growth_needed as u32)
         .is_err()
     {
-        return 0;
+        return Ok(0);
     };
 
     let ret = Blob {
@@ -402,21 +502,25 @@ fn face_copy_table(mut caller: Caller<'_, ShapingData>, _font: u32, tag: u32, bl
         )
         .is_err()
     {
-        return 0;
+        return Ok(0);
     };
 
     if memory.write(caller.as_context_mut(), eom, table).is_err() {
-        return 0;
+        return Ok(0);
     };
 
-    1
+    Ok(1)
 }
 
 // fn buffer_copy_contents(buffer: u32, cbuffer: *mut CBufferContents) -> bool;
 // Retrieves the contents of the host shaping engine's buffer into the buffer_contents structure. This should typically be called at the beginning of shaping.
-fn buffer_copy_contents(mut caller: Caller<'_, ShapingData>, _buffer: u32, cbuffer: u32) -> u32 {
+fn buffer_copy_contents(
+    mut caller: Caller<'_, ShapingData>,
+    _buffer: u32,
+    cbuffer: u32,
+) -> Result<u32, wasmi::Error> {
     // see face_copy_table for why we're growing memory.
-    let memory = caller.get_export("memory").unwrap().into_memory().unwrap();
+    let memory = guest_memory(&caller)?;
 
     let length = caller.data().buffer.len;
 
@@ -429,7 +533,7 @@ fn buffer_copy_contents(mut caller: Caller<'_, ShapingData>, _buffer: u32, cbuff
         .grow(&mut caller.as_context_mut(), page_growth_needed as u32)
         .is_err()
     {
-        return 0;
+        return Ok(0);
     };
 
     let (mem_data, store_data) = memory.data_and_store_mut(&mut caller);
@@ -439,8 +543,13 @@ fn buffer_copy_contents(mut caller: Caller<'_, ShapingData>, _buffer: u32, cbuff
     let pos_loc = eom + length * core::mem::size_of::<hb_glyph_info_t>();
     let end_loc = pos_loc + length * core::mem::size_of::<GlyphPosition>();
 
-    mem_data[eom..pos_loc].copy_from_slice(bytemuck::cast_slice(&rb_buffer.info));
-    mem_data[pos_loc..end_loc].copy_from_slice(bytemuck::cast_slice(&rb_buffer.pos));
+    let Some(dest) = mem_data.get_mut(eom..end_loc) else {
+        return Ok(0);
+    };
+    let (info, pos) = dest.split_at_mut(pos_loc - eom);
+    // The buffer's arrays can be longer than its length.
+    info.copy_from_slice(bytemuck::cast_slice(&rb_buffer.info[..length]));
+    pos.copy_from_slice(bytemuck::cast_slice(&rb_buffer.pos[..length]));
 
     let buffer_contents = CBufferContents {
         length: length as u32,
@@ -455,10 +564,10 @@ fn buffer_copy_contents(mut caller: Caller<'_, ShapingData>, _buffer: u32, cbuff
         )
         .is_err()
     {
-        return 0;
+        return Ok(0);
     };
 
-    1
+    Ok(1)
 }
 
 #[repr(C)]
@@ -474,58 +583,66 @@ unsafe impl bytemuck::Pod for CBufferContents {}
 
 // fn buffer_set_contents(buffer: u32, cbuffer: &CBufferContents) -> bool;
 // Copy the buffer_contents structure back into the host shaping engine's buffer. This should typically be called at the end of shaping.
-fn buffer_set_contents(mut caller: Caller<'_, ShapingData>, _buffer: u32, cbuffer: u32) -> u32 {
-    let memory = caller.get_export("memory").unwrap().into_memory().unwrap();
+fn buffer_set_contents(
+    mut caller: Caller<'_, ShapingData>,
+    _buffer: u32,
+    cbuffer: u32,
+) -> Result<u32, wasmi::Error> {
+    let memory = guest_memory(&caller)?;
 
     let mut buffer = [0; core::mem::size_of::<CBufferContents>()];
     if memory
         .read(caller.as_context_mut(), cbuffer as usize, &mut buffer)
         .is_err()
     {
-        return 0;
-    };
-    let Ok(buffer) = bytemuck::try_from_bytes::<CBufferContents>(&buffer) else {
-        return 0;
+        return Ok(0);
     };
+    let buffer: CBufferContents = bytemuck::pod_read_unaligned(&buffer);
 
     let (mem_data, store_data) = memory.data_and_store_mut(&mut caller);
 
-    let array_length = buffer.length as usize * core::mem::size_of::<hb_glyph_info_t>();
+    let length = buffer.length as usize;
+    let info_size = core::mem::size_of::<hb_glyph_info_t>();
+    let pos_size = core::mem::size_of::<GlyphPosition>();
+    let Some(info) = guest_slice(mem_data, buffer.info, length, info_size) else {
+        return Ok(0);
+    };
+    let Some(pos) = guest_slice(mem_data, buffer.position, length, pos_size) else {
+        return Ok(0);
+    };
 
-    store_data.buffer.len = buffer.length as usize;
+    // Guest pointers aren't necessarily aligned, so read the records one by one.
+    store_data.buffer.len = length;
 
     store_data.buffer.info.clear();
-    store_data
-        .buffer
-        .info
-        .extend_from_slice(bytemuck::cast_slice(
-            &mem_data[buffer.info as usize..buffer.info as usize + array_length],
-        ));
+    store_data.buffer.info.extend(
+        info.chunks_exact(info_size)
+            .map(bytemuck::pod_read_unaligned::<hb_glyph_info_t>),
+    );
 
     store_data.buffer.pos.clear();
-    store_data
-        .buffer
-        .pos
-        .extend_from_slice(bytemuck::cast_slice(
-            &mem_data[buffer.position as usize..buffer.position as usize + array_length],
-        ));
-
-    1
+    store_data.buffer.pos.extend(
+        pos.chunks_exact(pos_size)
+            .map(bytemuck::pod_read_unaligned::<GlyphPosition>),
+    );
+
+    Ok(1)
 }
 
 // fn debugprint(s: *const u8);
 // Produces a debugging message in the host shaper's log output;
-fn debugprint(caller: Caller<'_, ShapingData>, s: u32) {
-    let memory = caller.get_export("memory").unwrap().into_memory().unwrap();
-    if memory.data(&caller).get(s as usize).is_none() {
-        return;
-    }
-    let bytes = &memory.data(&caller)[s as usize..];
+fn debugprint(caller: Caller<'_, ShapingData>, s: u32) -> Result<(), wasmi::Error> {
+    let memory = guest_memory(&caller)?;
+    let Some(bytes) = memory.data(&caller).get(s as usize..) else {
+        return Ok(());
+    };
     let msg = CStr::from_bytes_until_nul(bytes)
         .unwrap_or_default()
         .to_string_lossy();
 
     log::debug!("Wasm Module: {msg}");
+
+    Ok(())
 }
 
 // fn shape_with(font: u32, buffer: u32, features: u32, num_features: u32, shaper: *const u8) -> i32;
@@ -540,19 +657,18 @@ fn shape_with(
     _num_features: u32,
     // we dont have custom shapers (yet?).
     shaper: u32,
-) -> i32 {
-    let memory = caller.get_export("memory").unwrap().into_memory().unwrap();
-    if memory.data(&caller).get(shaper as usize).is_none() {
-        return 0;
-    }
-    let bytes = &memory.data(&caller)[shaper as usize..];
+) -> Result<i32, wasmi::Error> {
+    let memory = guest_memory(&caller)?;
+    let Some(bytes) = memory.data(&caller).get(shaper as usize..) else {
+        return Ok(0);
+    };
     let shaper = CStr::from_bytes_until_nul(bytes)
         .unwrap_or_default()
         .to_string_lossy();
 
     if !(shaper.eq_ignore_ascii_case("ot") || shaper.eq_ignore_ascii_case("rustybuzz")) {
         log::warn!("Only ot shaper is available in rustybuzz.");
-        return 0;
+        return Ok(0);
     }
 
     let face = caller.data().font;
@@ -566,5 +682,5 @@ fn shape_with(
         target_direction,
     });
 
-    1
+    Ok(1)
 }
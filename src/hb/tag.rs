@@ -1,3 +1,6 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::str::FromStr;
 
 use smallvec::SmallVec;
@@ -22,10 +25,112 @@ impl<A: smallvec::Array> SmallVecExt for SmallVec<A> {
     }
 }
 
-/// Converts an `Script` and an `Language` to script and language tags.
+/// Converts a `Script` and a `Language` to OpenType script and language system tags.
+///
+/// Both lists are ordered from the most to the least preferred tag. A font
+/// usually has at most one of them, so the first tag it has should be used.
+/// An empty script list means the default script (`DFLT`), and an empty
+/// language list means the default language system.
 pub fn tags_from_script_and_language(
     script: Option<Script>,
     language: Option<&Language>,
+) -> (Vec<hb_tag_t>, Vec<hb_tag_t>) {
+    let (scripts, languages) = hb_ot_tags_from_script_and_language(script, language);
+    (scripts.to_vec(), languages.to_vec())
+}
+
+/// Converts an OpenType script tag to a `Script`.
+///
+/// Returns `None` for the default script (`DFLT`).
+pub fn tag_to_script(tag: hb_tag_t) -> Option<Script> {
+    let bytes = tag.to_bytes();
+    if bytes[3] == b'2' || bytes[3] == b'3' {
+        let mut tag2 = bytes;
+        tag2[3] = b'2';
+        return new_tag_to_script(hb_tag_t::from_bytes(&tag2));
+    }
+
+    old_tag_to_script(tag)
+}
+
+/// Converts an OpenType language system tag to a BCP 47 `Language`.
+///
+/// Tags that correspond to several languages are resolved to the one that the
+/// tag was registered for. Tags that are not known are preserved as
+/// a private use subtag, so converting the result back with
+/// [`tags_from_script_and_language`] yields the original tag.
+///
+/// Returns `None` for the default language system (`dflt`).
+pub fn tag_to_language(tag: hb_tag_t) -> Option<Language> {
+    if tag.is_null() || tag == hb_tag_t::from_bytes(b"dflt") {
+        return None;
+    }
+
+    if let Some(language) = tag_table::ambiguous_tag_to_language(tag) {
+        return Language::from_str(language).ok();
+    }
+
+    use tag_table::OPEN_TYPE_LANGUAGES as LANGUAGES;
+
+    // Prefer two-letter language subtags, like `ar` over `arb` for ARA.
+    let entry = LANGUAGES
+        .iter()
+        .find(|v| v.tag == tag && v.language.len() == 2)
+        .or_else(|| LANGUAGES.iter().find(|v| v.tag == tag));
+    if let Some(entry) = entry {
+        return Language::from_str(entry.language).ok();
+    }
+
+    // Return a custom language in the form of `x-hbot-AABBCCDD`.
+    // If it's three letters long, also guess it's ISO 639-3 and prepend it
+    // lowercased. The private use subtag makes sure that the original tag
+    // is still what `tags_from_script_and_language` returns.
+    let bytes = tag.to_bytes();
+    let language = if bytes[..3].iter().all(u8::is_ascii_alphabetic) && bytes[3] == b' ' {
+        let prefix: String = bytes[..3]
+            .iter()
+            .map(|c| c.to_ascii_lowercase() as char)
+            .collect();
+        format!("{}-x-hbot-{:08x}", prefix, tag.as_u32())
+    } else {
+        format!("x-hbot-{:08x}", tag.as_u32())
+    };
+
+    Language::from_str(&language).ok()
+}
+
+/// Converts an OpenType script tag and language system tag to a `Script`
+/// and a BCP 47 `Language`.
+///
+/// When the script tag is not the one that [`tags_from_script_and_language`]
+/// would pick first for the resulting script, like `deva` instead of `dev3`,
+/// it is preserved in the language as a private use subtag.
+pub fn tags_to_script_and_language(
+    script_tag: hb_tag_t,
+    language_tag: hb_tag_t,
+) -> (Option<Script>, Option<Language>) {
+    let script = tag_to_script(script_tag);
+    let language = tag_to_language(language_tag);
+
+    let (primary_script_tags, _) = hb_ot_tags_from_script_and_language(script, None);
+    if primary_script_tags.first() == Some(&script_tag) {
+        return (script, language);
+    }
+
+    let language = match language {
+        Some(language) if language.as_str().starts_with("x-") => {
+            format!("{}-hbsc-{:08x}", language.as_str(), script_tag.as_u32())
+        }
+        Some(language) => format!("{}-x-hbsc-{:08x}", language.as_str(), script_tag.as_u32()),
+        None => format!("x-hbsc-{:08x}", script_tag.as_u32()),
+    };
+
+    (script, Language::from_str(&language).ok())
+}
+
+pub(crate) fn hb_ot_tags_from_script_and_language(
+    script: Option<Script>,
+    language: Option<&Language>,
 ) -> (ThreeTags, ThreeTags) {
     let mut needs_script = true;
     let mut scripts = SmallVec::new();
@@ -107,11 +212,24 @@ fn parse_private_use_subtag(
     };
 
     let mut tag = SmallVec::<[u8; 4]>::new();
-    for c in private_use_subtag.bytes().take(4) {
-        if c.is_ascii_alphanumeric() {
-            tag.push((normalize)(&c));
-        } else {
-            break;
+    if let Some(hex) = private_use_subtag.strip_prefix('-') {
+        // A full tag written as eight hex digits, like `-hbsc-64657661`.
+        let hex = match hex.get(..8) {
+            Some(v) => v,
+            None => return false,
+        };
+
+        match u32::from_str_radix(hex, 16) {
+            Ok(v) if hex.bytes().all(|c| c.is_ascii_hexdigit()) => tag.extend(v.to_be_bytes()),
+            _ => return false,
+        }
+    } else {
+        for c in private_use_subtag.bytes().take(4) {
+            if c.is_ascii_alphanumeric() {
+                tag.push((normalize)(&c));
+            } else {
+                break;
+            }
         }
     }
 
@@ -253,70 +371,59 @@ fn old_tag_from_script(script: Script) -> hb_tag_t {
     }
 }
 
-#[rustfmt::skip]
-#[cfg(test)]
-mod tests {
-    #![allow(non_snake_case)]
-
-    use super::*;
-    use core::str::FromStr;
-    use alloc::vec::Vec;
-
-    fn new_tag_to_script(tag: hb_tag_t) -> Option<Script> {
-        match &tag.to_bytes() {
-            b"bng2" => Some(script::BENGALI),
-            b"dev2" => Some(script::DEVANAGARI),
-            b"gjr2" => Some(script::GUJARATI),
-            b"gur2" => Some(script::GURMUKHI),
-            b"knd2" => Some(script::KANNADA),
-            b"mlm2" => Some(script::MALAYALAM),
-            b"ory2" => Some(script::ORIYA),
-            b"tml2" => Some(script::TAMIL),
-            b"tel2" => Some(script::TELUGU),
-            b"mym2" => Some(script::MYANMAR),
-            _ => Some(script::UNKNOWN),
-        }
+fn new_tag_to_script(tag: hb_tag_t) -> Option<Script> {
+    match &tag.to_bytes() {
+        b"bng2" => Some(script::BENGALI),
+        b"dev2" => Some(script::DEVANAGARI),
+        b"gjr2" => Some(script::GUJARATI),
+        b"gur2" => Some(script::GURMUKHI),
+        b"knd2" => Some(script::KANNADA),
+        b"mlm2" => Some(script::MALAYALAM),
+        b"ory2" => Some(script::ORIYA),
+        b"tml2" => Some(script::TAMIL),
+        b"tel2" => Some(script::TELUGU),
+        b"mym2" => Some(script::MYANMAR),
+        _ => Some(script::UNKNOWN),
     }
+}
 
-    fn old_tag_to_script(tag: hb_tag_t) -> Option<Script> {
-        if tag == hb_tag_t::default_script() {
-            return None;
-        }
+fn old_tag_to_script(tag: hb_tag_t) -> Option<Script> {
+    if tag == hb_tag_t::default_script() {
+        return None;
+    }
 
-        let mut bytes = tag.to_bytes();
+    let mut bytes = tag.to_bytes();
 
-        // This side of the conversion is fully algorithmic.
+    // This side of the conversion is fully algorithmic.
 
-        // Any spaces at the end of the tag are replaced by repeating the last
-        // letter.  Eg 'nko ' -> 'Nkoo'
-        if bytes[2] == b' ' {
-            bytes[2] = bytes[1];
-        }
-        if bytes[3] == b' ' {
-            bytes[3] = bytes[2];
-        }
+    // Any spaces at the end of the tag are replaced by repeating the last
+    // letter.  Eg 'nko ' -> 'Nkoo'
+    if bytes[2] == b' ' {
+        bytes[2] = bytes[1];
+    }
+    if bytes[3] == b' ' {
+        bytes[3] = bytes[2];
+    }
 
-        // Change first char to uppercase.
-        bytes[0] = bytes[0].to_ascii_uppercase();
+    // Change first char to uppercase.
+    bytes[0] = bytes[0].to_ascii_uppercase();
 
-        Some(Script(hb_tag_t::from_bytes(&bytes)))
-    }
+    Some(Script(hb_tag_t::from_bytes(&bytes)))
+}
 
-    fn tag_to_script(tag: hb_tag_t) -> Option<Script> {
-        let bytes = tag.to_bytes();
-        if bytes[3] == b'2' || bytes[3] == b'3' {
-            let mut tag2 = bytes;
-            tag2[3] = b'2';
-            return new_tag_to_script(hb_tag_t::from_bytes(&tag2));
-        }
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    #![allow(non_snake_case)]
 
-        old_tag_to_script(tag)
-    }
+    use super::*;
+    use core::str::FromStr;
+    use alloc::vec::Vec;
 
     fn test_simple_tags(tag: &str, script: Script) {
         let tag = hb_tag_t::from_bytes_lossy(tag.as_bytes());
 
-        let (scripts, _) = tags_from_script_and_language(Some(script), None);
+        let (scripts, _) = hb_ot_tags_from_script_and_language(Some(script), None);
         if !scripts.is_empty() {
             assert_eq!(tag, scripts[0]);
         } else {
@@ -347,7 +454,7 @@ mod tests {
         // Hiragana and Katakana both map to 'kana'.
         test_simple_tags("kana", script::KATAKANA);
 
-        let (scripts, _) = tags_from_script_and_language(Some(script::HIRAGANA), None);
+        let (scripts, _) = hb_ot_tags_from_script_and_language(Some(script::HIRAGANA), None);
         assert_eq!(scripts.as_slice(), &[hb_tag_t::from_bytes(b"kana")]);
 
         // Spaces are replaced
@@ -390,7 +497,7 @@ mod tests {
             #[test]
             fn $name() {
                 let tag = hb_tag_t::from_bytes_lossy($tag.as_bytes());
-                let (scripts, _) = tags_from_script_and_language(
+                let (scripts, _) = hb_ot_tags_from_script_and_language(
                     $script, Language::from_str($lang).ok().as_ref(),
                 );
                 if !scripts.is_empty() {
@@ -425,7 +532,7 @@ mod tests {
             let tag2 = hb_tag_t::from_bytes_lossy(tag2.as_bytes());
             let tag3 = hb_tag_t::from_bytes_lossy(tag3.as_bytes());
 
-            let (scripts, _) = tags_from_script_and_language(Some(script), None);
+            let (scripts, _) = hb_ot_tags_from_script_and_language(Some(script), None);
             assert_eq!(scripts.as_slice(), &[tag1, tag2, tag3]);
             assert_eq!(tag_to_script(tag1), Some(script));
             assert_eq!(tag_to_script(tag2), Some(script));
@@ -449,7 +556,7 @@ mod tests {
             #[test]
             fn $name() {
                 let tag = hb_tag_t::from_bytes_lossy($tag.as_bytes());
-                let (_, languages) = tags_from_script_and_language(
+                let (_, languages) = hb_ot_tags_from_script_and_language(
                     None, Language::from_str(&$lang.to_lowercase()).ok().as_ref(),
                 );
                 if !languages.is_empty() {
@@ -628,7 +735,7 @@ mod tests {
         ($name:ident, $script:expr, $lang:expr, $scripts:expr, $langs:expr) => {
             #[test]
             fn $name() {
-                let (scripts, languages) = tags_from_script_and_language(
+                let (scripts, languages) = hb_ot_tags_from_script_and_language(
                     $script, Language::from_str($lang).ok().as_ref(),
                 );
 
@@ -651,4 +758,82 @@ mod tests {
     test_tags!(tag_full_ml, Some(script::MALAYALAM), "ml", &[b"mlm3", b"mlm2", b"mlym"], &[b"MAL", b"MLR"]);
     test_tags!(tag_full_xyz, None, "xyz", &[], &[b"XYZ"]);
     test_tags!(tag_full_xy, None, "xy", &[], &[]);
+
+    macro_rules! test_tag_to_language {
+        ($name:ident, $tag:expr, $lang:expr) => {
+            #[test]
+            fn $name() {
+                let tag = hb_tag_t::from_bytes_lossy($tag);
+                assert_eq!(tag_to_language(tag), Language::from_str($lang).ok());
+            }
+        };
+    }
+
+    test_tag_to_language!(tag_to_language_dflt, b"dflt", "");
+    test_tag_to_language!(tag_to_language_ALT, b"ALT", "alt");
+    test_tag_to_language!(tag_to_language_ARA, b"ARA", "ar");
+    test_tag_to_language!(tag_to_language_AZE, b"AZE", "az");
+    test_tag_to_language!(tag_to_language_CJA, b"CJA", "cja");
+    test_tag_to_language!(tag_to_language_HAL, b"HAL", "cfm");
+    test_tag_to_language!(tag_to_language_HYE, b"HYE", "hyw");
+    test_tag_to_language!(tag_to_language_HYE0, b"HYE0", "hy");
+    test_tag_to_language!(tag_to_language_IPPH, b"IPPH", "und-fonipa");
+    test_tag_to_language!(tag_to_language_MOL, b"MOL", "ro-md");
+    test_tag_to_language!(tag_to_language_MNK, b"MNK", "man");
+    test_tag_to_language!(tag_to_language_QIN, b"QIN", "bgr");
+    test_tag_to_language!(tag_to_language_SWA, b"SWA", "aii");
+    test_tag_to_language!(tag_to_language_SYR, b"SYR", "syr");
+    test_tag_to_language!(tag_to_language_ZHH, b"ZHH", "zh-hk");
+    test_tag_to_language!(tag_to_language_ZHS, b"ZHS", "zh-hans");
+    test_tag_to_language!(tag_to_language_ZHT, b"ZHT", "zh-hant");
+    test_tag_to_language!(tag_to_language_ZHTM, b"ZHTM", "zh-hant-mo");
+    test_tag_to_language!(tag_to_language_XYZ, b"XYZ", "xyz-x-hbot-58595a20");
+    test_tag_to_language!(tag_to_language_XYZW, b"XYZW", "x-hbot-58595a57");
+
+    #[test]
+    fn tag_to_language_round_trip() {
+        for entry in tag_table::OPEN_TYPE_LANGUAGES {
+            if entry.tag.is_null() {
+                continue;
+            }
+
+            let language = tag_to_language(entry.tag).unwrap();
+            let (_, languages) = hb_ot_tags_from_script_and_language(None, Some(&language));
+            assert!(languages.contains(&entry.tag), "{}", language.as_str());
+
+            // Disambiguated tags are always the preferred tag of their language.
+            if tag_table::ambiguous_tag_to_language(entry.tag).is_some() {
+                assert_eq!(languages[0], entry.tag, "{}", language.as_str());
+            }
+        }
+    }
+
+    macro_rules! test_tags_to_script_and_language {
+        ($name:ident, $script_tag:expr, $lang_tag:expr, $script:expr, $lang:expr) => {
+            #[test]
+            fn $name() {
+                let (script, language) = tags_to_script_and_language(
+                    hb_tag_t::from_bytes_lossy($script_tag), hb_tag_t::from_bytes_lossy($lang_tag),
+                );
+                assert_eq!(script, $script);
+                assert_eq!(language, Language::from_str($lang).ok());
+            }
+        };
+    }
+
+    test_tags_to_script_and_language!(tags_to_script_and_language_DFLT_ENG, b"DFLT", b"ENG", None, "en-x-hbsc-44464c54");
+    test_tags_to_script_and_language!(tags_to_script_and_language_latn_ENG, b"latn", b"ENG", Some(script::LATIN), "en");
+    test_tags_to_script_and_language!(tags_to_script_and_language_latn_dflt, b"latn", b"dflt", Some(script::LATIN), "");
+    test_tags_to_script_and_language!(tags_to_script_and_language_deva_MAR, b"deva", b"MAR", Some(script::DEVANAGARI), "mr-x-hbsc-64657661");
+    test_tags_to_script_and_language!(tags_to_script_and_language_dev2_MAR, b"dev2", b"MAR", Some(script::DEVANAGARI), "mr-x-hbsc-64657632");
+    test_tags_to_script_and_language!(tags_to_script_and_language_dev3_MAR, b"dev3", b"MAR", Some(script::DEVANAGARI), "mr");
+    test_tags_to_script_and_language!(tags_to_script_and_language_qaa_QTZ0, b"qaa", b"QTZ0", Script::from_iso15924_tag(hb_tag_t::from_bytes(b"Qaaa")), "x-hbot-51545a30-hbsc-71616120");
+
+    #[test]
+    fn tags_to_script_and_language_round_trip() {
+        let language = Language::from_str("mr-x-hbsc-64657661").unwrap();
+        let (scripts, languages) = hb_ot_tags_from_script_and_language(Some(script::DEVANAGARI), Some(&language));
+        assert_eq!(scripts.as_slice(), &[hb_tag_t::from_bytes(b"deva")]);
+        assert_eq!(languages.as_slice(), &[hb_tag_t::from_bytes(b"MAR ")]);
+    }
 }
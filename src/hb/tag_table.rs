@@ -2481,3 +2481,268 @@ pub fn tags_from_complex_language(language: &str, tags: &mut smallvec::SmallVec<
     }
     false
 }
+
+/// Returns the preferred BCP 47 language tag for an OpenType language system tag
+/// that a lookup in `OPEN_TYPE_LANGUAGES` would not resolve as well.
+#[rustfmt::skip]
+pub fn ambiguous_tag_to_language(tag: Tag) -> Option<&'static str> {
+    match &tag.to_bytes() {
+        b"AAQ " => Some("aaq"), // Eastern Abenaki -> Eastern Abnaki
+        b"ACH " => Some("ach"), // Acholi -> Acoli
+        b"ADY " => Some("ady"), // Adyghe
+        b"AIO " => Some("aio"), // Aiton
+        b"ALT " => Some("alt"), // Altai -> Southern Altai
+        b"ANG " => Some("ang"), // Anglo-Saxon -> Old English (ca. 450-1100)
+        b"APPH" => Some("und-fonnapa"), // Phonetic transcription—Americanist conventions -> Undetermined; North American Phonetic Alphabet
+        b"ARK " => Some("rki"), // Rakhine
+        b"AST " => Some("ast"), // Asturian
+        b"ATH " => Some("ath"), // Athapaskan -> Athapascan [collection]
+        b"ATS " => Some("ats"), // Gros Ventre (Atsina)
+        b"AVN " => Some("avn"), // Avatime
+        b"AWA " => Some("awa"), // Awadhi
+        b"BAN " => Some("ban"), // Balinese
+        b"BAR " => Some("bar"), // Bavarian
+        b"BBR " => Some("ber"), // Berber
+        b"BDC " => Some("bdc"), // Emberá-Baudó
+        b"BDY " => Some("bdy"), // Bandjalang
+        b"BEM " => Some("bem"), // Bemba (Zambia)
+        b"BGC " => Some("bgc"), // Haryanvi
+        b"BHO " => Some("bho"), // Bhojpuri
+        b"BIK " => Some("bik"), // Bikol
+        b"BJJ " => Some("bjj"), // Kanauji
+        b"BPY " => Some("bpy"), // Bishnupriya Manipuri -> Bishnupriya
+        b"BRH " => Some("brh"), // Brahui
+        b"BRX " => Some("brx"), // Bodo (India)
+        b"BSK " => Some("bsk"), // Burushaski
+        b"BTK " => Some("btk"), // Batak
+        b"BUG " => Some("bug"), // Bugis -> Buginese
+        b"CAY " => Some("cay"), // Cayuga
+        b"CBG " => Some("cbg"), // Chimila
+        b"CEB " => Some("ceb"), // Cebuano
+        b"CGG " => Some("cgg"), // Chiga
+        b"CHO " => Some("cho"), // Choctaw
+        b"CHR " => Some("chr"), // Cherokee
+        b"CHY " => Some("chy"), // Cheyenne
+        b"CJA " => Some("cja"), // Western Cham
+        b"CJM " => Some("cjm"), // Eastern Cham
+        b"CMI " => Some("cmi"), // Emberá-Chamí
+        b"COO " => Some("coo"), // Comox
+        b"COP " => Some("cop"), // Coptic
+        b"CPP " => Some("crp"), // Creoles -> Creoles and pidgins [collection]
+        b"CRR " => Some("crx"), // Carrier
+        b"CSB " => Some("csb"), // Kashubian
+        b"CTG " => Some("ctg"), // Chittagonian
+        b"CTO " => Some("cto"), // Emberá-Catío
+        b"CTT " => Some("ctt"), // Wayanad Chetti
+        b"CUK " => Some("cuk"), // San Blas Kuna
+        b"DAG " => Some("dag"), // Dagbani
+        b"DAR " => Some("dar"), // Dargwa
+        b"DAX " => Some("dax"), // Dayi
+        b"DGR " => Some("doi"), // Dogri (macrolanguage)
+        b"DHG " => Some("dhg"), // Dhangu
+        b"DNJ " => Some("dnj"), // Dan
+        b"DNK " => Some("din"), // Dinka
+        b"DRI " => Some("prs"), // Dari
+        b"EFI " => Some("efi"), // Efik
+        b"EMP " => Some("emp"), // Northern Emberá
+        b"ESU " => Some("esu"), // Central Yupik
+        b"FON " => Some("fon"), // Fon
+        b"FRC " => Some("frc"), // Cajun French
+        b"FRP " => Some("frp"), // Arpitan
+        b"GAG " => Some("gag"), // Gagauz
+        b"GEZ " => Some("gez"), // Geez
+        b"GIH " => Some("gih"), // Githabul
+        b"GLK " => Some("glk"), // Gilaki
+        b"GNN " => Some("gnn"), // Gumatj
+        b"GOG " => Some("gog"), // Gogo
+        b"GON " => Some("gon"), // Gondi
+        b"GUC " => Some("guc"), // Wayuu
+        b"GUF " => Some("guf"), // Gupapuyngu
+        b"GUZ " => Some("guz"), // Gusii
+        b"HAW " => Some("haw"), // Hawaiian
+        b"HAY " => Some("hay"), // Haya
+        b"HAZ " => Some("haz"), // Hazaragi
+        b"HEI " => Some("hei"), // Heiltsuk
+        b"HIL " => Some("hil"), // Hiligaynon
+        b"HMN " => Some("hmn"), // Hmong
+        b"HND " => Some("hnd"), // Hindko -> Southern Hindko
+        b"HUR " => Some("hur"), // Halkomelem
+        b"HYE " => Some("hyw"), // Armenian -> Western Armenian
+        b"IBA " => Some("iba"), // Iban
+        b"IBB " => Some("ibb"), // Ibibio
+        b"IJO " => Some("ijo"), // Ijo
+        b"ILO " => Some("ilo"), // Ilokano -> Iloko
+        b"IPPH" => Some("und-fonipa"), // Phonetic transcription—IPA conventions -> Undetermined; International Phonetic Alphabet
+        b"IRT " => Some("ga-Latg"), // Irish Traditional -> Irish; Latin (Gaelic variant)
+        b"IRU " => Some("iru"), // Irula
+        b"JBO " => Some("jbo"), // Lojban
+        b"JCT " => Some("jct"), // Krymchak
+        b"JDT " => Some("jdt"), // Judeo-Tat
+        b"JII " => Some("yi"), // Yiddish
+        b"KAL " => Some("kln"), // Kalenjin
+        b"KAW " => Some("kaw"), // Kawi (Old Javanese)
+        b"KBC " => Some("kbc"), // Kadiwéu
+        b"KDE " => Some("kde"), // Makonde
+        b"KGE " => Some("und-Geok"), // Khutsuri Georgian -> Undetermined; Khutsuri (Asomtavruli and Nuskhuri)
+        b"KHW " => Some("khw"), // Khowar
+        b"KJD " => Some("kjd"), // Southern Kiwai
+        b"KJJ " => Some("kjj"), // Khinalug -> Khinalugh
+        b"KJZ " => Some("kjz"), // Bumthangkha
+        b"KMZ " => Some("kmz"), // Khorasani Turkic -> Khorasani Turkish
+        b"KOH " => Some("okm"), // Korean Old Hangul -> Middle Korean (10th-16th cent.)
+        b"KOK " => Some("kok"), // Konkani
+        b"KOS " => Some("kos"), // Kosraean
+        b"KPL " => Some("kpe"), // Kpelle
+        b"KRL " => Some("krl"), // Karelian
+        b"KRN " => Some("kar"), // Karen
+        b"KUI " => Some("uki"), // Kui
+        b"KUM " => Some("kum"), // Kumyk
+        b"KWK " => Some("kwk"), // Kwakʼwala -> Kwakiutl
+        b"LEF " => Some("lef"), // Lelemi
+        b"LEZ " => Some("lez"), // Lezgi -> Lezghian
+        b"LIJ " => Some("lij"), // Ligurian
+        b"LIS " => Some("lis"), // Lisu
+        b"LIV " => Some("liv"), // Liv
+        b"LJP " => Some("ljp"), // Lampung -> Lampung Api
+        b"LKI " => Some("lki"), // Laki
+        b"LMA " => Some("mhr"), // Low Mari -> Eastern Mari
+        b"LMO " => Some("lmo"), // Lombard
+        b"LOM " => Some("lom"), // Loma (Liberia)
+        b"LPO " => Some("lpo"), // Lipo
+        b"LUA " => Some("lua"), // Luba-Lulua
+        b"LUH " => Some("luy"), // Luyia
+        b"LUO " => Some("luo"), // Luo (Kenya and Tanzania)
+        b"MAD " => Some("mad"), // Madura -> Madurese
+        b"MAG " => Some("mag"), // Magahi
+        b"MAW " => Some("mwr"), // Marwari
+        b"MBO " => Some("mbo"), // Mbo (Cameroon)
+        b"MDR " => Some("mdr"), // Mandar
+        b"MER " => Some("mer"), // Meru
+        b"MEV " => Some("mev"), // Mano
+        b"MKW " => Some("mkw"), // Kituba (Congo)
+        b"MLY " => Some("ms"), // Malay
+        b"MNI " => Some("mni"), // Manipuri
+        b"MNK " => Some("man"), // Maninka -> Mandingo [macrolanguage]
+        b"MOH " => Some("moh"), // Mohawk
+        b"MOL " => Some("ro-MD"), // Moldavian -> Romanian; Moldova, Republic of
+        b"MOS " => Some("mos"), // Mossi
+        b"MUS " => Some("mus"), // Muscogee -> Creek
+        b"MWL " => Some("mwl"), // Mirandese
+        b"MYN " => Some("myn"), // Mayan
+        b"MZN " => Some("mzn"), // Mazanderani
+        b"NAH " => Some("nah"), // Nahuatl
+        b"NAP " => Some("nap"), // Neapolitan
+        b"NDC " => Some("ndc"), // Ndau
+        b"NDS " => Some("nds"), // Low Saxon
+        b"NEW " => Some("new"), // Newari
+        b"NGA " => Some("nga"), // Ngbaka
+        b"NIS " => Some("njz"), // Nisi (India) -> Nyishi
+        b"NIU " => Some("niu"), // Niuean
+        b"NOE " => Some("noe"), // Nimadi
+        b"NOG " => Some("nog"), // Nogai
+        b"NOR " => Some("no"), // Norwegian
+        b"NOV " => Some("nov"), // Novial
+        b"NSO " => Some("nso"), // Northern Sotho
+        b"NYM " => Some("nym"), // Nyamwezi
+        b"NZA " => Some("nza"), // Mbembe Tigon -> Tigon Mbembe
+        b"ONE " => Some("one"), // Oneida
+        b"ONO " => Some("ono"), // Onondaga
+        b"PAG " => Some("pag"), // Pangasinan
+        b"PAM " => Some("pam"), // Pampangan -> Pampanga
+        b"PAU " => Some("pau"), // Palauan
+        b"PCC " => Some("pcc"), // Bouyei
+        b"PCD " => Some("pcd"), // Picard
+        b"PDC " => Some("pdc"), // Pennsylvania German
+        b"PGR " => Some("el-polyton"), // Polytonic Greek -> Modern Greek (1453-); Polytonic Greek
+        b"PHK " => Some("phk"), // Phake
+        b"PMS " => Some("pms"), // Piemontese
+        b"PNB " => Some("pnb"), // Western Panjabi
+        b"PON " => Some("pon"), // Pohnpeian
+        b"PRO " => Some("pro"), // Provençal / Old Provençal -> Old Provençal (to 1500)
+        b"QUH " => Some("quh"), // Quechua (Bolivia) -> South Bolivian Quechua
+        b"QVI " => Some("qvi"), // Quechua (Ecuador) -> Imbabura Highland Quichua
+        b"QWH " => Some("qwh"), // Quechua (Peru) -> Huaylas Ancash Quechua
+        b"RAJ " => Some("raj"), // Rajasthani
+        b"RAR " => Some("rar"), // Rarotongan
+        b"REJ " => Some("rej"), // Rejang
+        b"RHG " => Some("rhg"), // Rohingya
+        b"RIA " => Some("ria"), // Riang (India)
+        b"RIT " => Some("rit"), // Ritarungo -> Ritharrngu
+        b"RKW " => Some("rkw"), // Arakwal
+        b"ROM " => Some("ro"), // Romanian
+        b"ROY " => Some("rom"), // Romany
+        b"RTM " => Some("rtm"), // Rotuman
+        b"RUP " => Some("rup"), // Aromanian
+        b"SAS " => Some("sas"), // Sasak
+        b"SAT " => Some("sat"), // Santali
+        b"SCN " => Some("scn"), // Sicilian
+        b"SCO " => Some("sco"), // Scots
+        b"SEE " => Some("see"), // Seneca
+        b"SEL " => Some("sel"), // Selkup
+        b"SGA " => Some("sga"), // Old Irish
+        b"SGS " => Some("sgs"), // Samogitian
+        b"SHN " => Some("shn"), // Shan
+        b"SID " => Some("sid"), // Sidamo
+        b"SJA " => Some("sja"), // Epena
+        b"SNK " => Some("snk"), // Soninke
+        b"SOP " => Some("sop"), // Songe
+        b"SRB " => Some("sr"), // Serbian
+        b"SRR " => Some("srr"), // Serer
+        b"STQ " => Some("stq"), // Saterland Frisian -> Saterfriesisch
+        b"STR " => Some("str"), // Straits Salish
+        b"SUK " => Some("suk"), // Sukuma
+        b"SVA " => Some("sva"), // Svan
+        b"SXT " => Some("xnj"), // Sutu -> Ngoni (Tanzania)
+        b"SXU " => Some("sxu"), // Upper Saxon
+        b"SYL " => Some("syl"), // Sylheti
+        b"SYR " => Some("syr"), // Syriac
+        b"SYRE" => Some("und-Syre"), // Syriac, Estrangela script-variant (equivalent to ISO 15924 'Syre')
+        b"SYRJ" => Some("und-Syrj"), // Syriac, Western script-variant (equivalent to ISO 15924 'Syrj')
+        b"SYRN" => Some("und-Syrn"), // Syriac, Eastern script-variant (equivalent to ISO 15924 'Syrn')
+        b"SZL " => Some("szl"), // Silesian
+        b"TAB " => Some("tab"), // Tabasaran -> Tabassaran
+        b"TDC " => Some("tdc"), // Emberá-Tadó
+        b"TDD " => Some("tdd"), // Dehong Dai -> Tai Nüa
+        b"TET " => Some("tet"), // Tetum
+        b"THP " => Some("thp"), // Thompson
+        b"TIV " => Some("tiv"), // Tiv
+        b"TJL " => Some("tjl"), // Tai Laing
+        b"TLI " => Some("tli"), // Tlingit
+        b"TLY " => Some("tly"), // Talysh
+        b"TMH " => Some("tmh"), // Tamashek
+        b"TOD " => Some("xwo"), // Todo -> Written Oirat
+        b"TSJ " => Some("tsj"), // Tshangla
+        b"TUM " => Some("tum"), // Tumbuka
+        b"TUS " => Some("tus"), // Tuscarora
+        b"TVL " => Some("tvl"), // Tuvalu
+        b"TYZ " => Some("tyz"), // Tày
+        b"UDI " => Some("udi"), // Udi
+        b"UDM " => Some("udm"), // Udmurt
+        b"UMB " => Some("umb"), // Umbundu
+        b"VEC " => Some("vec"), // Venetian
+        b"WAR " => Some("war"), // Waray-Waray -> Waray (Philippines)
+        b"WBL " => Some("wbl"), // Wakhi
+        b"WCI " => Some("wci"), // Waci Gbe
+        b"WDT " => Some("wdt"), // Wendat
+        b"WTM " => Some("wtm"), // Mewati
+        b"WYN " => Some("wyn"), // Wyandot
+        b"XJB " => Some("xjb"), // Minjangbal -> Minjungbal
+        b"XKF " => Some("xkf"), // Khengkha
+        b"XOG " => Some("xog"), // Soga
+        b"XUB " => Some("xub"), // Bette Kuruma -> Betta Kurumba
+        b"XUJ " => Some("xuj"), // Jennu Kuruma -> Jennu Kurumba
+        b"YAO " => Some("yao"), // Yao
+        b"YAP " => Some("yap"), // Yapese
+        b"YGP " => Some("ygp"), // Gepo
+        b"YNA " => Some("yna"), // Aluo
+        b"YUF " => Some("yuf"), // Havasupai-Walapai-Yavapai
+        b"YWQ " => Some("ywq"), // Wuding-Luquan Yi
+        b"ZEA " => Some("zea"), // Zealandic -> Zeeuws
+        b"ZHH " => Some("zh-HK"), // Chinese, Traditional, Hong Kong SAR -> Chinese [macrolanguage]; Hong Kong
+        b"ZHS " => Some("zh-Hans"), // Chinese, Simplified -> Chinese [macrolanguage]; Han (Simplified variant)
+        b"ZHT " => Some("zh-Hant"), // Chinese, Traditional -> Chinese [macrolanguage]; Han (Traditional variant)
+        b"ZHTM" => Some("zh-Hant-MO"), // Chinese, Traditional, Macao SAR -> Chinese [macrolanguage]; Han (Traditional variant); Macao
+        b"ZZA " => Some("zza"), // Zazaki
+        _ => None,
+    }
+}
@@ -2,6 +2,7 @@ use core::convert::TryFrom;
 
 pub use unicode_ccc::CanonicalCombiningClass;
 // TODO: prefer unic-ucd-normal::CanonicalCombiningClass
+pub use unicode_properties::EmojiStatus as hb_emoji_status_t;
 pub use unicode_properties::GeneralCategory as hb_unicode_general_category_t;
 
 use crate::Script;
@@ -318,13 +319,84 @@ impl GeneralCategoryExt for hb_unicode_general_category_t {
     }
 }
 
+/// Unicode character properties used during shaping.
+///
+/// An equivalent of `hb_unicode_funcs_t`. All methods default to the built-in tables,
+/// so an implementation only has to override the properties it changes.
+/// They apply to every character, ASCII included. Default ignorable characters
+/// are always taken from the built-in tables.
+///
+/// Set with [`UnicodeBuffer::set_unicode_funcs`](crate::UnicodeBuffer::set_unicode_funcs).
+pub trait UnicodeFuncs: Send + Sync {
+    /// Returns the general category of a character.
+    fn general_category(&self, c: char) -> hb_unicode_general_category_t {
+        c.general_category()
+    }
+
+    /// Returns the canonical combining class of a character.
+    fn combining_class(&self, c: char) -> u8 {
+        unicode_ccc::get_canonical_combining_class(c) as u8
+    }
+
+    /// Returns the mirrored form of a character, if any.
+    fn mirroring(&self, c: char) -> Option<char> {
+        c.mirrored()
+    }
+
+    /// Returns the script of a character.
+    fn script(&self, c: char) -> Script {
+        c.script()
+    }
+
+    /// Returns the canonical composition of two characters, if any.
+    fn compose(&self, a: char, b: char) -> Option<char> {
+        compose(a, b)
+    }
+
+    /// Returns the canonical decomposition of a character, if any.
+    ///
+    /// Singleton decompositions return `'\0'` as the second character.
+    fn decompose(&self, ab: char) -> Option<(char, char)> {
+        decompose(ab)
+    }
+}
+
+impl dyn UnicodeFuncs + '_ {
+    pub(crate) fn modified_combining_class(&self, u: char) -> u8 {
+        // Reorder SAKOT to ensure it comes after any tone marks.
+        if u == '\u{1A60}' {
+            return 254;
+        }
+
+        // Reorder PADMA to ensure it comes after any vowel marks.
+        if u == '\u{0FC6}' {
+            return 254;
+        }
+
+        // Reorder TSA -PHRU to reorder before U+0F74
+        if u == '\u{0F39}' {
+            return 127;
+        }
+
+        MODIFIED_COMBINING_CLASS[usize::from(self.combining_class(u))]
+    }
+}
+
+/// The built-in Unicode tables.
+///
+/// Useful to delegate to from a custom [`UnicodeFuncs`] implementation.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct hb_unicode_funcs_default_t;
+
+impl UnicodeFuncs for hb_unicode_funcs_default_t {}
+
 pub trait CharExt {
     fn script(self) -> Script;
     fn general_category(self) -> hb_unicode_general_category_t;
     fn space_fallback(self) -> hb_unicode_funcs_t::space_t;
-    fn modified_combining_class(self) -> u8;
     fn mirrored(self) -> Option<char>;
     fn is_emoji_extended_pictographic(self) -> bool;
+    fn emoji_status(self) -> hb_emoji_status_t;
     fn is_default_ignorable(self) -> bool;
     fn is_variation_selector(self) -> bool;
     fn vertical(self) -> Option<char>;
@@ -525,28 +597,6 @@ impl CharExt for char {
         }
     }
 
-    fn modified_combining_class(self) -> u8 {
-        let u = self;
-
-        // Reorder SAKOT to ensure it comes after any tone marks.
-        if u == '\u{1A60}' {
-            return 254;
-        }
-
-        // Reorder PADMA to ensure it comes after any vowel marks.
-        if u == '\u{0FC6}' {
-            return 254;
-        }
-
-        // Reorder TSA -PHRU to reorder before U+0F74
-        if u == '\u{0F39}' {
-            return 127;
-        }
-
-        let k = unicode_ccc::get_canonical_combining_class(u);
-        MODIFIED_COMBINING_CLASS[k as usize]
-    }
-
     fn mirrored(self) -> Option<char> {
         unicode_bidi_mirroring::get_mirrored(self)
     }
@@ -636,6 +686,11 @@ impl CharExt for char {
         }
     }
 
+    /// Emoji properties from the Unicode emoji data.
+    fn emoji_status(self) -> hb_emoji_status_t {
+        unicode_properties::emoji::UnicodeEmoji::emoji_status(self)
+    }
+
     /// Default_Ignorable codepoints:
     ///
     /// Note: While U+115F, U+1160, U+3164 and U+FFA0 are Default_Ignorable,
@@ -840,6 +895,7 @@ pub fn decompose_hangul(ab: char) -> Option<(char, char)> {
 mod tests {
     #[test]
     fn check_unicode_version() {
+        assert_eq!(unicode_bidi::UNICODE_VERSION, (16, 0, 0));
         assert_eq!(unicode_bidi_mirroring::UNICODE_VERSION, (16, 0, 0));
         assert_eq!(unicode_ccc::UNICODE_VERSION, (16, 0, 0));
         assert_eq!(unicode_properties::UNICODE_VERSION, (16, 0, 0));
@@ -16,10 +16,64 @@ pub use ttf_parser;
 
 pub use hb::buffer::hb_glyph_info_t as GlyphInfo;
 pub use hb::buffer::{GlyphBuffer, GlyphPosition, UnicodeBuffer};
-pub use hb::common::{script, Direction, Feature, Language, Script, Variation};
+pub use hb::common::{script, Direction, Feature, Language, LayoutTags, Script, Variation};
 pub use hb::face::hb_font_t as Face;
+pub use hb::itemize::hb_emoji_presentation_t as EmojiPresentation;
+pub use hb::itemize::hb_emoji_run_t as EmojiRun;
+pub use hb::itemize::hb_emoji_runs_t as EmojiRuns;
+pub use hb::itemize::hb_script_run_t as ScriptRun;
+pub use hb::itemize::hb_script_runs_t as ScriptRuns;
+pub use hb::itemize::{emoji_runs, script_runs};
+pub use hb::justify::hb_justify_options_t as JustifyOptions;
+pub use hb::justify::hb_justify_result_t as Justification;
+pub use hb::justify::shape_justify;
+pub use hb::optical_bounds::hb_optical_bounds_t as OpticalBounds;
+pub use hb::ot_layout::TableIndex;
+pub use hb::ot_shape_normalize::hb_ot_shape_normalization_mode_t as NormalizationMode;
+pub use hb::ot_shape_plan::hb_ot_shape_plan_lookup_t as PlanLookup;
 pub use hb::ot_shape_plan::hb_ot_shape_plan_t as ShapePlan;
-pub use hb::shape::{shape, shape_with_plan};
+pub use hb::ot_var::hb_ot_var_named_instance_t as NamedInstance;
+pub use hb::paragraph::hb_paragraph_run_t as ParagraphRun;
+pub use hb::paragraph::shape_paragraph;
+pub use hb::shape::{shape, shape_cached, shape_with_plan};
+pub use hb::shape_hooks::hb_hook_buffer_t as HookBuffer;
+pub use hb::shape_hooks::ShapeHooks;
+pub use hb::shape_plan_cache::hb_shape_plan_cache_t as ShapePlanCache;
+#[cfg(feature = "wasm-shaper")]
+pub use hb::shape_wasm::hb_wasm_limits_t as WasmLimits;
+pub use hb::unicode::hb_unicode_funcs_default_t as BuiltinUnicodeFuncs;
+pub use hb::unicode::hb_unicode_general_category_t as GeneralCategory;
+pub use hb::unicode::UnicodeFuncs;
+
+/// AAT feature types and selectors.
+///
+/// Use [`Face::aat_feature_types`] to list them.
+pub mod aat {
+    pub use crate::hb::aat_layout::hb_aat_layout_feature_selector_info_t as FeatureSelector;
+    pub use crate::hb::aat_layout::hb_aat_layout_feature_type_info_t as FeatureType;
+}
+
+/// OpenType `MATH` table support.
+///
+/// Use [`Face::math`] to access the table.
+pub mod math {
+    pub use crate::hb::ot_math::hb_ot_math_assembly_glyph_t as AssemblyGlyph;
+    pub use crate::hb::ot_math::hb_ot_math_constant_t as Constant;
+    pub use crate::hb::ot_math::hb_ot_math_glyph_part_t as GlyphPart;
+    pub use crate::hb::ot_math::hb_ot_math_glyph_variant_t as GlyphVariant;
+    pub use crate::hb::ot_math::hb_ot_math_kern_entry_t as KernEntry;
+    pub use crate::hb::ot_math::hb_ot_math_kern_t as KernCorner;
+    pub use crate::hb::ot_math::hb_ot_math_stretched_glyph_t as StretchedGlyph;
+    pub use crate::hb::ot_math::hb_ot_math_t as Math;
+}
+
+/// Conversion between OpenType script and language system tags
+/// and [`Script`]/[`Language`] values.
+pub mod tag {
+    pub use crate::hb::tag::{
+        tag_to_language, tag_to_script, tags_from_script_and_language, tags_to_script_and_language,
+    };
+}
 
 bitflags::bitflags! {
     /// Flags for buffers.
@@ -40,7 +94,7 @@ bitflags::bitflags! {
         /// Indicates that the `UNSAFE_TO_CONCAT` glyph-flag should be produced by the shaper. By default it will not be produced since it incurs a cost.
         const PRODUCE_UNSAFE_TO_CONCAT      = 0x00000040;
         /// Indicates that the `SAFE_TO_INSERT_TATWEEL` glyph-flag should be produced by the shaper. By default it will not be produced.
-        const PRODUCE_SAFE_TO_INSERT_TATWEEL      = 0x00000040;
+        const PRODUCE_SAFE_TO_INSERT_TATWEEL      = 0x00000080;
         /// All currently defined flags
         const DEFINED = 0x000000FF;
     }
@@ -0,0 +1,448 @@
+// tests for AAT features not covered by the generated tests
+
+use crate::shape;
+
+// `TestMORXTwentynine-Kashida.ttf` is `TestMORXTwentynine.ttf`
+// with the KashidaLike flags set on all insertions.
+
+#[test]
+fn morx_kashida_like_marked_insert_before() {
+    let text = "\u{0050}\u{0051}\u{0052}\u{004D}\u{004D}\u{0058}\u{0058}\u{004D}\u{004D}\u{0059}\
+                \u{0059}\u{0041}\u{005A}\u{005A}";
+
+    // Split-vowel-like glyphs belong to the glyph they were inserted before.
+    assert_eq!(
+        shape(
+            "tests/fonts/text-rendering-tests/TestMORXTwentynine.ttf",
+            text,
+            "--no-positions --show-flags",
+        ),
+        "P=0|Q=1|R=2|M=3|M=4|X=5|X=6|M=7|\
+         I=8|N=8|S=8|M=8|\
+         Y=9#1|Y=10#1|A=11#1|Z=12|Z=13"
+    );
+
+    // Kashida-like glyphs belong to the preceding glyph.
+    assert_eq!(
+        shape(
+            "tests/fonts/rb_custom/TestMORXTwentynine-Kashida.ttf",
+            text,
+            "--no-positions --show-flags",
+        ),
+        "P=0|Q=1|R=2|M=3|M=4|X=5|X=6|M=7|\
+         I=7|N=7|S=7|M=8#1|\
+         Y=9#1|Y=10#1|A=11#1|Z=12|Z=13"
+    );
+}
+
+#[test]
+fn morx_kashida_like_marked_insert_after() {
+    assert_eq!(
+        shape(
+            "tests/fonts/rb_custom/TestMORXTwentynine-Kashida.ttf",
+            "\u{0050}\u{0051}\u{0052}\u{004D}\u{004D}\u{0058}\u{0058}\u{004D}\u{004D}\u{0059}\
+             \u{0059}\u{0042}\u{005A}\u{005A}",
+            "--no-positions --show-flags",
+        ),
+        "P=0|Q=1|R=2|M=3|M=4|X=5|X=6|M=7|\
+         M=8|I=8|N=8|S=8|\
+         Y=9#1|Y=10#1|B=11#1|Z=12|Z=13"
+    );
+}
+
+#[test]
+fn morx_kashida_like_current_insert_before() {
+    let text = "\u{0050}\u{0051}\u{0052}\u{004D}\u{004D}\u{0058}\u{0058}\u{004D}\u{004D}\u{0059}\
+                \u{0059}\u{0043}\u{005A}\u{005A}";
+
+    assert_eq!(
+        shape(
+            "tests/fonts/text-rendering-tests/TestMORXTwentynine.ttf",
+            text,
+            "--no-positions --show-flags",
+        ),
+        "P=0|Q=1|R=2|M=3|M=4|X=5|X=6|M=7|M=8|Y=9|Y=10|\
+         I=11#1|N=11#1|S=11#1|C=11#1|\
+         Z=12|Z=13"
+    );
+
+    assert_eq!(
+        shape(
+            "tests/fonts/rb_custom/TestMORXTwentynine-Kashida.ttf",
+            text,
+            "--no-positions --show-flags",
+        ),
+        "P=0|Q=1|R=2|M=3|M=4|X=5|X=6|M=7|M=8|Y=9|\
+         Y=10#1|I=10#1|N=10#1|S=10#1|C=11#1|\
+         Z=12|Z=13"
+    );
+}
+
+#[test]
+fn morx_kashida_like_current_insert_after() {
+    assert_eq!(
+        shape(
+            "tests/fonts/rb_custom/TestMORXTwentynine-Kashida.ttf",
+            "\u{0050}\u{0051}\u{0052}\u{004D}\u{004D}\u{0058}\u{0058}\u{004D}\u{004D}\u{0059}\
+             \u{0059}\u{0044}\u{005A}\u{005A}",
+            "--no-positions --show-flags",
+        ),
+        "P=0|Q=1|R=2|M=3|M=4|X=5|X=6|M=7|M=8|Y=9|Y=10|\
+         D=11#1|I=11#1|N=11#1|S=11#1|\
+         Z=12|Z=13"
+    );
+}
+
+// `TestGVAROne-VariableKern.ttf` is `TestGVAROne.ttf` with an Apple `kern` table
+// containing a -100 pair and a -200 variable pair for the `wght=700` shared tuple.
+
+#[test]
+fn kern_variable_subtable_default() {
+    assert_eq!(
+        shape(
+            "tests/fonts/rb_custom/TestGVAROne-VariableKern.ttf",
+            "\u{5F4C}\u{6953}",
+            "",
+        ),
+        "gid2=0+950|\
+         gid3=1@-50,0+950"
+    );
+}
+
+#[test]
+fn kern_variable_subtable_outside_of_tuple() {
+    assert_eq!(
+        shape(
+            "tests/fonts/rb_custom/TestGVAROne-VariableKern.ttf",
+            "\u{5F4C}\u{6953}",
+            "--variations=wght=300",
+        ),
+        "gid2=0+950|\
+         gid3=1@-50,0+950"
+    );
+}
+
+#[test]
+fn kern_variable_subtable_intermediate() {
+    assert_eq!(
+        shape(
+            "tests/fonts/rb_custom/TestGVAROne-VariableKern.ttf",
+            "\u{5F4C}\u{6953}",
+            "--variations=wght=550",
+        ),
+        "gid2=0+900|\
+         gid3=1@-100,0+900"
+    );
+}
+
+#[test]
+fn kern_variable_subtable_peak() {
+    assert_eq!(
+        shape(
+            "tests/fonts/rb_custom/TestGVAROne-VariableKern.ttf",
+            "\u{5F4C}\u{6953}",
+            "--variations=wght=700",
+        ),
+        "gid2=0+850|\
+         gid3=1@-150,0+850"
+    );
+}
+
+// `TestMORX*-Mort.ttf` are the corresponding `TestMORX*.ttf` fonts
+// with `morx` transcoded to a legacy `mort` table.
+// Expectations are the same as for the original fonts.
+
+#[test]
+fn mort_noncontextual() {
+    assert_eq!(
+        shape(
+            "tests/fonts/rb_custom/TestMORXOne-Mort.ttf",
+            "\u{0041}\u{0042}\u{0043}",
+            "--ned --remove-default-ignorables",
+        ),
+        "A.alt|\
+         B@1000,0|\
+         C.alt@2000,0"
+    );
+}
+
+#[test]
+fn mort_rearrangement() {
+    assert_eq!(
+        shape(
+            "tests/fonts/rb_custom/TestMORXTwo-Mort.ttf",
+            "\u{004F}\u{004F}\u{004F}\u{0041}\u{0042}\u{0058}\u{0059}\u{005A}\u{0043}\u{0044}\
+             \u{004F}\u{004F}\u{004F}\u{24FF}",
+            "--ned --remove-default-ignorables",
+        ),
+        "O|\
+         O@418,0|\
+         O@836,0|\
+         A@1254,0|\
+         B@2084,0|\
+         X@2914,0|\
+         Y@3744,0|\
+         Z@4574,0|\
+         C@5404,0|\
+         D@6234,0|\
+         O@7064,0|\
+         O@7482,0|\
+         O@7900,0|\
+         zero@8318,0"
+    );
+}
+
+#[test]
+fn mort_contextual() {
+    assert_eq!(
+        shape(
+            "tests/fonts/rb_custom/TestMORXTwenty-Mort.ttf",
+            "\u{0041}\u{0042}\u{0043}\u{0044}\u{0045}",
+            "--ned --remove-default-ignorables",
+        ),
+        "A|\
+         B@639,0|\
+         C.alt@1265,0|\
+         D@2265,0|\
+         E.alt1@2939,0"
+    );
+}
+
+#[test]
+fn mort_ligature() {
+    assert_eq!(
+        shape(
+            "tests/fonts/rb_custom/TestMORXFourtyone-Mort.ttf",
+            "\u{0061}\u{0062}\u{0063}\u{0063}",
+            "",
+        ),
+        "a=0+561|\
+         b_c=1+1070|\
+         c=3+480"
+    );
+}
+
+#[test]
+fn mort_insertion() {
+    assert_eq!(
+        shape(
+            "tests/fonts/rb_custom/TestMORXThirtyone-Mort.ttf",
+            "\u{0058}\u{0058}\u{0041}\u{0059}\u{0059}\u{0041}\u{005A}\u{005A}",
+            "--ned --remove-default-ignorables",
+        ),
+        "I|\
+         N@830,0|\
+         I@1660,0|\
+         N@2490,0|\
+         S@3320,0|\
+         S@4150,0|\
+         X@4980,0|\
+         X@5344,0|\
+         A@5708,0|\
+         Y@6538,0|\
+         Y@6870,0|\
+         A@7202,0|\
+         Z@8032,0|\
+         Z@8354,0"
+    );
+}
+
+// `TestMORXOne-Feat.ttf` is `TestMORXOne.ttf` with a `feat` table
+// listing a non-exclusive type 0 and an exclusive type 4 with the second selector as default.
+
+#[test]
+fn feat_feature_types() {
+    use rustybuzz::aat::{FeatureSelector, FeatureType};
+
+    let data = std::fs::read("tests/fonts/rb_custom/TestMORXOne-Feat.ttf").unwrap();
+    let face = rustybuzz::Face::from_slice(&data, 0).unwrap();
+
+    let types = face.aat_feature_types();
+    assert_eq!(
+        types,
+        vec![
+            FeatureType {
+                kind: 0,
+                name_id: 256,
+                is_exclusive: false,
+                default_index: None,
+                selectors: vec![
+                    FeatureSelector {
+                        name_id: 258,
+                        enable: 0,
+                        disable: 1,
+                    },
+                    FeatureSelector {
+                        name_id: 259,
+                        enable: 1,
+                        disable: 2,
+                    },
+                ],
+            },
+            FeatureType {
+                kind: 4,
+                name_id: 257,
+                is_exclusive: true,
+                default_index: Some(1),
+                selectors: vec![
+                    FeatureSelector {
+                        name_id: 260,
+                        enable: 0,
+                        disable: 1,
+                    },
+                    FeatureSelector {
+                        name_id: 261,
+                        enable: 1,
+                        disable: 1,
+                    },
+                ],
+            },
+        ]
+    );
+
+    assert_eq!(face.aat_feature_type(4).as_ref(), types.get(1));
+    assert_eq!(face.aat_feature_type(1), None);
+}
+
+fn shape_with_features(font_path: &str, text: &str, features: &[rustybuzz::Feature]) -> String {
+    let data = std::fs::read(font_path).unwrap();
+    let face = rustybuzz::Face::from_slice(&data, 0).unwrap();
+    let mut buffer = rustybuzz::UnicodeBuffer::new();
+    buffer.push_str(text);
+    let glyphs = rustybuzz::shape(&face, features, buffer);
+    glyphs.serialize(&face, rustybuzz::SerializeFlags::NO_ADVANCES)
+}
+
+#[test]
+fn morx_aat_feature_request() {
+    let font = "tests/fonts/text-rendering-tests/TestMORXOne.ttf";
+
+    // Type 0 selector 1 is "all typographic features off".
+    assert_eq!(
+        shape_with_features(font, "ABC", &[rustybuzz::Feature::aat(0, 1, ..)]),
+        "A=0|B=1@1000,0|C=2@2000,0"
+    );
+
+    assert_eq!(rustybuzz::Feature::aat(0, 1, ..).aat_selector, Some((0, 1)));
+
+    // Feature strings only name OpenType tags, whatever the tag and value.
+    let parsed = "AAT=65537".parse::<rustybuzz::Feature>().unwrap();
+    assert_eq!(parsed.aat_selector, None);
+    assert_eq!(
+        shape_with_features(font, "ABC", &[parsed]),
+        shape_with_features(font, "ABC", &[])
+    );
+}
+
+// `TestMORXOne-Opbd*.ttf` are `TestMORXOne.ttf` with `morx` replaced by an `opbd` table
+// for A and C, in the distance and in the control point format.
+// `TestMORXOne-LfbdRtbd.ttf` has a `GPOS` table with `lfbd` for A and `rtbd` for C instead.
+// `TestMORXOne-LfbdRtbdScripts.ttf` has an `lfbd` moving A by -100 under `DFLT`,
+// one moving it by -200 under `latn`, and no `lfbd` under `latn`/`TRK `.
+
+fn optical_bounds(font: &str, text: &str) -> rustybuzz::OpticalBounds {
+    let data = std::fs::read(font).unwrap();
+    let face = rustybuzz::Face::from_slice(&data, 0).unwrap();
+    let mut buffer = rustybuzz::UnicodeBuffer::new();
+    buffer.push_str(text);
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    face.optical_bounds(&glyphs)
+}
+
+#[test]
+fn opbd_distance() {
+    let font = "tests/fonts/rb_custom/TestMORXOne-OpbdDistance.ttf";
+
+    // A: left 50, right -30. C: left -20, right 40.
+    assert_eq!(
+        optical_bounds(font, "ABC"),
+        rustybuzz::OpticalBounds {
+            left: 50,
+            right: -40
+        }
+    );
+    assert_eq!(
+        optical_bounds(font, "CBA"),
+        rustybuzz::OpticalBounds {
+            left: -20,
+            right: 30
+        }
+    );
+    assert_eq!(
+        optical_bounds(font, "B"),
+        rustybuzz::OpticalBounds::default()
+    );
+}
+
+#[test]
+fn opbd_control_points() {
+    let font = "tests/fonts/rb_custom/TestMORXOne-OpbdPoints.ttf";
+
+    // A: left point 0 at 622, right point 2 at 946.
+    // C: no left point, right point 1 at 174.
+    assert_eq!(
+        optical_bounds(font, "ABC"),
+        rustybuzz::OpticalBounds {
+            left: 622,
+            right: 826
+        }
+    );
+    assert_eq!(
+        optical_bounds(font, "CBA"),
+        rustybuzz::OpticalBounds { left: 0, right: 54 }
+    );
+}
+
+#[test]
+fn lfbd_rtbd_optical_bounds() {
+    let font = "tests/fonts/rb_custom/TestMORXOne-LfbdRtbd.ttf";
+
+    // lfbd moves A by -100, rtbd shrinks C by 60.
+    assert_eq!(
+        optical_bounds(font, "ABC"),
+        rustybuzz::OpticalBounds {
+            left: 100,
+            right: 60
+        }
+    );
+    assert_eq!(
+        optical_bounds(font, "CBA"),
+        rustybuzz::OpticalBounds::default()
+    );
+}
+
+#[test]
+fn lfbd_rtbd_script_and_language() {
+    let data = std::fs::read("tests/fonts/rb_custom/TestMORXOne-LfbdRtbdScripts.ttf").unwrap();
+    let face = rustybuzz::Face::from_slice(&data, 0).unwrap();
+    let bounds = |script: Option<rustybuzz::Script>, language: Option<&str>| {
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str("ABC");
+        if let Some(script) = script {
+            buffer.set_script(script);
+        }
+        if let Some(language) = language {
+            buffer.set_language(language.parse().unwrap());
+        }
+        face.optical_bounds(&rustybuzz::shape(&face, &[], buffer))
+    };
+
+    assert_eq!(
+        bounds(None, None),
+        rustybuzz::OpticalBounds {
+            left: 200,
+            right: 60
+        }
+    );
+    assert_eq!(
+        bounds(None, Some("tr")),
+        rustybuzz::OpticalBounds { left: 0, right: 60 }
+    );
+    assert_eq!(
+        bounds(Some(rustybuzz::script::CYRILLIC), None),
+        rustybuzz::OpticalBounds {
+            left: 100,
+            right: 60
+        }
+    );
+}
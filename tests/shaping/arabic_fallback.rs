@@ -0,0 +1,52 @@
+// tests for the Arabic fallback shaping, using a font that only
+// has presentation forms and no Arabic GSUB features
+
+use crate::shape;
+
+const FONT: &str = "tests/fonts/text-rendering-tests/FDArrayTest257.otf";
+
+#[test]
+fn arabic_fallback_positional_forms() {
+    assert_eq!(
+        shape(FONT, "\u{0628}\u{0628}\u{0628}", "--no-positions"),
+        "gid145=2|gid147=1|gid146=0"
+    );
+    assert_eq!(shape(FONT, "\u{0628}", "--no-positions"), "gid144=0");
+    // ZWJ makes the preceding letter join.
+    assert_eq!(
+        shape(FONT, "\u{0628}\u{200D}", "--no-positions"),
+        "gid33=0|gid146=0"
+    );
+}
+
+#[test]
+fn arabic_fallback_lam_alef() {
+    assert_eq!(
+        shape(FONT, "\u{0644}\u{0627}", "--no-positions"),
+        "gid252=0"
+    );
+    assert_eq!(
+        shape(FONT, "\u{0644}\u{0625}", "--no-positions"),
+        "gid250=0"
+    );
+    assert_eq!(
+        shape(FONT, "\u{0633}\u{0644}\u{0627}", "--no-positions"),
+        "gid253=1|gid180=0"
+    );
+}
+
+#[test]
+fn arabic_fallback_lam_alef_with_mark() {
+    assert_eq!(
+        shape(FONT, "\u{0644}\u{064E}\u{0627}", "--no-positions"),
+        "gid79=0|gid252=0"
+    );
+}
+
+#[test]
+fn arabic_fallback_disabled_rlig() {
+    assert_eq!(
+        shape(FONT, "\u{0644}\u{0627}", "--no-positions --features=-rlig"),
+        "gid143=1|gid224=0"
+    );
+}
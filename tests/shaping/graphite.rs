@@ -0,0 +1,137 @@
+// tests for the Graphite shaper
+
+use crate::shape;
+
+// `TestGraphiteOne.ttf` has a substitution pass that maps 'a' to 'x' when the `alts`
+// feature is on, ligates 'b' 'c' into 'y', inserts a 'z' after 'd' and deletes 'e'.
+// A positioning pass kerns 'x' 'y', attaches U+0301 to 'f' and shifts 'z'.
+// The `xyz` language turns `alts` on by default.
+// Expectations match HarfBuzz with libgraphite2.
+
+const FONT: &str = "tests/fonts/rb_custom/TestGraphiteOne.ttf";
+
+#[test]
+fn substitution() {
+    assert_eq!(
+        shape(FONT, "abcdef", "--no-glyph-names"),
+        "68=0+582|\
+         92=1+531|\
+         71=3+1144|\
+         93=3@-495,30+0|\
+         73=5+364"
+    );
+}
+
+#[test]
+fn ligature() {
+    assert_eq!(shape(FONT, "bc", "--no-glyph-names"), "92=0+531");
+}
+
+#[test]
+fn insertion() {
+    assert_eq!(
+        shape(FONT, "dd", "--no-glyph-names"),
+        "71=0+2288|\
+         93=0@-1639,30+0|\
+         71=0@-1144,0+0|\
+         93=0@-495,30+0"
+    );
+}
+
+#[test]
+fn deletion() {
+    assert_eq!(shape(FONT, "e", "--no-glyph-names"), "");
+}
+
+#[test]
+fn attachment() {
+    assert_eq!(
+        shape(FONT, "f\u{0301}xy", "--no-glyph-names"),
+        "73=0+364|\
+         311=1@0,600+2|\
+         91=2+492|\
+         92=3+531"
+    );
+}
+
+#[test]
+fn feature() {
+    assert_eq!(
+        shape(FONT, "axyb", "--no-glyph-names --features=alts"),
+        "91=0+592|\
+         91=1+492|\
+         92=2+531|\
+         69=3+629"
+    );
+}
+
+#[test]
+fn language_defaults() {
+    assert_eq!(
+        shape(FONT, "axyb", "--no-glyph-names --language=xyz"),
+        "91=0+592|\
+         91=1+492|\
+         92=2+531|\
+         69=3+629"
+    );
+    assert_eq!(
+        shape(
+            FONT,
+            "axyb",
+            "--no-glyph-names --language=xyz --features=-alts"
+        ),
+        "68=0+582|\
+         91=1+492|\
+         92=2+531|\
+         69=3+629"
+    );
+}
+
+#[test]
+fn right_to_left() {
+    assert_eq!(
+        shape(
+            FONT,
+            "abcdf",
+            "--no-glyph-names --direction=rtl --script=Hebr"
+        ),
+        "73=4+1013|\
+         93=3@0,30+495|\
+         71=3@-1144,0+0|\
+         70=2+529|\
+         69=1+629|\
+         68=0+582"
+    );
+}
+
+#[test]
+fn reversed_clusters() {
+    // Latin text shaped right-to-left is passed to Graphite in reverse order.
+    assert_eq!(
+        shape(FONT, "abd", "--no-glyph-names --direction=rtl"),
+        "71=2+1773|\
+         93=2@-1124,30+0|\
+         69=2@-629,0+0|\
+         68=0+582"
+    );
+}
+
+#[test]
+fn reuses_face() {
+    // The parsed tables are cached on the face.
+    let data = std::fs::read(FONT).unwrap();
+    let face = rustybuzz::Face::from_slice(&data, 0).unwrap();
+    let shape = |text: &str| {
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(text);
+        rustybuzz::shape(&face, &[], buffer)
+            .glyph_infos()
+            .iter()
+            .map(|info| info.glyph_id)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(shape("abcdef"), [68, 92, 71, 93, 73]);
+    assert_eq!(shape("bc"), [92]);
+    assert_eq!(shape("abcdef"), [68, 92, 71, 93, 73]);
+}
@@ -0,0 +1,73 @@
+// tests for shape_justify
+
+use rustybuzz::ttf_parser::Tag;
+use rustybuzz::{JustifyOptions, ShapePlan, UnicodeBuffer};
+
+fn justify(
+    face: &rustybuzz::Face,
+    text: &str,
+    target_advance: i32,
+    options: &JustifyOptions,
+) -> rustybuzz::Justification {
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    let plan = ShapePlan::new(face, buffer.direction(), Some(buffer.script()), None, &[]);
+    rustybuzz::shape_justify(face, &plan, buffer, target_advance, options)
+}
+
+#[test]
+fn justify_with_axis() {
+    let data = include_bytes!("../fonts/rb_custom/Linefont.ttf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let wdth = Tag::from_bytes(b"wdth");
+    let options = JustifyOptions {
+        axis: Some(wdth),
+        ..JustifyOptions::default()
+    };
+
+    let result = justify(&face, "aaaa", 500, &options);
+    assert_eq!(result.advance, 500);
+    assert_eq!(result.variation.unwrap().tag, wdth);
+    assert_eq!(result.variation.unwrap().value, 125.0);
+
+    // Never exceeds the target when possible.
+    let result = justify(&face, "aaaa", 333, &options);
+    assert!(result.advance <= 333 && result.advance >= 330);
+
+    // Limited by the axis range.
+    let result = justify(&face, "aaaa", 2000, &options);
+    assert_eq!(result.advance, 800);
+    assert_eq!(result.variation.unwrap().value, 200.0);
+
+    let result = justify(&face, "aaaa", 0, &options);
+    assert_eq!(result.advance, 100);
+    assert_eq!(result.variation.unwrap().value, 25.0);
+}
+
+#[test]
+fn justify_with_tatweel() {
+    let data = include_bytes!("../fonts/text-rendering-tests/FDArrayTest257.otf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let options = JustifyOptions {
+        insert_tatweel: true,
+        ..JustifyOptions::default()
+    };
+
+    let result = justify(&face, "ببب", 3000, &options);
+    assert_eq!(result.advance, 3000);
+    assert_eq!(result.tatweel_count, 0);
+
+    let result = justify(&face, "ببب", 5000, &options);
+    assert_eq!(result.advance, 5000);
+    assert_eq!(result.tatweel_count, 2);
+    assert_eq!(
+        result.glyphs.serialize(&face, Default::default()),
+        "gid145=4+1000|gid65=4+1000|gid147=2+1000|gid65=2+1000|gid146=0+1000"
+    );
+
+    let result = justify(&face, "ببب", 4500, &options);
+    assert_eq!(result.advance, 4000);
+    assert_eq!(result.tatweel_count, 1);
+    assert!(result.variation.is_none());
+}
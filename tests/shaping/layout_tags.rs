@@ -0,0 +1,108 @@
+// tests for explicit OpenType script and language system tags
+
+use rustybuzz::ttf_parser::Tag;
+use rustybuzz::{
+    Direction, Language, LayoutTags, SerializeFlags, ShapePlan, ShapePlanCache, UnicodeBuffer,
+};
+
+const FONT: &[u8] =
+    include_bytes!("../fonts/in-house/6991b13ce889466be6de3f66e891de2bc0f117ee.ttf");
+
+fn tags(script: Option<&[u8; 4]>, language: Option<&[u8; 4]>) -> LayoutTags {
+    LayoutTags {
+        script: script.map(Tag::from_bytes),
+        language: language.map(Tag::from_bytes),
+    }
+}
+
+fn shape_tagged(language: Option<&str>, layout_tags: LayoutTags) -> String {
+    let face = rustybuzz::Face::from_slice(FONT, 0).unwrap();
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str("J");
+    if let Some(language) = language {
+        buffer.set_language(language.parse().unwrap());
+    }
+    buffer.set_layout_tags(layout_tags);
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    glyphs.serialize(&face, SerializeFlags::NO_POSITIONS)
+}
+
+#[test]
+fn layout_tags_language() {
+    assert_eq!(shape_tagged(None, tags(None, None)), "gid2=0");
+    assert_eq!(shape_tagged(None, tags(None, Some(b"ZHS "))), "gid4=0");
+    assert_eq!(shape_tagged(None, tags(None, Some(b"ZHT "))), "gid5=0");
+    assert_eq!(shape_tagged(None, tags(None, Some(b"ZHH "))), "gid6=0");
+}
+
+#[test]
+fn layout_tags_take_precedence() {
+    assert_eq!(shape_tagged(Some("zh-cn"), tags(None, None)), "gid4=0");
+    assert_eq!(
+        shape_tagged(Some("zh-cn"), tags(None, Some(b"ZHH "))),
+        "gid6=0"
+    );
+    assert_eq!(
+        shape_tagged(Some("x-hbot-zht"), tags(None, Some(b"ZHH "))),
+        "gid6=0"
+    );
+}
+
+#[test]
+fn layout_tags_script() {
+    assert_eq!(
+        shape_tagged(None, tags(Some(b"hani"), Some(b"ZHT "))),
+        "gid5=0"
+    );
+    // `hang` has no ZHT language system.
+    assert_eq!(
+        shape_tagged(None, tags(Some(b"hang"), Some(b"ZHT "))),
+        "gid2=0"
+    );
+    // Unknown languages use the default language system of the script.
+    assert_eq!(
+        shape_tagged(None, tags(Some(b"latn"), Some(b"XXX "))),
+        "gid2=0"
+    );
+}
+
+#[test]
+fn layout_tags_plan() {
+    let face = rustybuzz::Face::from_slice(FONT, 0).unwrap();
+    let language: Language = "zh-cn".parse().unwrap();
+    let plan = ShapePlan::with_layout_tags(
+        &face,
+        Direction::LeftToRight,
+        Some(rustybuzz::script::LATIN),
+        Some(&language),
+        tags(None, Some(b"ZHT ")),
+        &[],
+    );
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str("J");
+    buffer.set_language(language.clone());
+    let glyphs = rustybuzz::shape_with_plan(&face, &plan, buffer);
+    assert_eq!(
+        glyphs.serialize(&face, SerializeFlags::NO_POSITIONS),
+        "gid5=0"
+    );
+
+    // Plans with different tags are cached separately.
+    let cache = ShapePlanCache::default();
+    for (layout_tags, expected) in [
+        (tags(None, Some(b"ZHT ")), "gid5=0"),
+        (tags(None, None), "gid4=0"),
+    ] {
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str("J");
+        buffer.set_language(language.clone());
+        buffer.set_layout_tags(layout_tags);
+        let glyphs = rustybuzz::shape_cached(&face, &cache, &[], buffer);
+        assert_eq!(
+            glyphs.serialize(&face, SerializeFlags::NO_POSITIONS),
+            expected
+        );
+    }
+    assert_eq!(cache.len(), 2);
+}
@@ -1,8 +1,20 @@
+mod aat;
 mod aots;
+mod arabic_fallback;
 mod custom;
+#[cfg(feature = "graphite")]
+mod graphite;
 mod in_house;
+mod justify;
+mod layout_tags;
 mod macos;
+mod math;
+mod paragraph;
+mod shape_plan;
+mod spacing;
 mod text_rendering_tests;
+mod unicode_funcs;
+mod variations;
 #[cfg(feature = "wasm-shaper")]
 mod wasm;
 
@@ -0,0 +1,244 @@
+// tests for the MATH table API
+
+use rustybuzz::math::{
+    AssemblyGlyph, Constant, GlyphPart, GlyphVariant, KernCorner, KernEntry, StretchedGlyph,
+};
+use rustybuzz::ttf_parser::GlyphId;
+use rustybuzz::Direction;
+
+#[test]
+fn constants() {
+    let data = include_bytes!("../fonts/in-house/8d9c4b193808b8bde94389ba7831c1fc6f9e794e.ttf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let math = face.math().unwrap();
+
+    assert_eq!(math.constant(Constant::AxisHeight), 642);
+    assert_eq!(math.min_connector_overlap(), 40);
+}
+
+#[test]
+fn stretch_without_construction() {
+    let data = include_bytes!("../fonts/in-house/8d9c4b193808b8bde94389ba7831c1fc6f9e794e.ttf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let math = face.math().unwrap();
+
+    match math.stretch_glyph(GlyphId(1), Direction::LeftToRight, 10000) {
+        StretchedGlyph::Variant(variant) => assert_eq!(variant.glyph, GlyphId(1)),
+        StretchedGlyph::Assembly { .. } => panic!("unexpected assembly"),
+    }
+}
+
+#[test]
+fn no_math_table() {
+    let data = include_bytes!("../fonts/rb_custom/OpenSans.subset1.ttf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    assert!(face.math().is_none());
+}
+
+// `TestMATH.ttf` is `TestMORXOne.ttf` with `morx` replaced by a `MATH` table.
+// Glyph 1 has vertical variants 1, 2 and 3 (100, 200 and 300 units) and an assembly
+// of glyphs 4, 5 (extender) and 6, each 100 units with 50 unit connectors.
+// Glyph 2 has horizontal variants 2 and 3 (150 and 250 units) and no assembly.
+// The minimal connector overlap is 10. Expected values come from harfbuzz's `hb_ot_math_*`.
+
+fn test_math_face() -> rustybuzz::Face<'static> {
+    let data = include_bytes!("../fonts/rb_custom/TestMATH.ttf");
+    rustybuzz::Face::from_slice(data, 0).unwrap()
+}
+
+#[test]
+fn italics_correction() {
+    let face = test_math_face();
+    let math = face.math().unwrap();
+
+    assert_eq!(math.italics_correction(GlyphId(0)), 0);
+    assert_eq!(math.italics_correction(GlyphId(1)), 20);
+    assert_eq!(math.italics_correction(GlyphId(3)), -5);
+}
+
+#[test]
+fn kerning() {
+    let face = test_math_face();
+    let math = face.math().unwrap();
+
+    let kerning = |corner, heights: &[i32]| {
+        heights
+            .iter()
+            .map(|&h| math.kerning(GlyphId(1), corner, h))
+            .collect::<Vec<_>>()
+    };
+
+    let heights = [-10, 50, 99, 100, 101, 150, 200, 201, 1000];
+    assert_eq!(
+        kerning(KernCorner::TopRight, &heights),
+        vec![10, 10, 10, 10, 20, 20, 20, 30, 30]
+    );
+    assert_eq!(
+        kerning(KernCorner::BottomLeft, &heights),
+        vec![-5, -5, 5, 5, 5, 5, 5, 5, 5]
+    );
+    assert_eq!(kerning(KernCorner::TopLeft, &heights), vec![0; 9]);
+    assert_eq!(math.kerning(GlyphId(2), KernCorner::TopRight, 100), 0);
+
+    assert_eq!(
+        math.kernings(GlyphId(1), KernCorner::TopRight),
+        vec![
+            KernEntry {
+                max_correction_height: 100,
+                kern_value: 10
+            },
+            KernEntry {
+                max_correction_height: 200,
+                kern_value: 20
+            },
+            KernEntry {
+                max_correction_height: i32::MAX,
+                kern_value: 30
+            },
+        ]
+    );
+    assert!(math
+        .kernings(GlyphId(1), KernCorner::BottomRight)
+        .is_empty());
+}
+
+#[test]
+fn glyph_constructions() {
+    let face = test_math_face();
+    let math = face.math().unwrap();
+
+    let variant = |glyph, advance| GlyphVariant {
+        glyph: GlyphId(glyph),
+        advance,
+    };
+    let part = |glyph, start, end, is_extender| GlyphPart {
+        glyph: GlyphId(glyph),
+        start_connector_length: start,
+        end_connector_length: end,
+        full_advance: 100,
+        is_extender,
+    };
+
+    assert_eq!(
+        math.glyph_variants(GlyphId(1), Direction::TopToBottom),
+        vec![variant(1, 100), variant(2, 200), variant(3, 300)]
+    );
+    assert_eq!(
+        math.glyph_assembly(GlyphId(1), Direction::TopToBottom),
+        Some((
+            vec![
+                part(4, 0, 50, false),
+                part(5, 50, 50, true),
+                part(6, 50, 0, false)
+            ],
+            7
+        ))
+    );
+    assert!(math
+        .glyph_variants(GlyphId(1), Direction::LeftToRight)
+        .is_empty());
+    assert_eq!(
+        math.glyph_variants(GlyphId(2), Direction::LeftToRight),
+        vec![variant(2, 150), variant(3, 250)]
+    );
+    assert_eq!(
+        math.glyph_assembly(GlyphId(2), Direction::LeftToRight),
+        None
+    );
+}
+
+#[test]
+fn stretch_to_variant() {
+    let face = test_math_face();
+    let math = face.math().unwrap();
+
+    let stretch = |glyph, direction, size| match math.stretch_glyph(GlyphId(glyph), direction, size)
+    {
+        StretchedGlyph::Variant(variant) => (variant.glyph.0, variant.advance),
+        StretchedGlyph::Assembly { .. } => panic!("unexpected assembly"),
+    };
+
+    // The smallest variant that is large enough.
+    assert_eq!(stretch(1, Direction::TopToBottom, 50), (1, 100));
+    assert_eq!(stretch(1, Direction::TopToBottom, 150), (2, 200));
+    assert_eq!(stretch(1, Direction::TopToBottom, 300), (3, 300));
+    // No assembly, so the largest variant.
+    assert_eq!(stretch(2, Direction::LeftToRight, 1000), (3, 250));
+}
+
+#[test]
+fn stretch_to_assembly() {
+    let face = test_math_face();
+    let math = face.math().unwrap();
+
+    let placed = |glyph, offset, overlap| AssemblyGlyph {
+        glyph: GlyphId(glyph),
+        offset,
+        advance: 100,
+        overlap,
+    };
+
+    // Two extenders give 370 units with minimal overlaps.
+    // The 68 units of excess are spread over the three connections.
+    assert_eq!(
+        math.stretch_glyph(GlyphId(1), Direction::TopToBottom, 302),
+        StretchedGlyph::Assembly {
+            parts: vec![
+                placed(4, 0, 0),
+                placed(5, 67, 33),
+                placed(5, 134, 33),
+                placed(6, 202, 32),
+            ],
+            size: 302,
+            italics_correction: 7,
+        }
+    );
+
+    // Here the 69 units of excess split evenly.
+    assert_eq!(
+        math.stretch_glyph(GlyphId(1), Direction::TopToBottom, 301),
+        StretchedGlyph::Assembly {
+            parts: vec![
+                placed(4, 0, 0),
+                placed(5, 67, 33),
+                placed(5, 134, 33),
+                placed(6, 201, 33),
+            ],
+            size: 301,
+            italics_correction: 7,
+        }
+    );
+
+    // The number of parts is limited.
+    match math.stretch_glyph(GlyphId(1), Direction::TopToBottom, i32::MAX) {
+        StretchedGlyph::Assembly { parts, size, .. } => {
+            assert_eq!(parts.len(), 1024);
+            assert_eq!(size, 200 + 1022 * 100 - 1023 * 10);
+        }
+        StretchedGlyph::Variant(_) => panic!("expected an assembly"),
+    }
+}
+
+#[test]
+fn stretch_to_oversized_target() {
+    let face = test_math_face();
+    let math = face.math().unwrap();
+
+    // A 65000 units extender repeated up to the part limit.
+    match math.stretch_glyph(GlyphId(2), Direction::TopToBottom, i32::MAX) {
+        StretchedGlyph::Assembly { parts, size, .. } => {
+            assert_eq!(parts.len(), 1024);
+            assert_eq!(size, 1024 * 65000 - 1023 * 10);
+        }
+        StretchedGlyph::Variant(_) => panic!("expected an assembly"),
+    }
+
+    // Assemblies with too many parts are not used.
+    assert!(matches!(
+        math.stretch_glyph(GlyphId(3), Direction::TopToBottom, 1000),
+        StretchedGlyph::Variant(GlyphVariant {
+            glyph: GlyphId(3),
+            ..
+        })
+    ));
+}
@@ -0,0 +1,69 @@
+// tests for shape_paragraph
+
+use rustybuzz::{script, Direction, SerializeFlags};
+
+fn shape_paragraph(text: &str, base_direction: Direction) -> Vec<String> {
+    let data = include_bytes!("../fonts/text-rendering-tests/FDArrayTest257.otf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    rustybuzz::shape_paragraph(&face, text, base_direction, &[])
+        .iter()
+        .map(|run| {
+            format!(
+                "{:?} {} {} {}",
+                run.range,
+                run.level,
+                run.script.tag(),
+                run.glyphs.serialize(&face, SerializeFlags::NO_POSITIONS)
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn paragraph_ltr() {
+    assert_eq!(
+        shape_paragraph("abc בג.", Direction::Invalid),
+        [
+            "0..4 0 Latn gid98=0|gid99=1|gid100=2|gid33=3",
+            "4..8 1 Hebr gid211=6|gid210=4",
+            "8..9 0 Hebr gid47=8",
+        ]
+    );
+}
+
+#[test]
+fn paragraph_rtl() {
+    assert_eq!(
+        shape_paragraph("abc בג.", Direction::RightToLeft),
+        [
+            "4..9 1 Hebr gid47=8|gid211=6|gid210=4",
+            "3..4 1 Latn gid33=3",
+            "0..3 2 Latn gid98=0|gid99=1|gid100=2",
+        ]
+    );
+
+    // Detected from the first strong character.
+    assert_eq!(
+        shape_paragraph("שלום abc (دب)!", Direction::Invalid),
+        [
+            "18..20 1 Latn gid34=19|gid41=18",
+            "14..18 1 Arab gid144=16|gid170=14",
+            "12..14 1 Latn gid42=13|gid33=12",
+            "9..12 2 Latn gid98=9|gid99=10|gid100=11",
+            "0..9 1 Hebr gid33=8|gid222=6|gid214=4|gid221=2|gid234=0",
+        ]
+    );
+}
+
+#[test]
+fn paragraph_runs() {
+    let data = include_bytes!("../fonts/text-rendering-tests/FDArrayTest257.otf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let runs = rustybuzz::shape_paragraph(&face, "ab בג", Direction::Invalid, &[]);
+    assert_eq!(runs.len(), 2);
+    assert_eq!(runs[0].direction(), Direction::LeftToRight);
+    assert_eq!(runs[1].direction(), Direction::RightToLeft);
+    assert_eq!(runs[1].script, script::HEBREW);
+
+    assert!(rustybuzz::shape_paragraph(&face, "", Direction::Invalid, &[]).is_empty());
+}
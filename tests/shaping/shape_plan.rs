@@ -0,0 +1,488 @@
+// tests for shape plan reuse
+
+use rustybuzz::ttf_parser::Tag;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use rustybuzz::{
+    Direction, HookBuffer, Language, NormalizationMode, PlanLookup, ShapeHooks, ShapePlan,
+    TableIndex, UnicodeBuffer, Variation,
+};
+
+fn shape_r(face: &rustybuzz::Face, plan: &ShapePlan) -> String {
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str("r");
+    let glyphs = rustybuzz::shape_with_plan(face, plan, buffer);
+    let glyph_id = glyphs.glyph_infos()[0].glyph_id as u16;
+    face.glyph_name(rustybuzz::ttf_parser::GlyphId(glyph_id))
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn plan_with_mismatched_feature_variations() {
+    let data = include_bytes!("../fonts/in-house/d23d76ea0909c14972796937ba072b5a40c1e257.ttf");
+    let mut face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let fvtt = Tag::from_bytes(b"FVTT");
+
+    face.set_variations(&[Variation {
+        tag: fvtt,
+        value: 1.0,
+    }]);
+    let plan = ShapePlan::new(
+        &face,
+        Direction::LeftToRight,
+        Some(rustybuzz::script::LATIN),
+        None,
+        &[],
+    );
+    assert!(plan.matches_variations(&face));
+    assert_eq!(shape_r(&face, &plan), "rvrn_base");
+
+    face.set_variations(&[Variation {
+        tag: fvtt,
+        value: 491.0,
+    }]);
+    assert!(!plan.matches_variations(&face));
+    assert_eq!(shape_r(&face, &plan), "rvrn_subst");
+}
+
+#[test]
+fn cached_plans() {
+    let data = include_bytes!("../fonts/in-house/d23d76ea0909c14972796937ba072b5a40c1e257.ttf");
+    let mut face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let fvtt = Tag::from_bytes(b"FVTT");
+    let cache = rustybuzz::ShapePlanCache::new(2);
+
+    let shape = |face: &rustybuzz::Face, text: &str| {
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(text);
+        let glyphs = rustybuzz::shape_cached(face, &cache, &[], buffer);
+        let glyph_id = glyphs.glyph_infos()[0].glyph_id as u16;
+        face.glyph_name(rustybuzz::ttf_parser::GlyphId(glyph_id))
+            .unwrap()
+            .to_string()
+    };
+
+    assert_eq!(shape(&face, "r"), "rvrn_base");
+    assert_eq!(shape(&face, "rr"), "rvrn_base");
+    assert_eq!(cache.len(), 1);
+
+    // Coordinates selecting the same records share a plan.
+    face.set_variations(&[Variation {
+        tag: fvtt,
+        value: 1.0,
+    }]);
+    assert_eq!(shape(&face, "r"), "rvrn_base");
+    assert_eq!(cache.len(), 1);
+
+    face.set_variations(&[Variation {
+        tag: fvtt,
+        value: 491.0,
+    }]);
+    assert_eq!(shape(&face, "r"), "rvrn_subst");
+    assert_eq!(cache.len(), 2);
+
+    // The least recently used plan is evicted.
+    let _ = cache.get(
+        &face,
+        Direction::RightToLeft,
+        Some(rustybuzz::script::LATIN),
+        None,
+        &[],
+    );
+    assert_eq!(cache.len(), 2);
+
+    cache.clear();
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn plan_introspection() {
+    let data = include_bytes!("../fonts/in-house/872d2955d326bd6676a06f66b8238ebbaabc212f.ttf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let plan = ShapePlan::new(
+        &face,
+        Direction::RightToLeft,
+        Some(rustybuzz::script::ARABIC),
+        None,
+        &[],
+    );
+
+    assert_eq!(plan.shaper_name(), "arabic");
+
+    let arab = Some(Tag::from_bytes(b"arab"));
+    assert_eq!(plan.script_tag(TableIndex::GSUB), arab);
+    assert_eq!(plan.script_tag(TableIndex::GPOS), arab);
+    assert!(plan.found_script(TableIndex::GSUB));
+    assert_eq!(plan.language_tag(TableIndex::GSUB), None);
+
+    let tags = |s: &[&[u8; 4]]| s.iter().map(|t| Tag::from_bytes(t)).collect::<Vec<_>>();
+    assert_eq!(
+        plan.found_features(TableIndex::GSUB).collect::<Vec<_>>(),
+        tags(&[b"calt", b"fina", b"init", b"medi"])
+    );
+    assert_eq!(
+        plan.found_features(TableIndex::GPOS).collect::<Vec<_>>(),
+        tags(&[b"curs", b"kern", b"mark"])
+    );
+
+    // Arabic features are applied one stage at a time, in joining order.
+    let stages: Vec<_> = plan
+        .stages(TableIndex::GSUB)
+        .into_iter()
+        .filter(|stage| !stage.is_empty())
+        .map(|stage| stage[0].clone())
+        .collect();
+    assert_eq!(
+        stages,
+        vec![
+            PlanLookup {
+                index: 2,
+                features: tags(&[b"fina"])
+            },
+            PlanLookup {
+                index: 1,
+                features: tags(&[b"medi"])
+            },
+            PlanLookup {
+                index: 0,
+                features: tags(&[b"init"])
+            },
+            PlanLookup {
+                index: 6,
+                features: tags(&[b"calt"])
+            },
+        ]
+    );
+
+    assert_eq!(
+        plan.lookups(TableIndex::GSUB),
+        [0, 1, 2, 6, 8, 10, 13, 15, 17, 19, 21, 23, 24, 26, 27]
+    );
+    assert_eq!(plan.lookups(TableIndex::GPOS), [1, 3, 4]);
+}
+
+#[test]
+fn plan_introspection_fallback_script() {
+    let data = include_bytes!("../fonts/in-house/872d2955d326bd6676a06f66b8238ebbaabc212f.ttf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let plan = ShapePlan::new(
+        &face,
+        Direction::LeftToRight,
+        Some(rustybuzz::script::GREEK),
+        None,
+        &[],
+    );
+
+    assert_eq!(plan.shaper_name(), "default");
+    assert_eq!(
+        plan.script_tag(TableIndex::GSUB),
+        Some(Tag::from_bytes(b"DFLT"))
+    );
+    assert!(!plan.found_script(TableIndex::GSUB));
+    assert!(!plan.lookups(TableIndex::GSUB).contains(&0));
+}
+
+#[test]
+fn plan_introspection_language() {
+    let data = include_bytes!("../fonts/in-house/6991b13ce889466be6de3f66e891de2bc0f117ee.ttf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let language = Language::from_str("zh-Hant").unwrap();
+    let plan = ShapePlan::new(
+        &face,
+        Direction::LeftToRight,
+        Some(rustybuzz::script::HAN),
+        Some(&language),
+        &[],
+    );
+
+    assert_eq!(
+        plan.script_tag(TableIndex::GSUB),
+        Some(Tag::from_bytes(b"hani"))
+    );
+    assert_eq!(
+        plan.language_tag(TableIndex::GSUB),
+        Some(Tag::from_bytes(b"ZHT "))
+    );
+    assert_eq!(
+        plan.found_features(TableIndex::GSUB).collect::<Vec<_>>(),
+        [Tag::from_bytes(b"locl")]
+    );
+}
+
+#[test]
+fn plan_normalization_mode() {
+    let data = include_bytes!("../fonts/rb_custom/PT_Sans-Caption-Web-Regular.ttf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let mut plan = ShapePlan::new(
+        &face,
+        Direction::LeftToRight,
+        Some(rustybuzz::script::LATIN),
+        None,
+        &[],
+    );
+
+    let shape = |plan: &ShapePlan, text: &str| {
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.set_direction(Direction::LeftToRight);
+        buffer.set_script(rustybuzz::script::LATIN);
+        let glyphs = rustybuzz::shape_with_plan(&face, plan, buffer);
+        glyphs
+            .glyph_infos()
+            .iter()
+            .map(|info| info.glyph_id)
+            .collect::<Vec<_>>()
+    };
+
+    let composed = shape(&plan, "\u{00E9}");
+    let decomposed = shape(&plan, "e\u{0301}");
+    assert_eq!(composed.len(), 1);
+    assert_eq!(decomposed, composed);
+    assert_eq!(plan.normalization_mode(), None);
+
+    plan.set_normalization_mode(Some(NormalizationMode::Decomposed));
+    let e = shape(&plan, "e");
+    let acute = shape(&plan, "\u{0301}");
+    assert_eq!(shape(&plan, "\u{00E9}"), [e[0], acute[0]]);
+
+    plan.set_normalization_mode(Some(NormalizationMode::None));
+    assert_eq!(shape(&plan, "\u{00E9}"), composed);
+    assert_eq!(shape(&plan, "e\u{0301}"), [e[0], acute[0]]);
+
+    plan.set_normalization_mode(None);
+    assert_eq!(shape(&plan, "e\u{0301}"), composed);
+}
+
+#[test]
+fn plan_normalization_ascii() {
+    let data = include_bytes!("../fonts/rb_custom/PT_Sans-Caption-Web-Regular.ttf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+
+    let shape = |mode: Option<NormalizationMode>| {
+        let mut plan = ShapePlan::new(
+            &face,
+            Direction::LeftToRight,
+            Some(rustybuzz::script::LATIN),
+            None,
+            &[],
+        );
+        plan.set_normalization_mode(mode);
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str("Hello, world!");
+        buffer.set_direction(Direction::LeftToRight);
+        buffer.set_script(rustybuzz::script::LATIN);
+        let glyphs = rustybuzz::shape_with_plan(&face, &plan, buffer);
+        glyphs
+            .glyph_infos()
+            .iter()
+            .map(|info| (info.glyph_id, info.cluster))
+            .collect::<Vec<_>>()
+    };
+
+    // ASCII text never changes under normalization.
+    let expected = shape(None);
+    assert_eq!(expected.len(), 13);
+    for mode in [
+        NormalizationMode::None,
+        NormalizationMode::Decomposed,
+        NormalizationMode::ComposedDiacritics,
+        NormalizationMode::ComposedDiacriticsNoShortCircuit,
+        NormalizationMode::Auto,
+    ] {
+        assert_eq!(shape(Some(mode)), expected, "{:?}", mode);
+    }
+}
+
+#[test]
+fn plan_normalization_fast_path() {
+    let data = include_bytes!("../fonts/rb_custom/PT_Sans-Caption-Web-Regular.ttf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+
+    let shape = |mode: Option<NormalizationMode>, text: &str| {
+        let mut plan = ShapePlan::new(
+            &face,
+            Direction::LeftToRight,
+            Some(rustybuzz::script::LATIN),
+            None,
+            &[],
+        );
+        plan.set_normalization_mode(mode);
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.set_direction(Direction::LeftToRight);
+        buffer.set_script(rustybuzz::script::LATIN);
+        let glyphs = rustybuzz::shape_with_plan(&face, &plan, buffer);
+        glyphs
+            .glyph_infos()
+            .iter()
+            .map(|info| (info.glyph_id, info.cluster))
+            .collect::<Vec<_>>()
+    };
+
+    // Text without marks takes the fast path. A trailing mark in its own cluster
+    // forces the full path without changing the glyphs before it.
+    for text in [
+        "Hello, world!",
+        "\u{00C5}ngstr\u{00F6}m \u{2126} \u{212B} \u{FB01}",
+    ] {
+        for mode in [
+            None,
+            Some(NormalizationMode::None),
+            Some(NormalizationMode::Decomposed),
+            Some(NormalizationMode::ComposedDiacritics),
+            Some(NormalizationMode::ComposedDiacriticsNoShortCircuit),
+        ] {
+            let fast = shape(mode, text);
+            let full = shape(mode, &format!("{text} \u{0301}"));
+            assert_eq!(fast, full[..fast.len()], "{:?} {:?}", text, mode);
+        }
+    }
+}
+
+#[derive(Default)]
+struct RecordingHooks {
+    stages: Mutex<Vec<(&'static str, Vec<u32>, usize)>>,
+}
+
+impl RecordingHooks {
+    fn record(&self, stage: &'static str, buffer: &HookBuffer) {
+        let glyphs = buffer
+            .glyph_infos()
+            .iter()
+            .map(|info| info.glyph_id)
+            .collect();
+        let positions = buffer.glyph_positions().len();
+        self.stages.lock().unwrap().push((stage, glyphs, positions));
+    }
+}
+
+impl ShapeHooks for RecordingHooks {
+    fn pre_normalize(&self, _: &rustybuzz::Face, buffer: &mut HookBuffer) {
+        self.record("pre_normalize", buffer);
+        // Force the second character before the first one.
+        if buffer.len() >= 2 {
+            buffer.swap(0, 1);
+        }
+    }
+
+    fn post_substitute(&self, _: &rustybuzz::Face, buffer: &mut HookBuffer) {
+        self.record("post_substitute", buffer);
+    }
+
+    fn post_position(&self, _: &rustybuzz::Face, buffer: &mut HookBuffer) {
+        self.record("post_position", buffer);
+        for pos in buffer.glyph_positions_mut() {
+            pos.x_advance = 1000;
+        }
+    }
+}
+
+#[test]
+fn plan_hooks() {
+    let data = include_bytes!("../fonts/rb_custom/PT_Sans-Caption-Web-Regular.ttf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let mut plan = ShapePlan::new(
+        &face,
+        Direction::LeftToRight,
+        Some(rustybuzz::script::LATIN),
+        None,
+        &[],
+    );
+
+    let shape = |plan: &ShapePlan, text: &str| {
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.set_direction(Direction::LeftToRight);
+        buffer.set_script(rustybuzz::script::LATIN);
+        rustybuzz::shape_with_plan(&face, plan, buffer)
+    };
+
+    let reference = shape(&plan, "ba");
+    let reference = reference
+        .glyph_infos()
+        .iter()
+        .map(|info| info.glyph_id)
+        .collect::<Vec<_>>();
+
+    let hooks = Arc::new(RecordingHooks::default());
+    plan.set_hooks(Some(hooks.clone()));
+    assert!(plan.hooks().is_some());
+
+    let glyphs = shape(&plan, "ab");
+    let ids = glyphs
+        .glyph_infos()
+        .iter()
+        .map(|info| info.glyph_id)
+        .collect::<Vec<_>>();
+    assert_eq!(ids, reference);
+    assert!(glyphs
+        .glyph_positions()
+        .iter()
+        .all(|pos| pos.x_advance == 1000));
+
+    let stages = hooks.stages.lock().unwrap();
+    assert_eq!(
+        *stages,
+        [
+            ("pre_normalize", vec!['a' as u32, 'b' as u32], 0),
+            ("post_substitute", reference.clone(), 0),
+            ("post_position", reference.clone(), 2),
+        ]
+    );
+    drop(stages);
+
+    plan.set_hooks(None);
+    assert!(shape(&plan, "ab")
+        .glyph_positions()
+        .iter()
+        .all(|pos| pos.x_advance != 1000));
+}
+
+struct InvalidValueHooks;
+
+impl ShapeHooks for InvalidValueHooks {
+    fn pre_normalize(&self, _: &rustybuzz::Face, buffer: &mut HookBuffer) {
+        let infos = buffer.glyph_infos_mut();
+        infos[0].glyph_id = 0xD800;
+        infos[1].glyph_id = 0x11_0000;
+    }
+
+    fn post_substitute(&self, _: &rustybuzz::Face, buffer: &mut HookBuffer) {
+        buffer.glyph_infos_mut()[2].glyph_id = 0x1_0000;
+    }
+
+    fn post_position(&self, _: &rustybuzz::Face, buffer: &mut HookBuffer) {
+        buffer.glyph_infos_mut()[3].glyph_id = u32::MAX;
+    }
+}
+
+#[test]
+fn plan_hooks_invalid_values() {
+    let data = include_bytes!("../fonts/rb_custom/PT_Sans-Caption-Web-Regular.ttf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let mut plan = ShapePlan::new(
+        &face,
+        Direction::LeftToRight,
+        Some(rustybuzz::script::LATIN),
+        None,
+        &[],
+    );
+    plan.set_hooks(Some(Arc::new(InvalidValueHooks)));
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str("abcde");
+    buffer.set_direction(Direction::LeftToRight);
+    buffer.set_script(rustybuzz::script::LATIN);
+    let glyphs = rustybuzz::shape_with_plan(&face, &plan, buffer);
+    let ids = glyphs
+        .glyph_infos()
+        .iter()
+        .map(|info| info.glyph_id)
+        .collect::<Vec<_>>();
+
+    let replacement = face.glyph_index('\u{FFFD}').map_or(0, |g| u32::from(g.0));
+    let e = u32::from(face.glyph_index('e').unwrap().0);
+    assert_eq!(ids, [replacement, replacement, 0, 0, e]);
+}
@@ -0,0 +1,92 @@
+// tests for letter and word spacing
+
+use rustybuzz::{Direction, Feature, SerializeFlags, ShapePlan, UnicodeBuffer};
+
+fn shape_spaced(
+    text: &str,
+    letter_spacing: i32,
+    word_spacing: i32,
+    features: &[Feature],
+) -> String {
+    let data = include_bytes!("../fonts/rb_custom/PT_Sans-Caption-Web-Regular.ttf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.set_letter_spacing(letter_spacing);
+    buffer.set_word_spacing(word_spacing);
+    let glyphs = rustybuzz::shape(&face, features, buffer);
+    glyphs.serialize(&face, SerializeFlags::default())
+}
+
+#[test]
+fn letter_spacing() {
+    assert_eq!(
+        shape_spaced("fi a", 0, 0, &[]),
+        "fi=0+643|space=2+291|a=3+582"
+    );
+
+    // Optional ligatures are disabled.
+    assert_eq!(
+        shape_spaced("fi a", 100, 0, &[]),
+        "f=0@50,0+464|i=1@50,0+417|space=2@50,0+391|a=3@50,0+682"
+    );
+
+    // Unless requested explicitly.
+    assert_eq!(
+        shape_spaced("fi a", 100, 0, &["liga".parse().unwrap()]),
+        "fi=0@50,0+743|space=2@50,0+391|a=3@50,0+682"
+    );
+}
+
+#[test]
+fn letter_spacing_keeps_marks_with_base() {
+    assert_eq!(
+        shape_spaced("x\u{0301}y", 0, 0, &[]),
+        "x=0+592|acutecomb=0+0|y=3+531"
+    );
+    assert_eq!(
+        shape_spaced("x\u{0301}y", 100, 0, &[]),
+        "x=0@50,0+692|acutecomb=0@-50,0+0|y=3@50,0+631"
+    );
+}
+
+#[test]
+fn word_spacing() {
+    assert_eq!(
+        shape_spaced("fi a", 0, 50, &[]),
+        "fi=0+643|space=2+341|a=3+582"
+    );
+    assert_eq!(
+        shape_spaced("fi a", 100, 50, &[]),
+        "f=0@50,0+464|i=1@50,0+417|space=2@50,0+441|a=3@50,0+682"
+    );
+}
+
+#[test]
+fn letter_spacing_with_plan() {
+    let data = include_bytes!("../fonts/rb_custom/PT_Sans-Caption-Web-Regular.ttf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let shape = |features: &[Feature]| {
+        let plan = ShapePlan::new(
+            &face,
+            Direction::LeftToRight,
+            Some(rustybuzz::script::LATIN),
+            None,
+            features,
+        );
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str("fi a");
+        buffer.set_direction(Direction::LeftToRight);
+        buffer.set_script(rustybuzz::script::LATIN);
+        buffer.set_letter_spacing(100);
+        let glyphs = rustybuzz::shape_with_plan(&face, &plan, buffer);
+        glyphs.serialize(&face, SerializeFlags::default())
+    };
+
+    // The features of the plan are used as is.
+    assert_eq!(shape(&[]), "fi=0@50,0+743|space=2@50,0+391|a=3@50,0+682");
+    assert_eq!(
+        shape(&["-liga".parse().unwrap()]),
+        "f=0@50,0+464|i=1@50,0+417|space=2@50,0+391|a=3@50,0+682"
+    );
+}
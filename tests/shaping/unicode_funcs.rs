@@ -0,0 +1,130 @@
+// tests for custom Unicode character properties
+
+use std::sync::Arc;
+
+use rustybuzz::{
+    BuiltinUnicodeFuncs, Direction, GeneralCategory, Script, UnicodeBuffer, UnicodeFuncs,
+};
+
+struct CustomFuncs;
+
+impl UnicodeFuncs for CustomFuncs {
+    fn general_category(&self, c: char) -> GeneralCategory {
+        match c {
+            '\u{00E9}' | '^' => GeneralCategory::NonspacingMark,
+            _ => BuiltinUnicodeFuncs.general_category(c),
+        }
+    }
+
+    fn combining_class(&self, c: char) -> u8 {
+        match c {
+            '\u{00E9}' => 230,
+            '^' => 220,
+            _ => BuiltinUnicodeFuncs.combining_class(c),
+        }
+    }
+
+    fn mirroring(&self, _: char) -> Option<char> {
+        None
+    }
+
+    fn script(&self, c: char) -> Script {
+        match c {
+            'h' => rustybuzz::script::HEBREW,
+            _ => BuiltinUnicodeFuncs.script(c),
+        }
+    }
+
+    fn decompose(&self, ab: char) -> Option<(char, char)> {
+        match ab {
+            '\u{E000}' => Some(('a', 'c')),
+            _ => BuiltinUnicodeFuncs.decompose(ab),
+        }
+    }
+}
+
+fn shape(text: &str, funcs: bool, direction: Option<Direction>) -> Vec<(u32, u32)> {
+    let data = include_bytes!("../fonts/rb_custom/PT_Sans-Caption-Web-Regular.ttf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    if let Some(direction) = direction {
+        buffer.set_direction(direction);
+    }
+    if funcs {
+        buffer.set_unicode_funcs(Some(Arc::new(CustomFuncs)));
+    }
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    glyphs
+        .glyph_infos()
+        .iter()
+        .map(|info| (info.glyph_id, info.cluster))
+        .collect()
+}
+
+#[test]
+fn custom_general_category() {
+    // U+00E9 becomes a mark, so it joins the cluster of 'a'.
+    let builtin = shape("a\u{00E9}", false, None);
+    assert_eq!(builtin.iter().map(|g| g.1).collect::<Vec<_>>(), [0, 1]);
+    let custom = shape("a\u{00E9}", true, None);
+    assert_eq!(custom.iter().map(|g| g.1).collect::<Vec<_>>(), [0, 0]);
+    assert_eq!(
+        custom.iter().map(|g| g.0).collect::<Vec<_>>(),
+        builtin.iter().map(|g| g.0).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn custom_ascii_mark() {
+    // '^' becomes a mark with a lower combining class than U+0301, so it joins
+    // the cluster of 'x' and is reordered before U+0301.
+    let builtin = shape("x\u{0301}^", false, None);
+    assert_eq!(builtin.iter().map(|g| g.1).collect::<Vec<_>>(), [0, 0, 3]);
+    let custom = shape("x\u{0301}^", true, None);
+    assert_eq!(custom.iter().map(|g| g.1).collect::<Vec<_>>(), [0, 0, 0]);
+    assert_eq!(
+        custom.iter().map(|g| g.0).collect::<Vec<_>>(),
+        [builtin[0].0, builtin[2].0, builtin[1].0]
+    );
+}
+
+#[test]
+fn custom_mirroring() {
+    let builtin = shape("(", false, Some(Direction::RightToLeft));
+    let custom = shape("(", true, Some(Direction::RightToLeft));
+    assert_eq!(builtin, shape(")", false, None));
+    assert_eq!(custom, shape("(", false, None));
+}
+
+#[test]
+fn custom_script() {
+    // The buffer script is guessed from 'h', which makes the buffer right-to-left.
+    let custom = shape("hi", true, None);
+    assert_eq!(custom.iter().map(|g| g.1).collect::<Vec<_>>(), [1, 0]);
+    let builtin = shape("hi", false, None);
+    assert_eq!(builtin.iter().map(|g| g.1).collect::<Vec<_>>(), [0, 1]);
+}
+
+#[test]
+fn custom_decomposition() {
+    let custom = shape("\u{E000}", true, None);
+    let expected = shape("ac", false, None)
+        .into_iter()
+        .map(|(glyph, _)| (glyph, 0))
+        .collect::<Vec<_>>();
+    assert_eq!(custom, expected);
+
+    let builtin = shape("\u{E000}", false, None);
+    assert_eq!(builtin, [(0, 0)]);
+}
+
+#[test]
+fn unicode_funcs_are_kept_until_cleared() {
+    let mut buffer = UnicodeBuffer::new();
+    assert!(buffer.unicode_funcs().is_none());
+    buffer.set_unicode_funcs(Some(Arc::new(CustomFuncs)));
+    assert!(buffer.unicode_funcs().is_some());
+    buffer.clear();
+    assert!(buffer.unicode_funcs().is_none());
+}
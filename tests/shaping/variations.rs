@@ -0,0 +1,139 @@
+// tests for the variable fonts API
+
+use rustybuzz::ttf_parser::{NormalizedCoordinate, Tag};
+use rustybuzz::Variation;
+
+#[test]
+fn named_instances() {
+    let data = include_bytes!("../fonts/rb_custom/OpenSans.subset1.ttf");
+    let face = rustybuzz::Face::from_slice(data, 0).unwrap();
+
+    let instances = face.named_instances();
+    assert_eq!(instances.len(), 10);
+
+    let instance = &instances[7];
+    assert_eq!(instance.subfamily_name_id, 267);
+    assert_eq!(instance.postscript_name_id, Some(277));
+    assert_eq!(
+        instance.coordinates,
+        vec![
+            Variation {
+                tag: Tag::from_bytes(b"wght"),
+                value: 600.0
+            },
+            Variation {
+                tag: Tag::from_bytes(b"wdth"),
+                value: 75.0
+            },
+        ]
+    );
+    assert_eq!(instance.axis_value_name_ids, vec![280, 261]);
+}
+
+#[test]
+fn set_named_instance() {
+    let data = include_bytes!("../fonts/rb_custom/OpenSans.subset1.ttf");
+    let mut face = rustybuzz::Face::from_slice(data, 0).unwrap();
+
+    assert!(face.set_named_instance(3).is_some());
+    assert_eq!(
+        face.variation_coordinates(),
+        &[
+            NormalizedCoordinate::from(10412),
+            NormalizedCoordinate::from(0)
+        ]
+    );
+
+    assert!(face.set_named_instance(10).is_none());
+}
+
+#[test]
+fn set_variation_coordinates_with_avar() {
+    let data = include_bytes!("../fonts/rb_custom/OpenSans.subset1.ttf");
+    let mut face = rustybuzz::Face::from_slice(data, 0).unwrap();
+
+    for value in [-16384, -9000, -1, 0, 1, 123, 10412, 16384] {
+        let coords = [
+            NormalizedCoordinate::from(value),
+            NormalizedCoordinate::from(0),
+        ];
+        face.set_variation_coordinates(&coords);
+        assert_eq!(face.variation_coordinates(), &coords);
+    }
+}
+
+#[test]
+fn automatic_optical_sizing() {
+    let data = include_bytes!("../fonts/text-rendering-tests/TestCVARGVAROne.ttf");
+    let mut face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let opsz = |face: &rustybuzz::Face| face.variation_coordinates()[2].get();
+
+    // Disabled by default.
+    face.set_points_per_em(Some(42.0));
+    assert_eq!(opsz(&face), 0);
+
+    face.set_optical_sizing(true);
+    assert_eq!(opsz(&face), 8192);
+
+    // Clamped to the axis range.
+    face.set_points_per_em(Some(144.0));
+    assert_eq!(opsz(&face), 16384);
+
+    face.set_optical_sizing(false);
+    assert_eq!(opsz(&face), 0);
+}
+
+#[test]
+fn explicit_optical_size() {
+    let data = include_bytes!("../fonts/text-rendering-tests/TestCVARGVAROne.ttf");
+    let mut face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let opsz = |face: &rustybuzz::Face| face.variation_coordinates()[2].get();
+
+    face.set_optical_sizing(true);
+    face.set_variations(&[Variation {
+        tag: Tag::from_bytes(b"opsz"),
+        value: 12.0,
+    }]);
+    face.set_points_per_em(Some(72.0));
+    assert_eq!(opsz(&face), 0);
+}
+
+#[test]
+fn explicit_optical_size_reset() {
+    let data = include_bytes!("../fonts/text-rendering-tests/TestCVARGVAROne.ttf");
+    let mut face = rustybuzz::Face::from_slice(data, 0).unwrap();
+    let opsz = |face: &rustybuzz::Face| face.variation_coordinates()[2].get();
+    let set_explicit_opsz = |face: &mut rustybuzz::Face| {
+        face.set_variations(&[Variation {
+            tag: Tag::from_bytes(b"opsz"),
+            value: 12.0,
+        }]);
+        assert_eq!(opsz(face), 0);
+    };
+
+    face.set_points_per_em(Some(42.0));
+    face.set_optical_sizing(true);
+
+    // Enabling automatic optical sizing again.
+    set_explicit_opsz(&mut face);
+    face.set_optical_sizing(true);
+    assert_eq!(opsz(&face), 8192);
+
+    // Resetting all coordinates.
+    set_explicit_opsz(&mut face);
+    face.set_variation_coordinates(&[]);
+    assert_eq!(opsz(&face), 8192);
+
+    set_explicit_opsz(&mut face);
+    assert!(face.set_named_instance(0).is_some());
+    assert_eq!(opsz(&face), 8192);
+
+    // Other variations keep the explicit value.
+    set_explicit_opsz(&mut face);
+    face.set_variations(&[Variation {
+        tag: Tag::from_bytes(b"wght"),
+        value: 150.0,
+    }]);
+    face.set_points_per_em(Some(72.0));
+    assert_eq!(opsz(&face), 0);
+}
@@ -28,6 +28,88 @@ fn calculator() {
     assert_eq!(expected, res);
 }
 
+#[test]
+fn calculator_reuses_face() {
+    // The compiled module is cached on the face, but each call starts with fresh guest memory.
+    let calculator_font = include_bytes!("../fonts/text-rendering-tests/Calculator-Regular.ttf");
+    let face = rustybuzz::Face::from_slice(calculator_font, 0).unwrap();
+
+    let shape = |text: &str| {
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(text);
+        rustybuzz::shape(&face, &[], buffer)
+            .glyph_infos()
+            .iter()
+            .map(|i| i.glyph_id)
+            .collect::<Vec<_>>()
+    };
+
+    // glyphids for 3.142857
+    let expected = vec![20, 15, 18, 21, 19, 25, 22, 24];
+
+    assert_eq!(expected, shape("22/7="));
+    // glyphids for 1.5
+    assert_eq!(vec![18, 15, 22], shape("3/2="));
+    assert_eq!(expected, shape("22/7="));
+}
+
+fn shape_calculator(limits: rustybuzz::WasmLimits) -> Vec<u32> {
+    let calculator_font = include_bytes!("../fonts/text-rendering-tests/Calculator-Regular.ttf");
+    let mut face = rustybuzz::Face::from_slice(calculator_font, 0).unwrap();
+    face.set_wasm_limits(limits);
+
+    let mut buffer = rustybuzz::UnicodeBuffer::new();
+    buffer.push_str("22/7=");
+
+    rustybuzz::shape(&face, &[], buffer)
+        .glyph_infos()
+        .iter()
+        .map(|i| i.glyph_id)
+        .collect()
+}
+
+fn calculator_unshaped() -> Vec<u32> {
+    let calculator_font = include_bytes!("../fonts/text-rendering-tests/Calculator-Regular.ttf");
+    let face = rustybuzz::Face::from_slice(calculator_font, 0).unwrap();
+    "22/7="
+        .chars()
+        .map(|c| u32::from(face.glyph_index(c).unwrap().0))
+        .collect()
+}
+
+#[test]
+fn calculator_out_of_fuel() {
+    // Falls back to the OpenType shaper, which leaves the text as is.
+    let limits = rustybuzz::WasmLimits {
+        fuel: Some(1000),
+        ..Default::default()
+    };
+    assert_eq!(calculator_unshaped(), shape_calculator(limits));
+}
+
+#[test]
+fn calculator_out_of_memory() {
+    // The module asks for more than a single page of memory.
+    let limits = rustybuzz::WasmLimits {
+        max_memory: Some(0x10000),
+        ..Default::default()
+    };
+    assert_eq!(calculator_unshaped(), shape_calculator(limits));
+}
+
+#[test]
+fn calculator_unlimited() {
+    let limits = rustybuzz::WasmLimits {
+        fuel: None,
+        max_memory: None,
+    };
+    // glyphids for 3.142857
+    assert_eq!(
+        vec![20, 15, 18, 21, 19, 25, 22, 24],
+        shape_calculator(limits)
+    );
+}
+
 #[test]
 fn ruqaa_final_period() {
     // here are the wasm functions imported in this font:
@@ -172,3 +254,42 @@ fn ruqaa_no_final_period() {
         assert_eq!(expected, res);
     }
 }
+
+// `TestWasm-*.ttf` are `TestMORXOne.ttf` with `morx` replaced by a hand-written `Wasm` table.
+// `TestWasm-HostileMemory.ttf` calls `font_glyph_to_string` with a zero length and
+// `buffer_set_contents` with out of bounds arrays, then with a single misaligned glyph 3.
+// `TestWasm-NoMemory.ttf` has no memory export and calls `buffer_set_contents` from its
+// start function.
+
+fn shape_glyphs(font: &str, text: &str) -> Vec<u32> {
+    let data = std::fs::read(font).unwrap();
+    let face = rustybuzz::Face::from_slice(&data, 0).unwrap();
+    let mut buffer = rustybuzz::UnicodeBuffer::new();
+    buffer.push_str(text);
+    rustybuzz::shape(&face, &[], buffer)
+        .glyph_infos()
+        .iter()
+        .map(|i| i.glyph_id)
+        .collect()
+}
+
+#[test]
+fn hostile_memory_access() {
+    assert_eq!(
+        vec![3],
+        shape_glyphs("tests/fonts/rb_custom/TestWasm-HostileMemory.ttf", "ABC")
+    );
+}
+
+#[test]
+fn no_memory_export() {
+    // Falls back to the OpenType shaper, which leaves the text as is.
+    let font = "tests/fonts/rb_custom/TestWasm-NoMemory.ttf";
+    let data = std::fs::read(font).unwrap();
+    let face = rustybuzz::Face::from_slice(&data, 0).unwrap();
+    let unshaped: Vec<u32> = "ABC"
+        .chars()
+        .map(|c| u32::from(face.glyph_index(c).unwrap().0))
+        .collect();
+    assert_eq!(unshaped, shape_glyphs(font, "ABC"));
+}